@@ -1,4 +1,7 @@
 use clap::{Parser, Subcommand};
+use std::path::PathBuf;
+
+use crate::dotfiles::ConflictPolicy;
 
 /// Developer Workspace - Personal development workspace manager
 ///
@@ -13,6 +16,21 @@ pub struct Cli {
     #[arg(short, long, global = true)]
     pub verbose: bool,
 
+    /// How to handle existing files that dws does not manage when linking dotfiles
+    /// (overrides `[dotfiles] on_conflict`)
+    #[arg(long, global = true, value_enum, value_name = "POLICY")]
+    pub on_conflict: Option<ConflictPolicy>,
+
+    /// Print what init, use, sync, update or reset would change, grouped by action, and exit
+    /// without modifying anything
+    #[arg(long, global = true)]
+    pub dry_run: bool,
+
+    /// Seconds to wait for another running dws process before giving up
+    /// (overrides `[lock] timeout`; 0 fails immediately)
+    #[arg(long, global = true, value_name = "SECONDS")]
+    pub lock_timeout: Option<u64>,
+
     #[command(subcommand)]
     pub command: Commands,
 }
@@ -78,14 +96,117 @@ pub enum Commands {
     /// Show workspace status
     Status,
 
+    /// Fix links and tool files that drifted from the lockfile
+    ///
+    /// Recreates missing or misdirected links, unpacks tools whose files are
+    /// gone from the cached archive (downloading it only if that is gone too)
+    /// and drops receipts that can no longer be restored.
+    Repair,
+
+    /// Re-hash installed tool files and report anything changed since install
+    ///
+    /// Compares linked binaries and extras against the digests recorded at
+    /// install time, and cached archives against their release checksum.
+    /// Exits non-zero when a tool fails verification.
+    Verify {
+        /// Tool name (verifies all if not specified)
+        #[arg(value_name = "TOOL")]
+        name: Option<String>,
+    },
+
+    /// Show what installing the active profile (or switching profiles) would change
+    ///
+    /// Lists links to create or remove, existing files in the way, tools to
+    /// install, upgrade or remove and cache to prune. Nothing is modified.
+    Plan {
+        /// Plan switching to PROFILE instead of reinstalling the active one
+        #[arg(long, value_name = "PROFILE")]
+        profile: Option<String>,
+
+        /// Print the plan as JSON
+        #[arg(long)]
+        json: bool,
+    },
+
     /// Clean up unused cache and orphaned symlinks
     Cleanup,
 
     /// Validate profile and workspace manifests
     Check,
 
+    /// Put back files that were moved aside to make room for dotfile links
+    ///
+    /// Restored paths replace the dws links, so the next sync will back them up
+    /// again unless they are removed from the profile or `on_conflict = "skip"` is set.
+    RestoreBackups,
+
+    /// Move an existing file or directory into the profile and link it back
+    ///
+    /// `~/.config/foo` moves to the profile's `config/foo`, `~/.gitconfig` to
+    /// `home/.gitconfig`, and so on; the original path becomes a dws link.
+    Adopt {
+        /// File or directory to adopt
+        #[arg(value_name = "PATH")]
+        path: PathBuf,
+
+        /// Destination relative to the profile root (e.g. home/.config/foo)
+        #[arg(long = "as", value_name = "REL")]
+        dest: Option<PathBuf>,
+
+        /// Stage the adopted files in the profile's git repository
+        #[arg(long)]
+        git_add: bool,
+    },
+
+    /// Manage age-encrypted secrets in the profile
+    ///
+    /// Secrets are stored as `*.age` files and decrypted with
+    /// $XDG_CONFIG_HOME/dws/identity.txt into private (0600) files on install.
+    #[command(subcommand)]
+    Secret(SecretAction),
+
     /// Output environment setup (used in shell init)
     Env {
+        /// Shell type (zsh, bash, fish, nu, pwsh, elvish, xonsh)
+        #[arg(short, long, value_name = "SHELL", default_value = "zsh")]
+        shell: String,
+    },
+
+    /// Run a command inside the workspace environment
+    ///
+    /// Applies the same PATH, MANPATH and tool variables as `dws env` without
+    /// requiring shell integration, e.g. `dws exec -- rg --version`.
+    Exec {
+        /// Command and arguments to run
+        #[arg(
+            value_name = "COMMAND",
+            required = true,
+            trailing_var_arg = true,
+            allow_hyphen_values = true
+        )]
+        command: Vec<String>,
+    },
+
+    /// Start $SHELL with the workspace environment applied
+    Shell,
+
+    /// Trust the project `.dws.toml` in DIR (or a parent) and install its tools
+    ///
+    /// Allowed projects are activated by the `dws env` shell hook when you enter
+    /// them. Editing `.dws.toml` revokes trust until you allow it again.
+    Allow {
+        /// Project directory (defaults to the current directory)
+        #[arg(value_name = "DIR")]
+        path: Option<PathBuf>,
+
+        /// Stop activating the project instead
+        #[arg(long)]
+        revoke: bool,
+    },
+
+    /// Print shell code activating the project environment for the current directory
+    #[command(hide = true)]
+    HookEnv {
         /// Shell type (zsh, bash, fish)
         #[arg(short, long, value_name = "SHELL", default_value = "zsh")]
         shell: String,
@@ -96,6 +217,30 @@ pub enum Commands {
     Self_(SelfAction),
 }
 
+#[derive(Subcommand, Debug)]
+pub enum SecretAction {
+    /// Encrypt FILE into the active profile
+    ///
+    /// Files under $XDG_CONFIG_HOME go to `config/` and files under $HOME to
+    /// `home/`. An identity is generated on first use.
+    Add {
+        /// Plaintext file to encrypt
+        #[arg(value_name = "FILE")]
+        file: PathBuf,
+
+        /// Destination relative to the profile root (e.g. home/.netrc)
+        #[arg(long = "as", value_name = "PATH")]
+        dest: Option<PathBuf>,
+    },
+
+    /// Decrypt a secret, open it in $VISUAL/$EDITOR and re-encrypt it
+    Edit {
+        /// Secret path, absolute or relative to the profile root
+        #[arg(value_name = "PATH")]
+        path: PathBuf,
+    },
+}
+
 #[derive(Subcommand, Debug)]
 pub enum SelfAction {
     /// Show dws information (version, disk usage)
@@ -0,0 +1,22 @@
+use crate::{ui, Workspace};
+use anyhow::Result;
+use std::path::PathBuf;
+
+pub fn execute(
+    workspace: &Workspace,
+    path: PathBuf,
+    dest: Option<PathBuf>,
+    git_add: bool,
+) -> Result<()> {
+    let source = workspace.adopt(&path, dest.as_deref(), git_add)?;
+    ui::success(
+        "Adopted",
+        format!("{} -> {}", path.display(), source.display()),
+    );
+    if git_add {
+        ui::info("Staged in the profile repository; commit when ready");
+    } else {
+        ui::info("Review and commit the change in the profile repository");
+    }
+    Ok(())
+}
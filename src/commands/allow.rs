@@ -0,0 +1,17 @@
+use crate::Workspace;
+use anyhow::{Context, Result};
+use std::env;
+use std::path::PathBuf;
+
+pub fn execute(workspace: &Workspace, path: Option<PathBuf>, revoke: bool) -> Result<()> {
+    let path = match path {
+        Some(path) => path,
+        None => env::current_dir().context("Failed to determine current directory")?,
+    };
+
+    if revoke {
+        workspace.revoke_project(&path)
+    } else {
+        workspace.allow_project(&path)
+    }
+}
@@ -0,0 +1,33 @@
+use crate::Workspace;
+use anyhow::{Context, Result};
+use std::process::Command;
+
+pub fn execute(workspace: &Workspace, command: Vec<String>) -> Result<()> {
+    let (program, args) = command
+        .split_first()
+        .context("No command given. Usage: dws exec -- <command> [args]")?;
+
+    let mut child = Command::new(program);
+    child.args(args).envs(workspace.process_environment()?);
+    replace_process(child, program)
+}
+
+/// Replace the current process with `command`, or run it and forward its exit status where
+/// `exec` is unavailable.
+pub(super) fn replace_process(mut command: Command, program: &str) -> Result<()> {
+    #[cfg(unix)]
+    {
+        use std::os::unix::process::CommandExt;
+
+        let err = command.exec();
+        Err(err).with_context(|| format!("Failed to execute '{program}'"))
+    }
+
+    #[cfg(not(unix))]
+    {
+        let status = command
+            .status()
+            .with_context(|| format!("Failed to execute '{program}'"))?;
+        std::process::exit(status.code().unwrap_or(1));
+    }
+}
@@ -0,0 +1,13 @@
+use crate::Workspace;
+use anyhow::{Context, Result};
+use std::env;
+
+pub fn execute(workspace: &Workspace, shell: String) -> Result<()> {
+    let cwd = env::current_dir().context("Failed to determine current directory")?;
+    let script = workspace.hook_env(&shell, &cwd)?;
+    if !script.is_empty() {
+        println!("{script}");
+    }
+
+    Ok(())
+}
@@ -1,23 +1,49 @@
 use crate::cli::{Cli, Commands};
-use crate::Workspace;
+use crate::{LockMode, Workspace};
 use anyhow::Result;
+use std::time::Duration;
 
+mod adopt;
+mod allow;
 mod check;
 mod cleanup;
 mod clone_cmd;
 mod env;
+mod exec;
+mod hook_env;
 mod init;
+mod plan;
 mod profiles;
+mod repair;
 mod reset;
+mod restore_backups;
+mod secret;
 mod self_cmd;
+mod shell;
 mod status;
 mod sync;
 mod update;
 mod use_profile;
+mod verify;
 
 pub fn execute(cli: Cli) -> Result<()> {
     // Create workspace - this is the root entry point
     let mut workspace = Workspace::new()?;
+    workspace.set_conflict_policy(cli.on_conflict);
+    workspace.set_lock_timeout(cli.lock_timeout.map(Duration::from_secs));
+
+    // Held until the command returns.
+    let _lock = match lock_mode(&cli.command, cli.dry_run) {
+        Some(mode) => Some(workspace.lock(mode)?),
+        None => None,
+    };
+
+    if cli.dry_run {
+        if let Some(plan) = plan::dry_run(&workspace, &cli.command)? {
+            plan.print();
+            return Ok(());
+        }
+    }
 
     match cli.command {
         Commands::Init {
@@ -43,12 +69,54 @@ pub fn execute(cli: Cli) -> Result<()> {
 
         Commands::Status => status::execute(&workspace),
 
+        Commands::Repair => repair::execute(&workspace),
+
+        Commands::Verify { name } => verify::execute(&workspace, name),
+
+        Commands::Plan { profile, json } => plan::execute(&workspace, profile, json),
+
         Commands::Cleanup => cleanup::execute(&workspace),
 
         Commands::Check => check::execute(&workspace),
 
+        Commands::RestoreBackups => restore_backups::execute(&workspace),
+
+        Commands::Adopt {
+            path,
+            dest,
+            git_add,
+        } => adopt::execute(&workspace, path, dest, git_add),
+
+        Commands::Secret(action) => secret::execute(&workspace, action),
+
         Commands::Env { shell } => env::execute(&workspace, shell),
 
+        Commands::Exec { command } => exec::execute(&workspace, command),
+
+        Commands::Shell => shell::execute(&workspace),
+
+        Commands::Allow { path, revoke } => allow::execute(&workspace, path, revoke),
+
+        Commands::HookEnv { shell } => hook_env::execute(&workspace, shell),
+
         Commands::Self_(action) => self_cmd::execute(&workspace, action),
     }
 }
+
+/// Process lock `command` runs under.
+///
+/// Read-only commands share the lock so they never see a half-applied install. `exec`, `shell`
+/// and `hook-env` take none: the first two hand over to long-running children and `hook-env` runs
+/// on every prompt, so none of them may hold up (or wait for) an update.
+fn lock_mode(command: &Commands, dry_run: bool) -> Option<LockMode> {
+    match command {
+        Commands::Exec { .. } | Commands::Shell | Commands::HookEnv { .. } => None,
+        Commands::Profiles
+        | Commands::Status
+        | Commands::Plan { .. }
+        | Commands::Check
+        | Commands::Env { .. } => Some(LockMode::Shared),
+        _ if dry_run => Some(LockMode::Shared),
+        _ => Some(LockMode::Exclusive),
+    }
+}
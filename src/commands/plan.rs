@@ -0,0 +1,45 @@
+use crate::cli::Commands;
+use crate::{Plan, Workspace};
+use anyhow::Result;
+
+pub fn execute(workspace: &Workspace, profile: Option<String>, json: bool) -> Result<()> {
+    let plan = match profile {
+        Some(profile) => workspace.plan_use(&profile)?,
+        None => workspace.plan_install()?,
+    };
+    if json {
+        println!("{}", serde_json::to_string_pretty(&plan)?);
+    } else {
+        plan.print();
+    }
+    Ok(())
+}
+
+/// The plan for `command` under `--dry-run`, or `None` when the command changes nothing.
+pub fn dry_run(workspace: &Workspace, command: &Commands) -> Result<Option<Plan>> {
+    let plan = match command {
+        Commands::Init {
+            repository,
+            shell,
+            profile,
+        } => workspace.plan_init(repository.as_deref(), shell.as_deref(), profile.as_deref())?,
+        Commands::Use { profile } => workspace.plan_use(profile)?,
+        Commands::Sync => Plan {
+            command: "sync".to_string(),
+            ..workspace.plan_install()?
+        },
+        Commands::Update { name } => workspace.plan_update(name.as_deref())?,
+        Commands::Reset { .. } => workspace.plan_reset()?,
+        Commands::Repair => workspace.plan_repair()?,
+        Commands::Profiles
+        | Commands::Status
+        | Commands::Plan { .. }
+        | Commands::Check
+        | Commands::Env { .. }
+        | Commands::HookEnv { .. } => return Ok(None),
+        _ => anyhow::bail!(
+            "--dry-run is only supported by init, use, sync, update, repair and reset"
+        ),
+    };
+    Ok(Some(plan))
+}
@@ -0,0 +1,29 @@
+use crate::{ui, ActionKind, Workspace};
+use anyhow::Result;
+
+pub fn execute(workspace: &Workspace) -> Result<()> {
+    let fixed = workspace.repair()?;
+    if fixed.is_empty() {
+        ui::success(
+            "Repair",
+            "links and tool files match the lockfile; nothing to fix",
+        );
+        return Ok(());
+    }
+
+    for action in &fixed.actions {
+        let label = match action.action {
+            ActionKind::Relink => "Relinked",
+            ActionKind::Extract => "Extracted",
+            ActionKind::Download => "Downloaded",
+            ActionKind::RemoveTool => "Removed",
+            _ => "Fixed",
+        };
+        match &action.detail {
+            Some(detail) => ui::success(label, format!("{} ({detail})", action.target)),
+            None => ui::success(label, &action.target),
+        }
+    }
+    ui::success("Repaired", format!("{} issue(s)", fixed.actions.len()));
+    Ok(())
+}
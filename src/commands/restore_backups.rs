@@ -0,0 +1,22 @@
+use crate::{ui, Workspace};
+use anyhow::Result;
+
+pub fn execute(workspace: &Workspace) -> Result<()> {
+    let restored = workspace.restore_backups()?;
+    if restored.is_empty() {
+        ui::info("No backups to restore.");
+        return Ok(());
+    }
+
+    for path in &restored {
+        ui::status("Restored", path.display());
+    }
+    ui::success(
+        "Done",
+        format!(
+            "Restored {} file(s); they will conflict again on the next install",
+            restored.len()
+        ),
+    );
+    Ok(())
+}
@@ -0,0 +1,111 @@
+use crate::cli::SecretAction;
+use crate::secrets::{self, Keyring, SECRET_EXTENSION};
+use crate::{ui, Workspace, WorkspacePath};
+use anyhow::{Context, Result};
+use std::env;
+use std::fs;
+use std::os::unix::fs::DirBuilderExt;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+pub fn execute(workspace: &Workspace, action: SecretAction) -> Result<()> {
+    match action {
+        SecretAction::Add { file, dest } => add(workspace, &file, dest.as_deref()),
+        SecretAction::Edit { path } => edit(workspace, &path),
+    }
+}
+
+fn add(workspace: &Workspace, file: &Path, dest: Option<&Path>) -> Result<()> {
+    let (secret, generated) = workspace.add_secret(file, dest)?;
+    if generated {
+        ui::status(
+            "Generated",
+            format!(
+                "age identity {} (back it up; secrets cannot be decrypted without it)",
+                workspace.path(WorkspacePath::Identity).display()
+            ),
+        );
+    }
+    ui::success("Encrypted", secret.display());
+    ui::info(format!(
+        "Remove the plaintext {} and run `dws sync` to install the secret",
+        file.display()
+    ));
+    Ok(())
+}
+
+fn edit(workspace: &Workspace, path: &Path) -> Result<()> {
+    let secret = resolve_secret(workspace, path)?;
+    let keyring = Keyring::load(&workspace.path(WorkspacePath::Identity))?;
+    let plaintext = keyring.decrypt(&secret)?;
+
+    // Keep the plaintext in a private directory while the editor runs.
+    let scratch = env::temp_dir().join(format!("dws-secret-{}", std::process::id()));
+    fs::DirBuilder::new()
+        .mode(0o700)
+        .create(&scratch)
+        .with_context(|| format!("Failed to create {:?}", scratch))?;
+    let result = edit_in(&scratch, &secret, &keyring, &plaintext);
+    let _ = fs::remove_dir_all(&scratch);
+
+    if result? {
+        ui::success("Encrypted", secret.display());
+        ui::info("Run `dws sync` to install the updated secret");
+    } else {
+        ui::info("No changes");
+    }
+    Ok(())
+}
+
+/// Run the editor on a decrypted copy inside `scratch`; returns whether the secret changed.
+fn edit_in(scratch: &Path, secret: &Path, keyring: &Keyring, plaintext: &[u8]) -> Result<bool> {
+    let name = secret
+        .file_stem()
+        .map(PathBuf::from)
+        .unwrap_or_else(|| PathBuf::from("secret"));
+    let working = scratch.join(name);
+    secrets::write_private(&working, plaintext)?;
+
+    let editor = ["VISUAL", "EDITOR"]
+        .iter()
+        .filter_map(|var| env::var(var).ok())
+        .find(|value| !value.trim().is_empty())
+        .unwrap_or_else(|| "vi".to_string());
+    let status = Command::new("sh")
+        .arg("-c")
+        .arg(format!("{editor} \"$1\""))
+        .arg("sh")
+        .arg(&working)
+        .status()
+        .with_context(|| format!("Failed to run editor {:?}", editor))?;
+    if !status.success() {
+        anyhow::bail!(
+            "Editor {:?} exited with {}; secret left unchanged",
+            editor,
+            status
+        );
+    }
+
+    let edited = fs::read(&working).with_context(|| format!("Failed to read {:?}", working))?;
+    if edited == plaintext {
+        return Ok(false);
+    }
+    fs::write(secret, keyring.encrypt(&edited)?)
+        .with_context(|| format!("Failed to write {:?}", secret))?;
+    Ok(true)
+}
+
+/// Accept an existing path or a path relative to the profile root, with or without `.age`.
+fn resolve_secret(workspace: &Workspace, path: &Path) -> Result<PathBuf> {
+    let profile = workspace.path(WorkspacePath::Profile);
+    let mut candidates = vec![path.to_path_buf(), profile.join(path)];
+    if path.extension().and_then(|ext| ext.to_str()) != Some(SECRET_EXTENSION) {
+        let mut with_ext = path.as_os_str().to_owned();
+        with_ext.push(".age");
+        candidates.push(profile.join(with_ext));
+    }
+    candidates
+        .into_iter()
+        .find(|candidate| candidate.is_file())
+        .with_context(|| format!("No secret {:?} in {:?}", path, profile))
+}
@@ -0,0 +1,17 @@
+use crate::Workspace;
+use anyhow::Result;
+use std::env;
+use std::process::Command;
+
+use super::exec::replace_process;
+
+pub fn execute(workspace: &Workspace) -> Result<()> {
+    let shell = env::var("SHELL")
+        .ok()
+        .filter(|shell| !shell.is_empty())
+        .unwrap_or_else(|| "/bin/sh".to_string());
+
+    let mut child = Command::new(&shell);
+    child.envs(workspace.process_environment()?);
+    replace_process(child, &shell)
+}
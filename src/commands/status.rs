@@ -1,11 +1,12 @@
-use crate::lockfile::ToolReceipt;
+use crate::integrity::{check_symlink, check_wrapper, LinkState};
+use crate::lockfile::{ReceiptStatus, ToolReceipt};
+use crate::secrets::SECRET_EXTENSION;
+use crate::template::file_digest;
 use crate::{ui, Lockfile, Workspace, WorkspacePath};
 use anyhow::Result;
 use chrono::{DateTime, Local};
 use directories::BaseDirs;
 use std::collections::HashMap;
-use std::fs;
-use std::io::ErrorKind;
 use std::path::{Path, PathBuf};
 
 pub fn execute(workspace: &Workspace) -> Result<()> {
@@ -60,6 +61,15 @@ fn report_config_symlinks(lockfile: &Lockfile, display: &DisplayContext) -> Resu
     let total = entries.len();
     let mut issues = Vec::new();
     for entry in entries {
+        if let Some(digest) = &entry.rendered {
+            issues.extend(check_rendered(
+                &entry.source,
+                &entry.target,
+                digest,
+                display,
+            ));
+            continue;
+        }
         match check_symlink(&entry.source, &entry.target) {
             LinkState::Ok => {}
             LinkState::MissingTarget => issues.push(format!(
@@ -91,6 +101,15 @@ fn report_config_symlinks(lockfile: &Lockfile, display: &DisplayContext) -> Resu
         }
     }
 
+    for entry in lockfile.config_symlinks() {
+        if let Some(overlay) = &entry.overlay {
+            ui::status(
+                "Overlay",
+                format!("{} from {}", display.format(&entry.target), overlay),
+            );
+        }
+    }
+
     if issues.is_empty() {
         ui::success("Config", format!("{} item(s) healthy", total));
     } else {
@@ -98,11 +117,50 @@ fn report_config_symlinks(lockfile: &Lockfile, display: &DisplayContext) -> Resu
         for issue in issues {
             ui::warn(issue);
         }
+        ui::info("Run 'dws repair' to recreate missing or misdirected links.");
     }
 
     Ok(())
 }
 
+/// Inspect a file rendered from a template against the digest recorded at install time.
+fn check_rendered(
+    source: &Path,
+    target: &Path,
+    digest: &str,
+    display: &DisplayContext,
+) -> Option<String> {
+    if !target.exists() {
+        Some(format!(
+            "Rendered config missing: {} (template {})",
+            display.format(target),
+            display.format(source)
+        ))
+    } else if !source.exists() {
+        Some(format!(
+            "Template source missing: {} (rendered at {})",
+            display.format(source),
+            display.format(target)
+        ))
+    } else if file_digest(target).as_deref() != Some(digest) {
+        let hint = if source.extension().and_then(|ext| ext.to_str()) == Some(SECRET_EXTENSION) {
+            format!(
+                "update it with `dws secret edit {}`",
+                display.format(source)
+            )
+        } else {
+            format!("edit the template {} instead", display.format(source))
+        };
+        Some(format!(
+            "Rendered config edited since last sync: {} ({})",
+            display.format(target),
+            hint
+        ))
+    } else {
+        None
+    }
+}
+
 fn report_tools(
     workspace: &Workspace,
     display: &DisplayContext,
@@ -126,7 +184,7 @@ fn report_tools(
             for (name, rs) in receipts {
                 let versions: Vec<String> = rs.iter().map(|r| r.resolved_version.clone()).collect();
                 ui::warn(format!(
-                    "Tool '{}' (installed versions: {}) - consider removing with future cleanup.",
+                    "Tool '{}' (installed versions: {}) - remove it with 'dws repair'.",
                     name,
                     versions.join(", ")
                 ));
@@ -154,31 +212,47 @@ fn report_tools(
                     AssetState::Healthy => "asset: cached",
                     AssetState::Missing => "asset: missing",
                 };
+                let integrity = rs
+                    .iter()
+                    .map(|r| r.status)
+                    .find(|status| status.is_damaged())
+                    .or_else(|| {
+                        rs.iter()
+                            .map(|r| r.status)
+                            .find(|s| *s != ReceiptStatus::Ok)
+                    })
+                    .unwrap_or(ReceiptStatus::Ok);
 
                 if issues.is_empty() {
                     ui::success(
                         "Tool",
                         format!(
-                            "{} {} (binaries: {}, extras: {}, {})",
+                            "{} {} (binaries: {}, extras: {}, {}, integrity: {})",
                             name,
                             versions.join(", "),
                             binary_total,
                             extra_total,
-                            asset_summary
+                            asset_summary,
+                            integrity
                         ),
                     );
                     ok_count += 1;
                 } else {
                     ui::warn(format!(
-                        "Tool '{}' has {} issue(s) across version(s: {}) ({})",
+                        "Tool '{}' has {} issue(s) across version(s: {}) ({}, integrity: {})",
                         name,
                         issues.len(),
                         versions.join(", "),
-                        asset_summary
+                        asset_summary,
+                        integrity
                     ));
                     for issue in issues {
                         ui::warn(issue);
                     }
+                    ui::info(format!(
+                        "Run 'dws repair' to relink '{}' and restore missing files.",
+                        name
+                    ));
                 }
             }
         }
@@ -190,7 +264,7 @@ fn report_tools(
     for (name, rs) in receipts {
         let versions: Vec<String> = rs.iter().map(|r| r.resolved_version.clone()).collect();
         ui::warn(format!(
-            "Orphaned tool '{}' (versions: {}) not present in manifest.",
+            "Orphaned tool '{}' (versions: {}) not present in manifest; 'dws repair' removes it.",
             name,
             versions.join(", ")
         ));
@@ -212,13 +286,22 @@ fn verify_tool_receipts(
     for receipt in receipts {
         for bin in &receipt.binaries {
             binaries += 1;
-            match check_symlink(&bin.source, &bin.target) {
+            let state = if bin.wrapper.is_some() {
+                check_wrapper(&bin.source, &bin.target)
+            } else {
+                check_symlink(&bin.source, &bin.target)
+            };
+            match state {
                 LinkState::Ok => {}
                 LinkState::MissingTarget => issues.push(format!(
                     "Binary target missing: {} (expected -> {})",
                     display.format(&bin.target),
                     display.format(&bin.source)
                 )),
+                LinkState::NotSymlink if bin.wrapper.is_some() => issues.push(format!(
+                    "Binary target exists but is not a dws wrapper script: {}",
+                    display.format(&bin.target)
+                )),
                 LinkState::NotSymlink => issues.push(format!(
                     "Binary target exists but is not a symlink: {}",
                     display.format(&bin.target)
@@ -277,6 +360,13 @@ fn verify_tool_receipts(
             }
         }
 
+        if receipt.status.is_damaged() {
+            issues.push(format!(
+                "Last verification of {} {} found {}; run `dws verify {}` for details",
+                receipt.name, receipt.resolved_version, receipt.status, receipt.name
+            ));
+        }
+
         if let Some(asset) = &receipt.asset {
             asset_recorded = true;
             if !asset.archive_path.exists() {
@@ -323,54 +413,6 @@ fn format_timestamp(raw: &str) -> String {
     }
 }
 
-fn check_symlink(source: &Path, target: &Path) -> LinkState {
-    match fs::symlink_metadata(target) {
-        Ok(metadata) => {
-            if !metadata.file_type().is_symlink() {
-                return LinkState::NotSymlink;
-            }
-        }
-        Err(err) => {
-            if err.kind() == ErrorKind::NotFound {
-                return LinkState::MissingTarget;
-            }
-            return LinkState::IoError(err);
-        }
-    }
-
-    match fs::read_link(target) {
-        Ok(actual) => {
-            let resolved = if actual.is_absolute() {
-                actual
-            } else if let Some(parent) = target.parent() {
-                parent.join(actual)
-            } else {
-                actual
-            };
-
-            if resolved != source {
-                return LinkState::WrongTarget { actual: resolved };
-            }
-        }
-        Err(err) => return LinkState::IoError(err),
-    }
-
-    if !(source.exists() || source.symlink_metadata().is_ok()) {
-        return LinkState::MissingSource;
-    }
-
-    LinkState::Ok
-}
-
-enum LinkState {
-    Ok,
-    MissingTarget,
-    NotSymlink,
-    WrongTarget { actual: PathBuf },
-    MissingSource,
-    IoError(std::io::Error),
-}
-
 #[derive(Clone, Copy, Debug, PartialEq, Eq)]
 enum AssetState {
     NotRecorded,
@@ -379,9 +421,12 @@ enum AssetState {
 }
 
 #[cfg(test)]
+#[allow(clippy::items_after_test_module)]
 mod tests {
     use super::*;
+    use crate::installers::wrapper;
     use crate::lockfile::AssetRecord;
+    use std::collections::BTreeMap;
     use tempfile::TempDir;
 
     fn display_context() -> DisplayContext {
@@ -407,6 +452,8 @@ mod tests {
                 pattern_index: Some(0),
                 pattern: Some("mock".to_string()),
             }),
+            env: BTreeMap::new(),
+            status: ReceiptStatus::Ok,
         };
 
         let (_b, _e, asset_state, issues) = verify_tool_receipts(&[&receipt], &display_context());
@@ -444,6 +491,8 @@ mod tests {
                 pattern_index: Some(0),
                 pattern: Some("mock".to_string()),
             }),
+            env: BTreeMap::new(),
+            status: ReceiptStatus::Ok,
         };
 
         let (_b, _e, asset_state, issues) = verify_tool_receipts(&[&receipt], &display_context());
@@ -462,12 +511,69 @@ mod tests {
             binaries: Vec::new(),
             extras: Vec::new(),
             asset: None,
+            env: BTreeMap::new(),
+            status: ReceiptStatus::Ok,
         };
 
         let (_b, _e, asset_state, issues) = verify_tool_receipts(&[&receipt], &display_context());
         assert_eq!(asset_state, AssetState::NotRecorded);
         assert!(issues.is_empty());
     }
+
+    #[test]
+    fn verify_tool_receipts_reports_failed_verification() {
+        let receipt = ToolReceipt {
+            name: "mock".to_string(),
+            manifest_version: "latest".to_string(),
+            resolved_version: "v1.0.0".to_string(),
+            installer_kind: "github".to_string(),
+            installed_at: "2025-01-01T00:00:00Z".to_string(),
+            binaries: Vec::new(),
+            extras: Vec::new(),
+            asset: None,
+            env: BTreeMap::new(),
+            status: ReceiptStatus::ChecksumMismatch,
+        };
+
+        let (_b, _e, _asset_state, issues) = verify_tool_receipts(&[&receipt], &display_context());
+        assert_eq!(
+            issues,
+            ["Last verification of mock v1.0.0 found checksum_mismatch; run `dws verify mock` for details"]
+        );
+    }
+
+    #[test]
+    fn check_wrapper_validates_script_and_exec_target() {
+        use crate::toolset::BinWrapper;
+
+        let temp = TempDir::new().unwrap();
+        let source = temp.path().join("contents/tool");
+        let target = temp.path().join("bin/tool");
+        std::fs::create_dir_all(source.parent().unwrap()).unwrap();
+        std::fs::create_dir_all(target.parent().unwrap()).unwrap();
+        std::fs::write(&source, b"binary").unwrap();
+
+        assert!(matches!(
+            check_wrapper(&source, &target),
+            LinkState::MissingTarget
+        ));
+
+        let script = wrapper::render("tool", &source, &BinWrapper::default());
+        wrapper::write(&target, &script).unwrap();
+        assert!(matches!(check_wrapper(&source, &target), LinkState::Ok));
+
+        let other = temp.path().join("contents/other");
+        assert!(matches!(
+            check_wrapper(&other, &target),
+            LinkState::WrongTarget { .. }
+        ));
+
+        std::fs::write(&target, "#!/bin/sh\nexec /usr/bin/true\n").unwrap();
+        assert!(matches!(
+            check_wrapper(&source, &target),
+            LinkState::NotSymlink
+        ));
+    }
 }
 
 struct DisplayContext {
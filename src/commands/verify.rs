@@ -0,0 +1,48 @@
+use crate::integrity::Finding;
+use crate::lockfile::ReceiptStatus;
+use crate::{ui, Workspace};
+use anyhow::Result;
+
+pub fn execute(workspace: &Workspace, name: Option<String>) -> Result<()> {
+    let verifications = workspace.verify_tools(name.as_deref())?;
+    if verifications.is_empty() {
+        ui::info("No tools installed.");
+        return Ok(());
+    }
+
+    let mut damaged = Vec::new();
+    for verification in &verifications {
+        let label = format!("{} {}", verification.name, verification.version);
+        match verification.status {
+            ReceiptStatus::Ok => ui::success(
+                "Verified",
+                format!("{label} ({} file(s))", verification.checked),
+            ),
+            ReceiptStatus::Unverified => ui::warn(format!(
+                "{label}: no digests recorded; run `dws sync` to reinstall and record them"
+            )),
+            status => {
+                ui::error(format!("{label}: {status}"));
+                damaged.push(verification.name.as_str());
+            }
+        }
+        for finding in &verification.findings {
+            match finding {
+                Finding::ArchiveMissing(_) => ui::info(format!("  {finding}")),
+                Finding::ArchiveModified(_) => {
+                    ui::error(format!("  {finding} (removed from the cache)"))
+                }
+                _ => ui::error(format!("  {finding}")),
+            }
+        }
+    }
+
+    if !damaged.is_empty() {
+        anyhow::bail!(
+            "{} tool(s) failed verification ({}); run `dws sync` to reinstall them",
+            damaged.len(),
+            damaged.join(", ")
+        );
+    }
+    Ok(())
+}
@@ -1,3 +1,5 @@
+use crate::dotfiles::DotfilesSettings;
+use crate::process_lock::LockSettings;
 use crate::toolset::{ToolConfigFile, ToolSpecToml};
 use anyhow::{bail, Result};
 use std::collections::BTreeMap;
@@ -57,6 +59,18 @@ impl Config {
         &mut self.inner.tools
     }
 
+    pub fn dotfiles(&self) -> &DotfilesSettings {
+        &self.inner.dotfiles
+    }
+
+    pub fn lock(&self) -> &LockSettings {
+        &self.inner.lock
+    }
+
+    pub fn vars(&self) -> &BTreeMap<String, Value> {
+        &self.inner.vars
+    }
+
     pub fn extras(&self) -> &BTreeMap<String, Value> {
         &self.inner.extras
     }
@@ -1,7 +1,21 @@
 use anyhow::{Context, Result};
+use ignore::gitignore::{Gitignore, GitignoreBuilder};
+use ignore::Match;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashSet};
+use std::ffi::{OsStr, OsString};
 use std::fs;
+use std::io::{self, ErrorKind};
 use std::os::unix::fs::symlink;
-use std::path::{Path, PathBuf};
+use std::path::{Component, Path, PathBuf};
+
+use crate::secrets::{write_private, Keyring, SECRET_EXTENSION};
+use crate::template::{TemplateVars, TEMPLATE_EXTENSION};
+
+/// Marker file switching a directory (and its subdirectories) to per-file linking.
+pub(crate) const LINK_FILES_MARKER: &str = ".dws-link-files";
+/// Marker file forcing a directory to be linked as a whole.
+const LINK_DIR_MARKER: &str = ".dws-link-dir";
 
 /// Built-in ignore patterns (always ignored, similar to Git's built-ins)
 /// These are the bare minimum to prevent system files and VCS directories
@@ -9,8 +23,80 @@ const BUILTIN_IGNORES: &[&str] = &[
     ".git",
     ".DS_Store",
     ".dwsignore", // Don't symlink the ignore file itself
+    LINK_FILES_MARKER,
+    LINK_DIR_MARKER,
 ];
 
+/// How a directory under `config/` is installed into the target directory.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LinkMode {
+    /// Symlink the directory itself (`config/zsh` → `~/.config/zsh`).
+    #[default]
+    Directory,
+    /// Create a real directory and symlink each file inside it, recursively.
+    Files,
+}
+
+/// What to do when a target already exists and is not a link dws created.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, clap::ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum ConflictPolicy {
+    /// Move the existing file aside so `dws restore-backups` can put it back.
+    #[default]
+    Backup,
+    /// Leave the existing file alone and don't link the entry.
+    Skip,
+    /// Replace the profile copy with the existing file, then link it.
+    Adopt,
+    /// Delete the existing file or directory.
+    Overwrite,
+}
+
+/// `[dotfiles]` table in `dws.toml` / `config.toml`.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct DotfilesSettings {
+    /// Default link mode for directories without a marker file or directive.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub link_mode: Option<LinkMode>,
+    /// Policy for targets that exist but are not managed by dws.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub on_conflict: Option<ConflictPolicy>,
+}
+
+impl DotfilesSettings {
+    pub fn is_unset(&self) -> bool {
+        self.link_mode.is_none() && self.on_conflict.is_none()
+    }
+}
+
+/// Result of installing a single entry.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum InstallOutcome {
+    /// The target was free (or already a dws link or unchanged render) and is now installed.
+    Linked,
+    /// An existing target was moved to the contained backup path before linking.
+    BackedUp(PathBuf),
+    /// An existing target was moved into the profile before linking.
+    Adopted,
+    /// An existing target was deleted before linking.
+    Overwritten,
+    /// An existing target was left in place; nothing was linked.
+    Skipped,
+}
+
+/// What installing a single entry would do, worked out without touching disk.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EntryPlan {
+    /// The target already matches the profile.
+    Unchanged,
+    /// The target is free (or will be cleared) and would be linked or written.
+    Install,
+    /// The target exists and is not dws's; the contained policy would apply.
+    Conflict(ConflictPolicy),
+}
+
 /// Represents a dotfile configuration entry that should be installed
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub struct DotfileEntry {
@@ -18,69 +104,117 @@ pub struct DotfileEntry {
     pub source: PathBuf,
     /// Target path in XDG_CONFIG_HOME (e.g., $XDG_CONFIG_HOME/zsh/.zshrc)
     pub target: PathBuf,
+    /// Overlay directory that supplied `source` (e.g. `config@linux`), if not the base
+    pub overlay: Option<String>,
 }
 
 impl DotfileEntry {
     /// Create a new config entry
     pub fn new(source: PathBuf, target: PathBuf) -> Self {
-        Self { source, target }
+        Self {
+            source,
+            target,
+            overlay: None,
+        }
     }
 
-    /// Check if this entry's filename matches a pattern
-    pub fn matches_pattern(&self, pattern: &str) -> bool {
-        let filename = match self.source.file_name().and_then(|n| n.to_str()) {
-            Some(name) => name,
-            None => return false,
-        };
+    /// Check if this entry matches a single gitignore-style pattern
+    ///
+    /// The pattern is interpreted relative to the entry's parent directory.
+    pub fn matches_pattern(&self, pattern: &str) -> Result<bool> {
+        self.should_ignore(&[pattern.to_string()])
+    }
 
-        // Exact match
-        if pattern == filename {
-            return true;
+    /// Check if this entry should be ignored based on gitignore-style patterns
+    ///
+    /// Patterns are evaluated in order relative to the entry's parent directory, so a later
+    /// `!pattern` re-includes an earlier match.
+    pub fn should_ignore(&self, user_patterns: &[String]) -> Result<bool> {
+        if is_builtin_ignore(&self.source) {
+            return Ok(true);
         }
 
-        // Support simple wildcards like *.log
-        if pattern.contains('*') {
-            let pattern_parts: Vec<&str> = pattern.split('*').collect();
-            if pattern_parts.len() == 2 {
-                let (prefix, suffix) = (pattern_parts[0], pattern_parts[1]);
-                if filename.starts_with(prefix) && filename.ends_with(suffix) {
-                    return true;
-                }
-            }
+        let root = self.source.parent().unwrap_or(Path::new(""));
+        let mut builder = GitignoreBuilder::new(root);
+        for pattern in user_patterns {
+            builder
+                .add_line(None, pattern)
+                .with_context(|| format!("Invalid ignore pattern {:?}", pattern))?;
         }
+        let rules = builder
+            .build()
+            .context("Failed to compile ignore patterns")?;
+        Ok(rules
+            .matched(&self.source, self.source.is_dir())
+            .is_ignore())
+    }
 
-        false
+    /// Whether the target is a symlink dws created for this entry.
+    fn is_linked(&self) -> bool {
+        fs::read_link(&self.target)
+            .map(|link| link == self.source)
+            .unwrap_or(false)
     }
 
-    /// Check if this entry should be ignored based on patterns
-    pub fn should_ignore(&self, user_patterns: &[String]) -> bool {
-        let filename = match self.source.file_name().and_then(|n| n.to_str()) {
-            Some(name) => name,
-            None => return false,
-        };
+    /// Whether the source is a `*.tmpl` file rendered into the target instead of linked.
+    pub fn is_template(&self) -> bool {
+        has_extension(&self.source, TEMPLATE_EXTENSION) && self.source.is_file()
+    }
 
-        // Check built-in ignores first
-        if BUILTIN_IGNORES.contains(&filename) {
-            return true;
-        }
+    /// Whether the source is an age-encrypted `*.age` file decrypted into the target.
+    pub fn is_secret(&self) -> bool {
+        has_extension(&self.source, SECRET_EXTENSION) && self.source.is_file()
+    }
 
-        // Check user patterns
-        user_patterns.iter().any(|p| self.matches_pattern(p))
+    /// Whether the target is a generated regular file rather than a symlink.
+    pub fn is_rendered(&self) -> bool {
+        self.is_template() || self.is_secret()
     }
 
-    /// Install this config entry (currently via symlink)
-    pub fn install(&self) -> Result<()> {
-        // Ensure parent directory exists
+    /// Create the target's parent directory, refusing to write through a link into the profile.
+    fn prepare_target(&self) -> Result<()> {
         if let Some(parent) = self.target.parent() {
+            // A leftover whole-directory link would make the target the source file itself;
+            // replacing it would destroy the profile copy.
+            let same_dir = match (parent.canonicalize(), self.source.parent()) {
+                (Ok(parent), Some(source_parent)) => source_parent
+                    .canonicalize()
+                    .map(|source_parent| source_parent == parent)
+                    .unwrap_or(false),
+                _ => false,
+            };
+            if same_dir {
+                anyhow::bail!(
+                    "Cannot link {:?}: {:?} resolves into the profile (remove the directory symlink first)",
+                    self.target,
+                    parent
+                );
+            }
             fs::create_dir_all(parent)
                 .with_context(|| format!("Failed to create parent directory {:?}", parent))?;
         }
+        Ok(())
+    }
 
-        // Remove existing file/symlink if it exists
-        if self.target.exists() || self.target.symlink_metadata().is_ok() {
-            fs::remove_file(&self.target).with_context(|| {
-                format!("Failed to remove existing config entry {:?}", self.target)
-            })?;
+    /// Install this config entry (currently via symlink)
+    ///
+    /// An existing target that is not already linked to the source is a conflict, resolved
+    /// according to `policy`. Backups go to `backup_dir`, mirroring the target's absolute path.
+    pub fn install(&self, policy: ConflictPolicy, backup_dir: &Path) -> Result<InstallOutcome> {
+        self.prepare_target()?;
+
+        let mut outcome = InstallOutcome::Linked;
+        if let Ok(metadata) = self.target.symlink_metadata() {
+            if self.is_linked() {
+                fs::remove_file(&self.target).with_context(|| {
+                    format!("Failed to remove existing config entry {:?}", self.target)
+                })?;
+            } else {
+                outcome = self.resolve_conflict(policy, backup_dir, metadata.is_dir())?;
+                if outcome == InstallOutcome::Skipped {
+                    return Ok(outcome);
+                }
+            }
         }
 
         // Install via symlink (implementation detail)
@@ -91,89 +225,602 @@ impl DotfileEntry {
             )
         })?;
 
-        Ok(())
+        Ok(outcome)
+    }
+
+    /// Write rendered template or decrypted secret output to the target as a regular file.
+    ///
+    /// A target that already holds exactly `contents` is rewritten in place; anything else is a
+    /// conflict, handled as in [`DotfileEntry::install`]. Secrets are written with mode 0600;
+    /// templates copy the source's permissions.
+    pub fn install_rendered(
+        &self,
+        contents: &[u8],
+        policy: ConflictPolicy,
+        backup_dir: &Path,
+    ) -> Result<InstallOutcome> {
+        self.prepare_target()?;
+
+        let mut outcome = InstallOutcome::Linked;
+        if let Ok(metadata) = self.target.symlink_metadata() {
+            let unchanged = metadata.is_file()
+                && fs::read(&self.target)
+                    .map(|existing| existing == contents)
+                    .unwrap_or(false);
+            if !unchanged {
+                outcome = self.resolve_conflict(policy, backup_dir, metadata.is_dir())?;
+                if outcome == InstallOutcome::Skipped {
+                    return Ok(outcome);
+                }
+            }
+        }
+
+        if self.is_secret() {
+            write_private(&self.target, contents)?;
+        } else {
+            fs::write(&self.target, contents)
+                .with_context(|| format!("Failed to write rendered template {:?}", self.target))?;
+        }
+        if let (false, Ok(metadata)) = (self.is_secret(), fs::metadata(&self.source)) {
+            fs::set_permissions(&self.target, metadata.permissions())
+                .with_context(|| format!("Failed to set permissions on {:?}", self.target))?;
+        }
+
+        Ok(outcome)
+    }
+
+    /// Predict [`DotfileEntry::install`] / [`DotfileEntry::install_rendered`] for this entry.
+    ///
+    /// `rendered` is the output for templates and secrets. `cleared` means the install will
+    /// remove whatever dws previously put at the target before linking.
+    pub fn plan(
+        &self,
+        policy: ConflictPolicy,
+        rendered: Option<&[u8]>,
+        cleared: bool,
+    ) -> EntryPlan {
+        let unchanged = match rendered {
+            Some(contents) => fs::read(&self.target)
+                .map(|existing| existing == contents)
+                .unwrap_or(false),
+            None => self.is_linked(),
+        };
+        if unchanged {
+            EntryPlan::Unchanged
+        } else if cleared || self.target.symlink_metadata().is_err() {
+            EntryPlan::Install
+        } else if policy == ConflictPolicy::Adopt && self.is_rendered() {
+            EntryPlan::Conflict(ConflictPolicy::Backup)
+        } else {
+            EntryPlan::Conflict(policy)
+        }
+    }
+
+    /// Clear an unmanaged target out of the way (or decide to leave it).
+    fn resolve_conflict(
+        &self,
+        policy: ConflictPolicy,
+        backup_dir: &Path,
+        is_dir: bool,
+    ) -> Result<InstallOutcome> {
+        match policy {
+            ConflictPolicy::Skip => Ok(InstallOutcome::Skipped),
+            ConflictPolicy::Backup => {
+                let relative: PathBuf = self
+                    .target
+                    .components()
+                    .filter(|component| matches!(component, Component::Normal(_)))
+                    .collect();
+                let backup = backup_dir.join(relative);
+                move_path(&self.target, &backup)?;
+                Ok(InstallOutcome::BackedUp(backup))
+            }
+            // Adopting would replace the template or ciphertext with its own output; back it up
+            // instead.
+            ConflictPolicy::Adopt if self.is_rendered() => {
+                self.resolve_conflict(ConflictPolicy::Backup, backup_dir, is_dir)
+            }
+            ConflictPolicy::Adopt => {
+                // The profile copy is only dropped once the target has taken its place.
+                let mut aside = self.source.clone().into_os_string();
+                aside.push(".dws-replaced");
+                let aside = PathBuf::from(aside);
+                let stashed = self.source.symlink_metadata().is_ok();
+                if stashed {
+                    remove_path(&aside)?;
+                    move_path(&self.source, &aside)?;
+                }
+                if let Err(err) = move_path(&self.target, &self.source) {
+                    if stashed {
+                        move_path(&aside, &self.source).with_context(|| {
+                            format!("Failed to restore profile copy from {:?}", aside)
+                        })?;
+                    }
+                    return Err(err);
+                }
+                if stashed {
+                    remove_path(&aside)?;
+                }
+                Ok(InstallOutcome::Adopted)
+            }
+            ConflictPolicy::Overwrite => {
+                if is_dir {
+                    fs::remove_dir_all(&self.target)
+                } else {
+                    fs::remove_file(&self.target)
+                }
+                .with_context(|| {
+                    format!("Failed to remove existing config entry {:?}", self.target)
+                })?;
+                Ok(InstallOutcome::Overwritten)
+            }
+        }
+    }
+}
+
+fn has_extension(path: &Path, extension: &str) -> bool {
+    path.extension().and_then(|ext| ext.to_str()) == Some(extension)
+}
+
+/// Whether `dir` has to be walked per file rather than linked as a whole.
+///
+/// That is the case when it holds a `*.tmpl` or `*.age` file or a nested `.dwsignore`, or when
+/// the active ignore rules exclude anything inside it, at any depth (not following symlinks).
+/// `base` is the path of `dir` in the base directory, which the rules are matched against.
+fn needs_walk(dir: &Path, base: &Path, ignores: &[IgnoreFile]) -> bool {
+    walkdir::WalkDir::new(dir)
+        .min_depth(1)
+        .into_iter()
+        .filter_map(|entry| entry.ok())
+        .any(|entry| {
+            let path = entry.path();
+            let file_type = entry.file_type();
+            if file_type.is_file()
+                && (has_extension(path, TEMPLATE_EXTENSION)
+                    || has_extension(path, SECRET_EXTENSION)
+                    || path.file_name() == Some(OsStr::new(".dwsignore")))
+            {
+                return true;
+            }
+            let relative = path.strip_prefix(dir).unwrap_or(path);
+            is_ignored(ignores, &base.join(relative), file_type.is_dir())
+        })
+}
+
+/// Remove a file, symlink or directory tree if it exists.
+fn remove_path(path: &Path) -> Result<()> {
+    let result = match path.symlink_metadata() {
+        Ok(metadata) if metadata.is_dir() => fs::remove_dir_all(path),
+        Ok(_) => fs::remove_file(path),
+        Err(_) => return Ok(()),
+    };
+    result.with_context(|| format!("Failed to remove {:?}", path))
+}
+
+/// Move a file, symlink or directory, creating the destination's parent directories.
+///
+/// Moves onto another filesystem (a backup directory on a separate `$XDG_STATE_HOME` mount, say)
+/// fall back to copying and then removing the original.
+pub(crate) fn move_path(from: &Path, to: &Path) -> Result<()> {
+    if let Some(parent) = to.parent() {
+        fs::create_dir_all(parent)
+            .with_context(|| format!("Failed to create directory {:?}", parent))?;
+    }
+    match fs::rename(from, to) {
+        Err(err) if err.kind() == ErrorKind::CrossesDevices => copy_then_remove(from, to),
+        result => result.with_context(|| format!("Failed to move {:?} to {:?}", from, to)),
+    }
+}
+
+/// Copy `from` to `to`, then remove `from`; a partial copy is removed again on failure.
+fn copy_then_remove(from: &Path, to: &Path) -> Result<()> {
+    if let Err(err) = copy_path(from, to) {
+        let _ = remove_path(to);
+        return Err(err).with_context(|| format!("Failed to copy {:?} to {:?}", from, to));
+    }
+    remove_path(from)
+}
+
+/// Copy a file, symlink or directory tree without following symlinks, keeping permissions.
+fn copy_path(from: &Path, to: &Path) -> io::Result<()> {
+    let metadata = from.symlink_metadata()?;
+    if metadata.file_type().is_symlink() {
+        symlink(fs::read_link(from)?, to)
+    } else if metadata.is_dir() {
+        fs::create_dir(to)?;
+        for entry in fs::read_dir(from)? {
+            let entry = entry?;
+            copy_path(&entry.path(), &to.join(entry.file_name()))?;
+        }
+        fs::set_permissions(to, metadata.permissions())
+    } else {
+        fs::copy(from, to).map(|_| ())
+    }
+}
+
+/// Rules from one `.dwsignore` file, applied to the directory containing it.
+///
+/// Patterns follow gitignore semantics. Lines of the form `@files <dir>` / `@dir <dir>` are
+/// link-mode directives rather than patterns; `<dir>` is relative to the file's directory.
+struct IgnoreFile {
+    rules: Gitignore,
+    directives: BTreeMap<PathBuf, LinkMode>,
+}
+
+impl IgnoreFile {
+    /// Load `dir/.dwsignore`, matching its patterns against paths under `root`.
+    ///
+    /// `root` is the corresponding base directory, so an overlay's file applies to the merged
+    /// tree exactly like one in `config/` would.
+    fn load(dir: &Path, root: &Path) -> Result<Option<Self>> {
+        let path = dir.join(".dwsignore");
+        if !path.is_file() {
+            return Ok(None);
+        }
+
+        let contents =
+            fs::read_to_string(&path).with_context(|| format!("Failed to read {:?}", path))?;
+        let mut builder = GitignoreBuilder::new(root);
+        let mut directives = BTreeMap::new();
+
+        for line in contents.lines() {
+            let trimmed = line.trim();
+            let directive = trimmed
+                .strip_prefix("@files ")
+                .map(|target| (target, LinkMode::Files))
+                .or_else(|| {
+                    trimmed
+                        .strip_prefix("@dir ")
+                        .map(|target| (target, LinkMode::Directory))
+                });
+            if let Some((target, mode)) = directive {
+                directives.insert(root.join(target.trim().trim_matches('/')), mode);
+                continue;
+            }
+
+            builder
+                .add_line(Some(path.clone()), line)
+                .with_context(|| format!("Invalid pattern {:?} in {:?}", line, path))?;
+        }
+
+        let rules = builder
+            .build()
+            .with_context(|| format!("Failed to compile patterns in {:?}", path))?;
+        Ok(Some(Self { rules, directives }))
     }
 }
 
+/// Built-in ignores match by file name anywhere and cannot be negated.
+fn is_builtin_ignore(path: &Path) -> bool {
+    path.file_name()
+        .and_then(|name| name.to_str())
+        .is_some_and(|name| BUILTIN_IGNORES.contains(&name))
+}
+
+/// Evaluate the stacked `.dwsignore` files, nearest first, like git does.
+fn is_ignored(ignores: &[IgnoreFile], path: &Path, is_dir: bool) -> bool {
+    for file in ignores.iter().rev() {
+        match file.rules.matched(path, is_dir) {
+            Match::Ignore(_) => return true,
+            Match::Whitelist(_) => return false,
+            Match::None => {}
+        }
+    }
+    false
+}
+
+/// A directory entry found in one layer (base or overlay) while walking the merged tree.
+#[derive(Clone)]
+struct LayerChild<'a> {
+    /// Overlay name, `None` for the base directory
+    layer: Option<&'a str>,
+    path: PathBuf,
+    is_dir: bool,
+}
+
 /// Manages configuration files (dotfiles) for the workspace
 pub struct Dotfiles {
     /// Path to the workspace's config directory
     config_dir: PathBuf,
     /// Target directory ($XDG_CONFIG_HOME, default: ~/.config)
     target_dir: PathBuf,
-    /// Ignore patterns loaded from .dwsignore
-    ignore_patterns: Vec<String>,
+    /// Link mode for directories without a marker file or directive
+    link_mode: LinkMode,
+    /// How targets that dws does not manage are handled
+    conflict_policy: ConflictPolicy,
+    /// Variables for rendering `*.tmpl` entries
+    template_vars: TemplateVars,
+    /// Active overlay directories (name, path), least specific first
+    overlays: Vec<(String, PathBuf)>,
+    /// age identity file for `*.age` entries
+    identity: Option<PathBuf>,
 }
 
 impl Dotfiles {
     /// Create a new profile configuration
     pub fn new(config_dir: PathBuf, target_dir: PathBuf) -> Self {
-        let ignore_patterns = Self::load_ignore_patterns(&config_dir);
         Self {
             config_dir,
             target_dir,
-            ignore_patterns,
+            link_mode: LinkMode::default(),
+            conflict_policy: ConflictPolicy::default(),
+            template_vars: TemplateVars::default(),
+            overlays: Vec::new(),
+            identity: None,
         }
     }
 
-    /// Load ignore patterns from .dwsignore file
-    fn load_ignore_patterns(config_dir: &Path) -> Vec<String> {
-        let ignore_file = config_dir.join(".dwsignore");
+    /// Set the default link mode for directories.
+    pub fn with_link_mode(mut self, link_mode: LinkMode) -> Self {
+        self.link_mode = link_mode;
+        self
+    }
+
+    /// Set the policy for existing targets not managed by dws.
+    pub fn with_conflict_policy(mut self, policy: ConflictPolicy) -> Self {
+        self.conflict_policy = policy;
+        self
+    }
+
+    pub fn conflict_policy(&self) -> ConflictPolicy {
+        self.conflict_policy
+    }
+
+    /// Set the variables available to `*.tmpl` entries.
+    pub fn with_template_vars(mut self, vars: TemplateVars) -> Self {
+        self.template_vars = vars;
+        self
+    }
+
+    /// Merge sibling overlay directories (`config@<tag>`) that match this machine.
+    ///
+    /// `<tag>` is one of `tags` (as produced by `toolset::platform_tags`) or `host-<host_slug>`.
+    /// Overlays win per file over the base directory and over less specific overlays: a host
+    /// overlay beats `os-arch`/`os-distro` tags, which beat a bare `os` tag.
+    pub fn with_overlays(mut self, tags: &HashSet<String>, host_slug: Option<&str>) -> Self {
+        let (Some(parent), Some(base)) = (
+            self.config_dir.parent(),
+            self.config_dir.file_name().and_then(|name| name.to_str()),
+        ) else {
+            return self;
+        };
+        let Ok(siblings) = fs::read_dir(parent) else {
+            return self;
+        };
 
-        if !ignore_file.exists() {
-            return Vec::new();
+        let host_tag = host_slug.map(|slug| format!("host-{slug}"));
+        let mut overlays: Vec<(u8, String, PathBuf)> = siblings
+            .filter_map(|entry| entry.ok())
+            .filter(|entry| entry.path().is_dir())
+            .filter_map(|entry| {
+                let name = entry.file_name().to_str()?.to_string();
+                let tag = name
+                    .strip_prefix(base)?
+                    .strip_prefix('@')?
+                    .to_ascii_lowercase();
+                let rank = if host_tag.as_deref() == Some(tag.as_str()) {
+                    3
+                } else if tags.contains(&tag) {
+                    if tag.contains('-') {
+                        2
+                    } else {
+                        1
+                    }
+                } else {
+                    return None;
+                };
+                Some((rank, name, entry.path()))
+            })
+            .collect();
+        overlays.sort();
+
+        self.overlays = overlays
+            .into_iter()
+            .map(|(_, name, path)| (name, path))
+            .collect();
+        self
+    }
+
+    /// Base directory followed by the active overlays, least specific first.
+    fn layers(&self) -> impl Iterator<Item = (Option<&str>, &Path)> {
+        std::iter::once((None, self.config_dir.as_path())).chain(
+            self.overlays
+                .iter()
+                .map(|(name, path)| (Some(name.as_str()), path.as_path())),
+        )
+    }
+
+    /// Set the age identity file used to decrypt `*.age` entries.
+    pub fn with_identity(mut self, path: PathBuf) -> Self {
+        self.identity = Some(path);
+        self
+    }
+
+    /// Install one discovered entry: render templates, decrypt secrets, symlink everything else.
+    pub fn install_entry(&self, entry: &DotfileEntry, backup_dir: &Path) -> Result<InstallOutcome> {
+        if entry.is_template() {
+            let contents = self.template_vars.render(&entry.source)?;
+            entry.install_rendered(contents.as_bytes(), self.conflict_policy, backup_dir)
+        } else if entry.is_secret() {
+            let identity = self.identity.as_deref().with_context(|| {
+                format!("No age identity configured to decrypt {:?}", entry.source)
+            })?;
+            let contents = Keyring::load(identity)?.decrypt(&entry.source)?;
+            entry.install_rendered(&contents, self.conflict_policy, backup_dir)
+        } else {
+            entry.install(self.conflict_policy, backup_dir)
         }
+    }
 
-        fs::read_to_string(&ignore_file)
-            .unwrap_or_default()
-            .lines()
-            .map(|line| line.trim())
-            .filter(|line| !line.is_empty() && !line.starts_with('#'))
-            .map(|line| line.to_string())
-            .collect()
+    /// Predict what [`Dotfiles::install_entry`] would do, rendering in memory only.
+    pub fn plan_entry(&self, entry: &DotfileEntry, cleared: bool) -> Result<EntryPlan> {
+        let rendered = if entry.is_template() {
+            Some(self.template_vars.render(&entry.source)?.into_bytes())
+        } else if entry.is_secret() {
+            match self
+                .identity
+                .as_deref()
+                .filter(|identity| identity.exists())
+            {
+                Some(identity) => Some(Keyring::load(identity)?.decrypt(&entry.source)?),
+                None => None,
+            }
+        } else {
+            None
+        };
+        Ok(entry.plan(self.conflict_policy, rendered.as_deref(), cleared))
+    }
+
+    /// Resolve the link mode for the merged directory at `base`, found in `layer_dirs`.
+    ///
+    /// Marker files (in any layer) win over .dwsignore directives (the nearest file first),
+    /// which win over the inherited mode.
+    fn link_mode_for(
+        layer_dirs: &[PathBuf],
+        base: &Path,
+        ignores: &[IgnoreFile],
+        inherited: LinkMode,
+    ) -> LinkMode {
+        let has_marker = |marker: &str| layer_dirs.iter().any(|dir| dir.join(marker).exists());
+        if has_marker(LINK_FILES_MARKER) {
+            LinkMode::Files
+        } else if has_marker(LINK_DIR_MARKER) {
+            LinkMode::Directory
+        } else {
+            ignores
+                .iter()
+                .rev()
+                .find_map(|file| file.directives.get(base).copied())
+                .unwrap_or(inherited)
+        }
     }
 
     /// Discover all config entries in the profile's config directory
+    ///
+    /// Directories in [`LinkMode::Directory`] become a single entry; directories in
+    /// [`LinkMode::Files`] are walked so every file gets its own entry.
     pub fn discover_entries(&self) -> Result<Vec<DotfileEntry>> {
         let mut entries = Vec::new();
 
-        if !self.config_dir.exists() {
+        if !self.layers().any(|(_, dir)| dir.exists()) {
             return Ok(entries);
         }
 
-        for entry in fs::read_dir(&self.config_dir)
-            .with_context(|| format!("Failed to read config directory {:?}", self.config_dir))?
-        {
-            let entry = entry?;
-            let source = entry.path();
+        let mut ignores = Vec::new();
+        self.collect_entries(Path::new(""), self.link_mode, &mut ignores, &mut entries)?;
+        Ok(entries)
+    }
 
-            let relative = source
-                .strip_prefix(&self.config_dir)
-                .context("Failed to get relative path")?;
+    /// Walk `relative` in every layer at once. Ignore rules and directives are always evaluated
+    /// against the base directory's paths, so overlays behave as if merged into `config/`.
+    fn collect_entries(
+        &self,
+        relative: &Path,
+        mode: LinkMode,
+        ignores: &mut Vec<IgnoreFile>,
+        entries: &mut Vec<DotfileEntry>,
+    ) -> Result<()> {
+        let base_dir = self.config_dir.join(relative);
+        let layer_dirs: Vec<(Option<&str>, PathBuf)> = self
+            .layers()
+            .map(|(name, root)| (name, root.join(relative)))
+            .filter(|(_, dir)| dir.is_dir())
+            .collect();
+
+        // Each walked directory may carry its own .dwsignore, scoped to that subtree.
+        let mut pushed = 0;
+        for (_, dir) in &layer_dirs {
+            if let Some(file) = IgnoreFile::load(dir, &base_dir)? {
+                ignores.push(file);
+                pushed += 1;
+            }
+        }
 
-            let target = self.target_dir.join(relative);
+        // Later (more specific) layers replace earlier ones per name.
+        let mut children: BTreeMap<OsString, Vec<LayerChild>> = BTreeMap::new();
+        for (layer, dir) in &layer_dirs {
+            for child in fs::read_dir(dir)
+                .with_context(|| format!("Failed to read config directory {:?}", dir))?
+            {
+                let child =
+                    child.with_context(|| format!("Failed to read config directory {:?}", dir))?;
+                // Symlinked directories inside the profile are linked as-is rather than walked.
+                let is_dir = child.file_type()?.is_dir();
+                children
+                    .entry(child.file_name())
+                    .or_default()
+                    .push(LayerChild {
+                        layer: *layer,
+                        path: child.path(),
+                        is_dir,
+                    });
+            }
+        }
 
-            let config_entry = DotfileEntry::new(source, target);
+        for (name, candidates) in children {
+            let Some(LayerChild {
+                layer,
+                path: source,
+                is_dir,
+            }) = candidates.last().cloned()
+            else {
+                continue;
+            };
+            let base_source = base_dir.join(&name);
+            let child_relative = relative.join(&name);
+            let target = self.target_dir.join(&child_relative);
 
             // Skip ignored entries
-            if config_entry.should_ignore(&self.ignore_patterns) {
+            if is_builtin_ignore(&base_source) || is_ignored(ignores, &base_source, is_dir) {
                 continue;
             }
 
-            entries.push(config_entry);
+            if is_dir {
+                let child_dirs: Vec<PathBuf> = candidates
+                    .iter()
+                    .filter(|child| child.is_dir)
+                    .map(|child| child.path.clone())
+                    .collect();
+                // Templates and secrets are written individually, overlays merge per file and
+                // ignore rules can exclude single files deep down, so those directories are
+                // walked even when linked as a whole.
+                let child_mode = Self::link_mode_for(&child_dirs, &base_source, ignores, mode);
+                if child_mode == LinkMode::Files
+                    || child_dirs.len() > 1
+                    || child_dirs
+                        .iter()
+                        .any(|dir| needs_walk(dir, &base_source, ignores))
+                {
+                    self.collect_entries(&child_relative, child_mode, ignores, entries)?;
+                    continue;
+                }
+            }
+
+            let target = match target.to_str().and_then(|path| {
+                path.strip_suffix(&format!(".{TEMPLATE_EXTENSION}"))
+                    .or_else(|| path.strip_suffix(&format!(".{SECRET_EXTENSION}")))
+            }) {
+                Some(rendered) if !is_dir => PathBuf::from(rendered),
+                _ => target,
+            };
+            let mut entry = DotfileEntry::new(source, target);
+            entry.overlay = layer.map(str::to_string);
+            entries.push(entry);
         }
 
-        Ok(entries)
+        ignores.truncate(ignores.len() - pushed);
+        Ok(())
     }
 
-    /// Install all discovered config entries
-    pub fn install(&self) -> Result<Vec<PathBuf>> {
+    /// Install all discovered config entries, returning the linked targets
+    pub fn install(&self, backup_dir: &Path) -> Result<Vec<PathBuf>> {
         let entries = self.discover_entries()?;
         let mut installed = Vec::new();
 
         for entry in &entries {
-            entry.install()?;
-            installed.push(entry.target.clone());
+            if self.install_entry(entry, backup_dir)? != InstallOutcome::Skipped {
+                installed.push(entry.target.clone());
+            }
         }
 
         Ok(installed)
@@ -334,8 +981,8 @@ mod tests {
             PathBuf::from("/home/README.md"),
         );
 
-        assert!(entry.matches_pattern("README.md"));
-        assert!(!entry.matches_pattern("LICENSE"));
+        assert!(entry.matches_pattern("README.md").unwrap());
+        assert!(!entry.matches_pattern("LICENSE").unwrap());
     }
 
     #[test]
@@ -345,8 +992,8 @@ mod tests {
             PathBuf::from("/home/test.log"),
         );
 
-        assert!(entry.matches_pattern("*.log"));
-        assert!(!entry.matches_pattern("*.tmp"));
+        assert!(entry.matches_pattern("*.log").unwrap());
+        assert!(!entry.matches_pattern("*.tmp").unwrap());
     }
 
     #[test]
@@ -357,19 +1004,19 @@ mod tests {
             PathBuf::from("/config/README.md"),
             PathBuf::from("/home/README.md"),
         );
-        assert!(readme.should_ignore(&patterns));
+        assert!(readme.should_ignore(&patterns).unwrap());
 
         let log = DotfileEntry::new(
             PathBuf::from("/config/test.log"),
             PathBuf::from("/home/test.log"),
         );
-        assert!(log.should_ignore(&patterns));
+        assert!(log.should_ignore(&patterns).unwrap());
 
         let zshrc = DotfileEntry::new(
             PathBuf::from("/config/.zshrc"),
             PathBuf::from("/home/.zshrc"),
         );
-        assert!(!zshrc.should_ignore(&patterns));
+        assert!(!zshrc.should_ignore(&patterns).unwrap());
     }
 
     #[test]
@@ -377,13 +1024,13 @@ mod tests {
         let patterns = vec![];
 
         let git = DotfileEntry::new(PathBuf::from("/config/.git"), PathBuf::from("/home/.git"));
-        assert!(git.should_ignore(&patterns));
+        assert!(git.should_ignore(&patterns).unwrap());
 
         let ds_store = DotfileEntry::new(
             PathBuf::from("/config/.DS_Store"),
             PathBuf::from("/home/.DS_Store"),
         );
-        assert!(ds_store.should_ignore(&patterns));
+        assert!(ds_store.should_ignore(&patterns).unwrap());
     }
 
     #[test]
@@ -398,7 +1045,7 @@ mod tests {
         fs::write(profile_config_dir.join("zsh/.zshrc"), "test").unwrap();
 
         let config = Dotfiles::new(profile_config_dir.clone(), xdg_config_home.clone());
-        let installed = config.install().unwrap();
+        let installed = config.install(&temp.path().join("backups")).unwrap();
 
         assert_eq!(installed.len(), 1);
         assert_eq!(installed[0], xdg_config_home.join("zsh"));
@@ -438,4 +1085,513 @@ mod tests {
         assert!(!entry_path.exists());
         assert!(regular_dir.exists());
     }
+
+    fn nested_profile(temp: &TempDir) -> (PathBuf, PathBuf) {
+        let profile_config_dir = temp.path().join("profile/config");
+        let xdg_config_home = temp.path().join("xdg_config");
+        fs::create_dir_all(profile_config_dir.join("nvim/lua/plugins")).unwrap();
+        fs::create_dir_all(profile_config_dir.join("git")).unwrap();
+        fs::create_dir_all(&xdg_config_home).unwrap();
+        fs::write(profile_config_dir.join("nvim/init.lua"), "init").unwrap();
+        fs::write(profile_config_dir.join("nvim/lua/plugins/lsp.lua"), "lsp").unwrap();
+        fs::write(profile_config_dir.join("git/config"), "git").unwrap();
+        fs::write(profile_config_dir.join("starship.toml"), "prompt").unwrap();
+        (profile_config_dir, xdg_config_home)
+    }
+
+    fn targets(dotfiles: &Dotfiles, root: &Path) -> Vec<PathBuf> {
+        dotfiles
+            .discover_entries()
+            .unwrap()
+            .into_iter()
+            .map(|entry| entry.target.strip_prefix(root).unwrap().to_path_buf())
+            .collect()
+    }
+
+    #[test]
+    fn test_files_mode_links_each_file() {
+        let temp = TempDir::new().unwrap();
+        let (config_dir, home) = nested_profile(&temp);
+
+        let dotfiles =
+            Dotfiles::new(config_dir.clone(), home.clone()).with_link_mode(LinkMode::Files);
+        assert_eq!(
+            targets(&dotfiles, &home),
+            vec![
+                PathBuf::from("git/config"),
+                PathBuf::from("nvim/init.lua"),
+                PathBuf::from("nvim/lua/plugins/lsp.lua"),
+                PathBuf::from("starship.toml"),
+            ]
+        );
+
+        // Machine-local state can live next to managed files.
+        fs::create_dir_all(home.join("nvim")).unwrap();
+        fs::write(home.join("nvim/lazy-lock.json"), "local").unwrap();
+
+        dotfiles.install(&temp.path().join("backups")).unwrap();
+        assert!(!home.join("nvim").symlink_metadata().unwrap().is_symlink());
+        assert_eq!(
+            fs::read_link(home.join("nvim/lua/plugins/lsp.lua")).unwrap(),
+            config_dir.join("nvim/lua/plugins/lsp.lua")
+        );
+        assert!(home.join("nvim/lazy-lock.json").exists());
+        assert!(!config_dir.join("nvim/lazy-lock.json").exists());
+    }
+
+    #[test]
+    fn test_marker_files_override_default_mode() {
+        let temp = TempDir::new().unwrap();
+        let (config_dir, home) = nested_profile(&temp);
+        fs::write(config_dir.join("nvim/.dws-link-files"), "").unwrap();
+        fs::write(config_dir.join("nvim/lua/.dws-link-dir"), "").unwrap();
+
+        let dotfiles = Dotfiles::new(config_dir, home.clone());
+        assert_eq!(
+            targets(&dotfiles, &home),
+            vec![
+                PathBuf::from("git"),
+                PathBuf::from("nvim/init.lua"),
+                PathBuf::from("nvim/lua"),
+                PathBuf::from("starship.toml"),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_dwsignore_directives_set_link_mode() {
+        let temp = TempDir::new().unwrap();
+        let (config_dir, home) = nested_profile(&temp);
+        fs::write(
+            config_dir.join(".dwsignore"),
+            "@dir git\n@files nvim/\n*.toml\n",
+        )
+        .unwrap();
+
+        let dotfiles = Dotfiles::new(config_dir, home.clone()).with_link_mode(LinkMode::Files);
+        assert_eq!(
+            targets(&dotfiles, &home),
+            vec![
+                PathBuf::from("git"),
+                PathBuf::from("nvim/init.lua"),
+                PathBuf::from("nvim/lua/plugins/lsp.lua"),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_install_refuses_to_replace_source_through_directory_link() {
+        let temp = TempDir::new().unwrap();
+        let (config_dir, home) = nested_profile(&temp);
+        symlink(config_dir.join("git"), home.join("git")).unwrap();
+
+        let entry = DotfileEntry::new(config_dir.join("git/config"), home.join("git/config"));
+        let err = entry
+            .install(ConflictPolicy::Overwrite, &temp.path().join("backups"))
+            .unwrap_err();
+        assert!(err.to_string().contains("resolves into the profile"));
+        assert_eq!(
+            fs::read_to_string(config_dir.join("git/config")).unwrap(),
+            "git"
+        );
+    }
+
+    #[test]
+    fn test_install_conflict_policies_for_existing_file() {
+        let temp = TempDir::new().unwrap();
+        let (config_dir, home) = nested_profile(&temp);
+        let backups = temp.path().join("backups");
+        let source = config_dir.join("starship.toml");
+        let target = home.join("starship.toml");
+        let entry = DotfileEntry::new(source.clone(), target.clone());
+
+        fs::write(&target, "distro").unwrap();
+        assert_eq!(
+            entry.install(ConflictPolicy::Skip, &backups).unwrap(),
+            InstallOutcome::Skipped
+        );
+        assert_eq!(fs::read_to_string(&target).unwrap(), "distro");
+
+        let outcome = entry.install(ConflictPolicy::Backup, &backups).unwrap();
+        let backup = backups.join(target.strip_prefix("/").unwrap());
+        assert_eq!(outcome, InstallOutcome::BackedUp(backup.clone()));
+        assert_eq!(fs::read_to_string(&backup).unwrap(), "distro");
+        assert_eq!(fs::read_link(&target).unwrap(), source);
+
+        // Re-installing over our own link is not a conflict.
+        assert_eq!(
+            entry.install(ConflictPolicy::Skip, &backups).unwrap(),
+            InstallOutcome::Linked
+        );
+
+        fs::remove_file(&target).unwrap();
+        fs::write(&target, "local").unwrap();
+        assert_eq!(
+            entry.install(ConflictPolicy::Adopt, &backups).unwrap(),
+            InstallOutcome::Adopted
+        );
+        assert_eq!(fs::read_to_string(&source).unwrap(), "local");
+        assert_eq!(fs::read_link(&target).unwrap(), source);
+        assert!(!config_dir.join("starship.toml.dws-replaced").exists());
+    }
+
+    #[test]
+    fn test_copy_then_remove_moves_trees_with_symlinks() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let temp = TempDir::new().unwrap();
+        let from = temp.path().join("from");
+        fs::create_dir_all(from.join("nested")).unwrap();
+        fs::write(from.join("nested/config"), "config").unwrap();
+        fs::write(from.join("run.sh"), "#!/bin/sh\n").unwrap();
+        fs::set_permissions(from.join("run.sh"), fs::Permissions::from_mode(0o755)).unwrap();
+        symlink("nested/config", from.join("link")).unwrap();
+
+        let to = temp.path().join("backups/to");
+        fs::create_dir_all(to.parent().unwrap()).unwrap();
+        copy_then_remove(&from, &to).unwrap();
+
+        assert!(!from.exists());
+        assert_eq!(
+            fs::read_to_string(to.join("nested/config")).unwrap(),
+            "config"
+        );
+        let mode = fs::metadata(to.join("run.sh"))
+            .unwrap()
+            .permissions()
+            .mode();
+        assert_eq!(mode & 0o777, 0o755);
+        assert_eq!(
+            fs::read_link(to.join("link")).unwrap(),
+            PathBuf::from("nested/config")
+        );
+    }
+
+    #[test]
+    fn test_install_conflict_with_existing_directory() {
+        let temp = TempDir::new().unwrap();
+        let (config_dir, home) = nested_profile(&temp);
+        let backups = temp.path().join("backups");
+        fs::create_dir_all(home.join("git")).unwrap();
+        fs::write(home.join("git/config"), "distro").unwrap();
+
+        let entry = DotfileEntry::new(config_dir.join("git"), home.join("git"));
+        let outcome = entry.install(ConflictPolicy::Backup, &backups).unwrap();
+        let InstallOutcome::BackedUp(backup) = outcome else {
+            panic!("expected a backup, got {outcome:?}");
+        };
+        assert_eq!(fs::read_to_string(backup.join("config")).unwrap(), "distro");
+        assert_eq!(
+            fs::read_link(home.join("git")).unwrap(),
+            config_dir.join("git")
+        );
+
+        fs::remove_file(home.join("git")).unwrap();
+        fs::create_dir_all(home.join("git")).unwrap();
+        fs::write(home.join("git/ignore"), "*.swp").unwrap();
+        assert_eq!(
+            entry.install(ConflictPolicy::Overwrite, &backups).unwrap(),
+            InstallOutcome::Overwritten
+        );
+        assert_eq!(fs::read_to_string(home.join("git/config")).unwrap(), "git");
+    }
+
+    #[test]
+    fn test_templates_are_rendered_per_file() {
+        let temp = TempDir::new().unwrap();
+        let (config_dir, home) = nested_profile(&temp);
+        fs::write(config_dir.join("git/ignore"), "*.swp").unwrap();
+        fs::write(
+            config_dir.join("git/config.tmpl"),
+            "[user]\n\tname = {{ username }}\n",
+        )
+        .unwrap();
+        fs::remove_file(config_dir.join("git/config")).unwrap();
+
+        let dotfiles =
+            Dotfiles::new(config_dir.clone(), home.clone()).with_template_vars(TemplateVars {
+                username: "sam".to_string(),
+                ..TemplateVars::default()
+            });
+        // The directory holding a template is split even in directory mode.
+        assert_eq!(
+            targets(&dotfiles, &home),
+            vec![
+                PathBuf::from("git/config"),
+                PathBuf::from("git/ignore"),
+                PathBuf::from("nvim"),
+                PathBuf::from("starship.toml"),
+            ]
+        );
+
+        let backups = temp.path().join("backups");
+        dotfiles.install(&backups).unwrap();
+        let rendered = home.join("git/config");
+        assert!(!fs::symlink_metadata(&rendered).unwrap().is_symlink());
+        assert_eq!(
+            fs::read_to_string(&rendered).unwrap(),
+            "[user]\n\tname = sam\n"
+        );
+
+        // Unchanged output is not a conflict; an edited copy is.
+        let entry = DotfileEntry::new(config_dir.join("git/config.tmpl"), rendered.clone());
+        assert!(entry.is_template());
+        assert_eq!(
+            dotfiles.install_entry(&entry, &backups).unwrap(),
+            InstallOutcome::Linked
+        );
+        fs::write(&rendered, "edited").unwrap();
+        assert!(matches!(
+            dotfiles.install_entry(&entry, &backups).unwrap(),
+            InstallOutcome::BackedUp(_)
+        ));
+    }
+
+    #[test]
+    fn test_overlays_merge_per_file_most_specific_wins() {
+        let temp = TempDir::new().unwrap();
+        let (config_dir, home) = nested_profile(&temp);
+        let profile = config_dir.parent().unwrap();
+        for (overlay, file, contents) in [
+            ("config@linux", "git/config", "linux"),
+            ("config@linux", "starship.toml", "linux"),
+            ("config@linux-x86_64", "starship.toml", "arch"),
+            ("config@host-work", "git/config", "host"),
+            ("config@host-work", "git/work.inc", "host"),
+            ("config@macos", "git/config", "macos"),
+        ] {
+            let path = profile.join(overlay).join(file);
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            fs::write(path, contents).unwrap();
+        }
+
+        let tags = HashSet::from(["linux".to_string(), "linux-x86_64".to_string()]);
+        let dotfiles =
+            Dotfiles::new(config_dir.clone(), home.clone()).with_overlays(&tags, Some("work"));
+        let entries: Vec<(PathBuf, PathBuf, Option<String>)> = dotfiles
+            .discover_entries()
+            .unwrap()
+            .into_iter()
+            .map(|entry| {
+                (
+                    entry.target.strip_prefix(&home).unwrap().to_path_buf(),
+                    entry.source.strip_prefix(profile).unwrap().to_path_buf(),
+                    entry.overlay,
+                )
+            })
+            .collect();
+
+        let host = Some("config@host-work".to_string());
+        assert_eq!(
+            entries,
+            vec![
+                (
+                    PathBuf::from("git/config"),
+                    PathBuf::from("config@host-work/git/config"),
+                    host.clone()
+                ),
+                (
+                    PathBuf::from("git/work.inc"),
+                    PathBuf::from("config@host-work/git/work.inc"),
+                    host
+                ),
+                // Untouched directories are still linked whole from the base.
+                (PathBuf::from("nvim"), PathBuf::from("config/nvim"), None),
+                (
+                    PathBuf::from("starship.toml"),
+                    PathBuf::from("config@linux-x86_64/starship.toml"),
+                    Some("config@linux-x86_64".to_string())
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_overlay_dwsignore_applies_to_merged_tree() {
+        let temp = TempDir::new().unwrap();
+        let (config_dir, home) = nested_profile(&temp);
+        let overlay = config_dir.parent().unwrap().join("config@linux");
+        fs::create_dir_all(overlay.join("git")).unwrap();
+        fs::write(overlay.join("git/credentials"), "secret").unwrap();
+        fs::write(
+            overlay.join(".dwsignore"),
+            "starship.toml\ngit/credentials\n",
+        )
+        .unwrap();
+
+        let tags = HashSet::from(["linux".to_string()]);
+        let dotfiles = Dotfiles::new(config_dir, home.clone()).with_overlays(&tags, None);
+        assert_eq!(
+            targets(&dotfiles, &home),
+            vec![PathBuf::from("git/config"), PathBuf::from("nvim")]
+        );
+    }
+
+    /// Lay out `files` under a fresh `config/`, write `ignore` as its .dwsignore and return the
+    /// discovered targets (per-file mode) relative to the target directory.
+    fn discover_with_ignore(files: &[&str], ignore: &str) -> Vec<String> {
+        discover_with_ignore_in(LinkMode::Files, files, ignore)
+    }
+
+    fn discover_with_ignore_in(mode: LinkMode, files: &[&str], ignore: &str) -> Vec<String> {
+        let temp = TempDir::new().unwrap();
+        let config_dir = temp.path().join("config");
+        let home = temp.path().join("home");
+        for file in files {
+            let path = config_dir.join(file);
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            fs::write(path, "").unwrap();
+        }
+        fs::create_dir_all(&config_dir).unwrap();
+        fs::write(config_dir.join(".dwsignore"), ignore).unwrap();
+
+        Dotfiles::new(config_dir, home.clone())
+            .with_link_mode(mode)
+            .discover_entries()
+            .unwrap()
+            .into_iter()
+            .map(|entry| {
+                entry
+                    .target
+                    .strip_prefix(&home)
+                    .unwrap()
+                    .to_string_lossy()
+                    .into_owned()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_dwsignore_double_star() {
+        let found = discover_with_ignore(
+            &[
+                "a/cache/x",
+                "a/b/cache/y",
+                "a/keep",
+                "logs/deep/z.log",
+                "top.log",
+            ],
+            "**/cache/\nlogs/**/*.log\n",
+        );
+        assert_eq!(found, vec!["a/keep", "top.log"]);
+    }
+
+    #[test]
+    fn test_dwsignore_question_mark_and_character_class() {
+        let found = discover_with_ignore(
+            &[
+                "app/file1.bak",
+                "app/file22.bak",
+                "app/v1.tmp",
+                "app/vx.tmp",
+            ],
+            "file?.bak\nv[0-9].tmp\n",
+        );
+        assert_eq!(found, vec!["app/file22.bak", "app/vx.tmp"]);
+    }
+
+    #[test]
+    fn test_dwsignore_anchored_pattern() {
+        let found = discover_with_ignore(
+            &["README.md", "nvim/README.md", "docs/notes"],
+            "/README.md\n/docs\n",
+        );
+        assert_eq!(found, vec!["nvim/README.md"]);
+    }
+
+    #[test]
+    fn test_dwsignore_directory_only_pattern() {
+        let found = discover_with_ignore(
+            &["manifests/tools.toml", "zsh/manifests", "zsh/.zshrc"],
+            "manifests/\n",
+        );
+        assert_eq!(found, vec!["zsh/.zshrc", "zsh/manifests"]);
+    }
+
+    #[test]
+    fn test_dwsignore_negation() {
+        let found = discover_with_ignore(
+            &["git/a.local", "git/keep.local", "git/config"],
+            "*.local\n!keep.local\n",
+        );
+        assert_eq!(found, vec!["git/config", "git/keep.local"]);
+    }
+
+    #[test]
+    fn test_dwsignore_nested_files_are_scoped() {
+        let temp = TempDir::new().unwrap();
+        let config_dir = temp.path().join("config");
+        let home = temp.path().join("home");
+        for file in [
+            "nvim/init.lua",
+            "nvim/lazy-lock.json",
+            "nvim/plugin.json",
+            "other.json",
+        ] {
+            let path = config_dir.join(file);
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            fs::write(path, "").unwrap();
+        }
+        fs::write(config_dir.join(".dwsignore"), "*.json\n").unwrap();
+        // The nested file re-includes one file and ignores another, only inside nvim/.
+        fs::write(
+            config_dir.join("nvim/.dwsignore"),
+            "!plugin.json\n/init.lua\n",
+        )
+        .unwrap();
+
+        let found: Vec<_> = Dotfiles::new(config_dir, home.clone())
+            .with_link_mode(LinkMode::Files)
+            .discover_entries()
+            .unwrap()
+            .into_iter()
+            .map(|entry| entry.target.strip_prefix(&home).unwrap().to_path_buf())
+            .collect();
+        assert_eq!(found, vec![PathBuf::from("nvim/plugin.json")]);
+    }
+
+    #[test]
+    fn test_dwsignore_deep_pattern_in_directory_mode() {
+        let found = discover_with_ignore_in(
+            LinkMode::Directory,
+            &["nvim/init.lua", "nvim/lua/plugins.lua.bak", "git/config"],
+            "nvim/**/*.bak\n",
+        );
+        // nvim/ is walked so the backup stays out; git/ has nothing ignored and is linked whole.
+        assert_eq!(found, vec!["git", "nvim/init.lua"]);
+    }
+
+    #[test]
+    fn test_dwsignore_nested_file_in_directory_mode() {
+        let temp = TempDir::new().unwrap();
+        let config_dir = temp.path().join("config");
+        let home = temp.path().join("home");
+        for file in ["nvim/init.lua", "nvim/lazy-lock.json", "git/config"] {
+            let path = config_dir.join(file);
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            fs::write(path, "").unwrap();
+        }
+        fs::write(config_dir.join("nvim/.dwsignore"), "lazy-lock.json\n").unwrap();
+
+        assert_eq!(
+            targets(&Dotfiles::new(config_dir, home.clone()), &home),
+            vec![PathBuf::from("git"), PathBuf::from("nvim/init.lua")]
+        );
+    }
+
+    #[test]
+    fn test_should_ignore_rejects_malformed_pattern() {
+        let entry = DotfileEntry::new(PathBuf::from("/config/a"), PathBuf::from("/home/a"));
+        let err = entry.should_ignore(&["a[z-a]".to_string()]).unwrap_err();
+        assert!(format!("{err:#}").contains("a[z-a]"), "{err:#}");
+    }
+
+    #[test]
+    fn test_dwsignore_comments_and_escapes() {
+        let found = discover_with_ignore(
+            &["#notes", "a/b", "!important"],
+            "# comment\n\\#notes\n\\!important\n",
+        );
+        assert_eq!(found, vec!["a/b"]);
+    }
 }
@@ -1,6 +1,12 @@
-use anyhow::Result;
-use std::path::PathBuf;
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
 
+use crate::lockfile::Lockfile;
+use crate::project;
 use crate::workspace::{Workspace, WorkspacePath};
 
 /// Shell type for environment generation
@@ -9,14 +15,33 @@ pub enum Shell {
     Zsh,
     Bash,
     Fish,
+    Nushell,
+    PowerShell,
+    Elvish,
+    Xonsh,
 }
 
 impl Shell {
+    /// Every supported shell, in the order shown to users.
+    pub const ALL: [Shell; 7] = [
+        Shell::Zsh,
+        Shell::Bash,
+        Shell::Fish,
+        Shell::Nushell,
+        Shell::PowerShell,
+        Shell::Elvish,
+        Shell::Xonsh,
+    ];
+
     pub fn from_name(name: &str) -> Option<Self> {
         match name.to_lowercase().as_str() {
             "zsh" => Some(Shell::Zsh),
             "bash" => Some(Shell::Bash),
             "fish" => Some(Shell::Fish),
+            "nu" | "nushell" => Some(Shell::Nushell),
+            "pwsh" | "powershell" => Some(Shell::PowerShell),
+            "elvish" => Some(Shell::Elvish),
+            "xonsh" => Some(Shell::Xonsh),
             _ => None,
         }
     }
@@ -26,8 +51,167 @@ impl Shell {
             Shell::Zsh => "zsh",
             Shell::Bash => "bash",
             Shell::Fish => "fish",
+            Shell::Nushell => "nu",
+            Shell::PowerShell => "pwsh",
+            Shell::Elvish => "elvish",
+            Shell::Xonsh => "xonsh",
+        }
+    }
+
+    /// Comma-separated list of supported shell names for error messages.
+    pub fn supported_names() -> String {
+        Self::ALL
+            .iter()
+            .map(|shell| shell.as_str())
+            .collect::<Vec<_>>()
+            .join(", ")
+    }
+
+    /// Directory under `share/` where completions for this shell are linked.
+    ///
+    /// Each location follows the shell's own vendor layout so it can be wired up with the
+    /// standard search path (`fpath`, `XDG_DATA_DIRS`, `fish_complete_path`). Xonsh reuses
+    /// bash completions through its bash completer. Nushell, pwsh and elvish have no search
+    /// path, so `dws env` loads their scripts itself.
+    pub fn completion_dir(self, share_path: &Path) -> PathBuf {
+        match self {
+            Shell::Zsh => share_path.join("zsh/site-functions"),
+            Shell::Bash | Shell::Xonsh => share_path.join("bash-completion/completions"),
+            Shell::Fish => share_path.join("fish/vendor_completions.d"),
+            Shell::Nushell => share_path.join("nushell/completions"),
+            Shell::PowerShell => share_path.join("powershell/completions"),
+            Shell::Elvish => share_path.join("elvish/completions"),
+        }
+    }
+
+    /// Startup file (relative to `$HOME`) that `dws init` adds the integration line to.
+    pub fn rc_file(self) -> &'static str {
+        match self {
+            Shell::Zsh => ".zshenv",
+            Shell::Bash => ".bashrc",
+            Shell::Fish => ".config/fish/config.fish",
+            Shell::Nushell => ".config/nushell/env.nu",
+            Shell::PowerShell => ".config/powershell/Microsoft.PowerShell_profile.ps1",
+            Shell::Elvish => ".config/elvish/rc.elv",
+            Shell::Xonsh => ".xonshrc",
+        }
+    }
+
+    /// Line added to [`Shell::rc_file`] that evaluates `dws env` on startup.
+    pub fn integration_line(self) -> &'static str {
+        match self {
+            Shell::Zsh => "eval \"$(dws env --shell zsh)\"",
+            Shell::Bash => "eval \"$(dws env --shell bash)\"",
+            Shell::Fish => "dws env --shell fish | source",
+            // `source` needs a path that exists at parse time, so env.nu regenerates a vendor
+            // autoload script that nushell sources once config.nu has run.
+            Shell::Nushell => "mkdir ($nu.data-dir | path join 'vendor/autoload'); dws env --shell nu | save --force ($nu.data-dir | path join 'vendor/autoload/dws.nu')",
+            Shell::PowerShell => "Invoke-Expression (& dws env --shell pwsh | Out-String)",
+            Shell::Elvish => "eval (dws env --shell elvish | slurp)",
+            Shell::Xonsh => "execx($(dws env --shell xonsh))",
+        }
+    }
+}
+
+/// Value of a manifest-declared environment variable (`[env]` or a tool's `env = { ... }`).
+///
+/// Plain strings replace the variable; tables extend a `:`-separated path list:
+///
+/// ```toml
+/// [env]
+/// GOROOT = "{install_dir}/go"
+/// PATH = { prepend = ["{install_dir}/go/bin"] }
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum EnvValue {
+    Value(String),
+    PathList(PathList),
+}
+
+/// Entries added before and after the existing value of a path-list variable.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct PathList {
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub prepend: Vec<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub append: Vec<String>,
+}
+
+impl EnvValue {
+    /// Expand `{install_dir}`, `$HOME` and `${XDG_*}` references.
+    ///
+    /// `install_dir` is only available for per-tool variables; profile-level values referencing
+    /// it are rejected.
+    pub fn interpolate(&self, install_dir: Option<&Path>) -> Result<Self> {
+        Ok(match self {
+            EnvValue::Value(value) => EnvValue::Value(interpolate(value, install_dir)?),
+            EnvValue::PathList(list) => EnvValue::PathList(PathList {
+                prepend: list
+                    .prepend
+                    .iter()
+                    .map(|value| interpolate(value, install_dir))
+                    .collect::<Result<_>>()?,
+                append: list
+                    .append
+                    .iter()
+                    .map(|value| interpolate(value, install_dir))
+                    .collect::<Result<_>>()?,
+            }),
+        })
+    }
+}
+
+/// Whether `name` is usable as an environment variable name in every supported shell.
+pub(crate) fn is_valid_env_name(name: &str) -> bool {
+    let mut chars = name.chars();
+    matches!(chars.next(), Some(first) if first == '_' || first.is_ascii_alphabetic())
+        && chars.all(|ch| ch == '_' || ch.is_ascii_alphanumeric())
+}
+
+pub(crate) fn interpolate(value: &str, install_dir: Option<&Path>) -> Result<String> {
+    let replaced = if value.contains("{install_dir}") {
+        let dir = install_dir.with_context(|| {
+            format!("`{{install_dir}}` is only available in per-tool env (in '{value}')")
+        })?;
+        value.replace("{install_dir}", &dir.to_string_lossy())
+    } else {
+        value.to_string()
+    };
+
+    shellexpand::env_with_context(&replaced, lookup_variable)
+        .map(|expanded| expanded.into_owned())
+        .map_err(|err| anyhow::anyhow!("Failed to interpolate '{}': {}", value, err))
+}
+
+fn lookup_variable(name: &str) -> std::result::Result<Option<String>, String> {
+    let from_home = |suffix: &str| -> std::result::Result<Option<String>, String> {
+        let home = env::var("HOME").map(PathBuf::from).or_else(|_| {
+            directories::BaseDirs::new()
+                .map(|dirs| dirs.home_dir().to_path_buf())
+                .ok_or_else(|| "home directory is unavailable".to_string())
+        })?;
+        Ok(Some(home.join(suffix).to_string_lossy().into_owned()))
+    };
+
+    if let Ok(value) = env::var(name) {
+        if name == "HOME" || name.starts_with("XDG_") {
+            return Ok(Some(value));
         }
     }
+
+    match name {
+        "HOME" => from_home(""),
+        "XDG_CONFIG_HOME" => from_home(".config"),
+        "XDG_DATA_HOME" => from_home(".local/share"),
+        "XDG_STATE_HOME" => from_home(".local/state"),
+        "XDG_CACHE_HOME" => from_home(".cache"),
+        _ if name.starts_with("XDG_") => Err(format!("${name} is not set")),
+        _ => Err(format!(
+            "only $HOME and $XDG_* may be interpolated, found ${name}"
+        )),
+    }
 }
 
 /// Shell environment configuration
@@ -35,17 +219,45 @@ impl Shell {
 pub struct Environment {
     pub bin_path: PathBuf,
     pub man_path: PathBuf,
+    pub share_path: PathBuf,
+    /// Completion directory for the shell this environment was built for.
     pub completions_path: PathBuf,
+    /// Manifest-declared variables read from the lockfile, profile-level first then per tool.
+    pub variables: Vec<(String, EnvValue)>,
 }
 
 impl Environment {
     /// Create a new shell environment from workspace
-    pub fn new_from_workspace(workspace: &Workspace, _shell: Shell) -> Result<Self> {
+    pub fn new_from_workspace(workspace: &Workspace, shell: Shell) -> Result<Self> {
         let share_path = workspace.path(WorkspacePath::Share);
+
+        // Variables were interpolated at install time, so the lockfile is the only input.
+        let lockfile_path = workspace.path(WorkspacePath::Lockfile);
+        let mut variables = Vec::new();
+        if lockfile_path.exists() {
+            let lockfile = Lockfile::load(&lockfile_path)?;
+            variables.extend(
+                lockfile
+                    .env
+                    .iter()
+                    .map(|(name, value)| (name.clone(), value.clone())),
+            );
+            for receipt in lockfile.tool_receipts() {
+                variables.extend(
+                    receipt
+                        .env
+                        .iter()
+                        .map(|(name, value)| (name.clone(), value.clone())),
+                );
+            }
+        }
+
         Ok(Self {
             bin_path: workspace.path(WorkspacePath::Bin),
             man_path: share_path.join("man"),
-            completions_path: share_path.join("zsh/site-functions"),
+            completions_path: shell.completion_dir(&share_path),
+            share_path,
+            variables,
         })
     }
 
@@ -55,35 +267,403 @@ impl Environment {
             Shell::Zsh => self.format_zsh(),
             Shell::Bash => self.format_bash(),
             Shell::Fish => self.format_fish(),
+            Shell::Nushell => self.format_nushell(),
+            Shell::PowerShell => self.format_powershell(),
+            Shell::Elvish => self.format_elvish(),
+            Shell::Xonsh => self.format_xonsh(),
         }
     }
 
     fn format_zsh(&self) -> String {
-        format!(
+        let mut output = format!(
             "export PATH=\"{}:$PATH\"\nexport MANPATH=\"{}:${{MANPATH:-}}\"\nfpath=({} ${{fpath[@]}})",
             self.bin_path.display(),
             self.man_path.display(),
             self.completions_path.display()
-        )
+        );
+        self.append_posix_variables(&mut output);
+        append_project_hook(&mut output, Shell::Zsh);
+        output
     }
 
     fn format_bash(&self) -> String {
-        format!(
-            "export PATH=\"{}:$PATH\"\nexport MANPATH=\"{}:${{MANPATH:-}}\"",
+        // bash-completion discovers `<dir>/bash-completion/completions` through XDG_DATA_DIRS;
+        // without it loaded, fall back to sourcing every file eagerly.
+        let completions = Shell::Bash.completion_dir(&self.share_path);
+        let mut output = format!(
+            "export PATH=\"{}:$PATH\"\nexport MANPATH=\"{}:${{MANPATH:-}}\"\n\
+             export XDG_DATA_DIRS=\"{}:${{XDG_DATA_DIRS:-/usr/local/share:/usr/share}}\"\n\
+             if ! declare -F _completion_loader >/dev/null 2>&1; then\n\
+             \x20 for __dws_completion in \"{}\"/*; do [ -r \"$__dws_completion\" ] && . \"$__dws_completion\"; done\n\
+             \x20 unset __dws_completion\n\
+             fi",
             self.bin_path.display(),
-            self.man_path.display()
-        )
+            self.man_path.display(),
+            self.share_path.display(),
+            completions.display()
+        );
+        self.append_posix_variables(&mut output);
+        append_project_hook(&mut output, Shell::Bash);
+        output
     }
 
     fn format_fish(&self) -> String {
-        format!(
-            "set -gx PATH {} $PATH\nset -gx MANPATH {} $MANPATH",
+        let completions = Shell::Fish.completion_dir(&self.share_path);
+        let functions = self.share_path.join("fish/vendor_functions.d");
+        let mut output = format!(
+            "set -gx PATH {} $PATH\nset -gx MANPATH {} $MANPATH\n\
+             contains -- {completions} $fish_complete_path; or set -g fish_complete_path {completions} $fish_complete_path\n\
+             contains -- {functions} $fish_function_path; or set -g fish_function_path {functions} $fish_function_path",
             self.bin_path.display(),
-            self.man_path.display()
-        )
+            self.man_path.display(),
+            completions = fish_quote(&completions.to_string_lossy()),
+            functions = fish_quote(&functions.to_string_lossy()),
+        );
+        for (name, value) in &self.variables {
+            output.push('\n');
+            match value {
+                EnvValue::Value(value) => {
+                    output.push_str(&format!("set -gx {name} {}", fish_quote(value)));
+                }
+                EnvValue::PathList(list) => {
+                    let mut parts: Vec<String> =
+                        list.prepend.iter().map(|item| fish_quote(item)).collect();
+                    parts.push(format!("${name}"));
+                    parts.extend(list.append.iter().map(|item| fish_quote(item)));
+                    output.push_str(&format!("set -gx --path {name} {}", parts.join(" ")));
+                }
+            }
+        }
+        append_project_hook(&mut output, Shell::Fish);
+        output
+    }
+
+    fn format_nushell(&self) -> String {
+        let mut output = format!(
+            "$env.PATH = ($env.PATH | split row (char esep) | prepend {} | uniq)\n\
+             $env.MANPATH = ([{} ($env.MANPATH? | default '')] | str join (char esep))",
+            nu_quote(&self.bin_path.to_string_lossy()),
+            nu_quote(&self.man_path.to_string_lossy()),
+        );
+        for (name, value) in &self.variables {
+            output.push('\n');
+            match value {
+                EnvValue::Value(value) => {
+                    output.push_str(&format!("$env.{name} = {}", nu_quote(value)));
+                }
+                EnvValue::PathList(list) => {
+                    let quote_all = |items: &[String]| {
+                        items
+                            .iter()
+                            .map(|item| nu_quote(item))
+                            .collect::<Vec<_>>()
+                            .join(" ")
+                    };
+                    // Nushell keeps PATH as a list; other variables must stay strings to reach
+                    // external commands.
+                    let join = if name == "PATH" {
+                        ""
+                    } else {
+                        " | str join (char esep)"
+                    };
+                    output.push_str(&format!(
+                        "$env.{name} = ($env.{name}? | default [] | split row (char esep) | where $it != '' | prepend [{}] | append [{}]{join})",
+                        quote_all(&list.prepend),
+                        quote_all(&list.append),
+                    ));
+                }
+            }
+        }
+
+        // Nushell cannot `source` a path computed at runtime, so completion scripts are
+        // inlined into the generated file.
+        let completions = Shell::Nushell.completion_dir(&self.share_path);
+        for script in completion_scripts(&completions, "nu") {
+            if let Ok(contents) = fs::read_to_string(&script) {
+                output.push_str(&format!(
+                    "\n# {}\n{}",
+                    script.display(),
+                    contents.trim_end()
+                ));
+            }
+        }
+        output
+    }
+
+    fn format_powershell(&self) -> String {
+        let completions = Shell::PowerShell.completion_dir(&self.share_path);
+        let mut output = format!(
+            "$env:PATH = {} + [IO.Path]::PathSeparator + $env:PATH\n\
+             $env:MANPATH = {} + ':' + $env:MANPATH\n\
+             Get-ChildItem -Path {} -Filter '*.ps1' -ErrorAction SilentlyContinue | ForEach-Object {{ . $_.FullName }}",
+            ps_quote(&self.bin_path.to_string_lossy()),
+            ps_quote(&self.man_path.to_string_lossy()),
+            ps_quote(&completions.to_string_lossy()),
+        );
+        for (name, value) in &self.variables {
+            output.push('\n');
+            match value {
+                EnvValue::Value(value) => {
+                    output.push_str(&format!("$env:{name} = {}", ps_quote(value)));
+                }
+                EnvValue::PathList(list) => {
+                    let quote_all = |items: &[String]| {
+                        items
+                            .iter()
+                            .map(|item| ps_quote(item))
+                            .collect::<Vec<_>>()
+                            .join(", ")
+                    };
+                    output.push_str(&format!(
+                        "$env:{name} = (@({}) + @($env:{name}) + @({}) | Where-Object {{ $_ }}) -join [IO.Path]::PathSeparator",
+                        quote_all(&list.prepend),
+                        quote_all(&list.append),
+                    ));
+                }
+            }
+        }
+        output
+    }
+
+    fn format_elvish(&self) -> String {
+        let completions = Shell::Elvish.completion_dir(&self.share_path);
+        let mut output = format!(
+            "set paths = [{} $@paths]\n\
+             set E:MANPATH = {}':'$E:MANPATH\n\
+             for f [(put {}/*[nomatch-ok].elv)] {{ eval (slurp < $f) }}",
+            elvish_quote(&self.bin_path.to_string_lossy()),
+            elvish_quote(&self.man_path.to_string_lossy()),
+            elvish_quote(&completions.to_string_lossy()),
+        );
+        if self
+            .variables
+            .iter()
+            .any(|(_, value)| matches!(value, EnvValue::PathList(_)))
+        {
+            output.push_str("\nuse str");
+        }
+        for (name, value) in &self.variables {
+            output.push('\n');
+            match value {
+                EnvValue::Value(value) => {
+                    output.push_str(&format!("set E:{name} = {}", elvish_quote(value)));
+                }
+                EnvValue::PathList(list) => {
+                    let mut parts: Vec<String> =
+                        list.prepend.iter().map(|item| elvish_quote(item)).collect();
+                    parts.push(format!("(if (has-env {name}) {{ put $E:{name} }})"));
+                    parts.extend(list.append.iter().map(|item| elvish_quote(item)));
+                    output.push_str(&format!(
+                        "set E:{name} = (str:join ':' [{}])",
+                        parts.join(" ")
+                    ));
+                }
+            }
+        }
+        output
+    }
+
+    fn format_xonsh(&self) -> String {
+        // Xonsh completes through bash-completion, which searches XDG_DATA_DIRS.
+        let mut output = format!(
+            "$PATH.insert(0, {})\n\
+             $MANPATH = [{}] + list(${{...}}.get('MANPATH', ['']))\n\
+             $XDG_DATA_DIRS = [{}] + list(${{...}}.get('XDG_DATA_DIRS', ['/usr/local/share', '/usr/share']))",
+            py_quote(&self.bin_path.to_string_lossy()),
+            py_quote(&self.man_path.to_string_lossy()),
+            py_quote(&self.share_path.to_string_lossy()),
+        );
+        for (name, value) in &self.variables {
+            output.push('\n');
+            match value {
+                EnvValue::Value(value) => {
+                    output.push_str(&format!("${name} = {}", py_quote(value)));
+                }
+                EnvValue::PathList(list) => {
+                    let quote_all = |items: &[String]| {
+                        items
+                            .iter()
+                            .map(|item| py_quote(item))
+                            .collect::<Vec<_>>()
+                            .join(", ")
+                    };
+                    let (prepend, append) = (quote_all(&list.prepend), quote_all(&list.append));
+                    // Xonsh only treats `*PATH` and `*DIRS` variables as path lists.
+                    if name.ends_with("PATH") || name.ends_with("DIRS") {
+                        output.push_str(&format!(
+                            "${name} = [{prepend}] + list(${{...}}.get('{name}', [])) + [{append}]"
+                        ));
+                    } else {
+                        output.push_str(&format!(
+                            "${name} = ':'.join([{prepend}] + [p for p in ${{...}}.get('{name}', '').split(':') if p] + [{append}])"
+                        ));
+                    }
+                }
+            }
+        }
+        output
+    }
+
+    /// Resolve the environment into concrete values for a child process.
+    ///
+    /// `current` looks up the inherited value of a variable. Only variables the environment
+    /// changes are returned; completion wiring is shell state and is left to `dws env`.
+    pub fn process_variables(
+        &self,
+        current: impl Fn(&str) -> Option<String>,
+    ) -> BTreeMap<String, String> {
+        let mut resolved = BTreeMap::new();
+        let lookup = |resolved: &BTreeMap<String, String>, name: &str| {
+            resolved
+                .get(name)
+                .cloned()
+                .or_else(|| current(name))
+                .filter(|value| !value.is_empty())
+        };
+
+        let path = join_path_list(
+            &[self.bin_path.to_string_lossy().into_owned()],
+            lookup(&resolved, "PATH"),
+            &[],
+        );
+        resolved.insert("PATH".to_string(), path);
+        // A trailing `:` keeps man's default search path, matching the shell output.
+        let manpath = format!(
+            "{}:{}",
+            self.man_path.display(),
+            lookup(&resolved, "MANPATH").unwrap_or_default()
+        );
+        resolved.insert("MANPATH".to_string(), manpath);
+        let data_dirs = format!(
+            "{}:{}",
+            self.share_path.display(),
+            lookup(&resolved, "XDG_DATA_DIRS")
+                .unwrap_or_else(|| "/usr/local/share:/usr/share".to_string())
+        );
+        resolved.insert("XDG_DATA_DIRS".to_string(), data_dirs);
+
+        for (name, value) in &self.variables {
+            let value = match value {
+                EnvValue::Value(value) => value.clone(),
+                EnvValue::PathList(list) => {
+                    join_path_list(&list.prepend, lookup(&resolved, name), &list.append)
+                }
+            };
+            resolved.insert(name.clone(), value);
+        }
+        resolved
+    }
+
+    /// Render manifest variables with `export` syntax shared by zsh and bash.
+    fn append_posix_variables(&self, output: &mut String) {
+        for (name, value) in &self.variables {
+            output.push('\n');
+            match value {
+                EnvValue::Value(value) => {
+                    output.push_str(&format!("export {name}=\"{}\"", posix_escape(value)));
+                }
+                EnvValue::PathList(list) => {
+                    let prepend = list
+                        .prepend
+                        .iter()
+                        .map(|item| posix_escape(item))
+                        .collect::<Vec<_>>()
+                        .join(":");
+                    let append = list
+                        .append
+                        .iter()
+                        .map(|item| posix_escape(item))
+                        .collect::<Vec<_>>()
+                        .join(":");
+                    let value = match (prepend.is_empty(), append.is_empty()) {
+                        (false, false) => format!("{prepend}${{{name}:+:${name}}}:{append}"),
+                        (false, true) => format!("{prepend}${{{name}:+:${name}}}"),
+                        (true, false) => format!("${{{name}:+${name}:}}{append}"),
+                        (true, true) => format!("${{{name}:-}}"),
+                    };
+                    output.push_str(&format!("export {name}=\"{value}\""));
+                }
+            }
+        }
+    }
+}
+
+/// Append the directory-change hook that activates project `.dws.toml` environments.
+fn append_project_hook(output: &mut String, shell: Shell) {
+    if let Some(hook) = project::hook_script(shell) {
+        output.push('\n');
+        output.push_str(hook);
+    }
+}
+
+/// Join `prepend`, the existing value (if any) and `append` into a `:`-separated list.
+fn join_path_list(prepend: &[String], existing: Option<String>, append: &[String]) -> String {
+    prepend
+        .iter()
+        .cloned()
+        .chain(existing)
+        .chain(append.iter().cloned())
+        .collect::<Vec<_>>()
+        .join(":")
+}
+
+/// Escape a value for inclusion inside a double-quoted POSIX shell string.
+pub(crate) fn posix_escape(value: &str) -> String {
+    let mut escaped = String::with_capacity(value.len());
+    for ch in value.chars() {
+        if matches!(ch, '\\' | '"' | '$' | '`') {
+            escaped.push('\\');
+        }
+        escaped.push(ch);
+    }
+    escaped
+}
+
+/// Quote a value as a single fish word.
+pub(crate) fn fish_quote(value: &str) -> String {
+    format!("'{}'", value.replace('\\', "\\\\").replace('\'', "\\'"))
+}
+
+/// Quote a value as a nushell string, falling back to a raw string when it contains `'`.
+fn nu_quote(value: &str) -> String {
+    if value.contains('\'') {
+        format!("r#'{value}'#")
+    } else {
+        format!("'{value}'")
     }
 }
 
+/// Quote a value as a PowerShell verbatim string.
+fn ps_quote(value: &str) -> String {
+    format!("'{}'", value.replace('\'', "''"))
+}
+
+/// Quote a value as an elvish single-quoted string.
+fn elvish_quote(value: &str) -> String {
+    format!("'{}'", value.replace('\'', "''"))
+}
+
+/// Quote a value as a Python (xonsh) string literal.
+fn py_quote(value: &str) -> String {
+    format!("'{}'", value.replace('\\', "\\\\").replace('\'', "\\'"))
+}
+
+/// Completion scripts with the given extension in `dir`, sorted for stable output.
+fn completion_scripts(dir: &Path, extension: &str) -> Vec<PathBuf> {
+    let mut scripts: Vec<PathBuf> = fs::read_dir(dir)
+        .map(|entries| {
+            entries
+                .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+                .filter(|path| {
+                    path.is_file() && path.extension().is_some_and(|ext| ext == extension)
+                })
+                .collect()
+        })
+        .unwrap_or_default();
+    scripts.sort();
+    scripts
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -161,12 +741,41 @@ mod tests {
         assert!(output.contains("/share/man $MANPATH"));
     }
 
+    #[test]
+    #[serial]
+    fn test_completion_dirs_follow_shell() {
+        let _temp = setup_test_env();
+        let workspace = Workspace::new().unwrap();
+
+        let bash = Environment::new_from_workspace(&workspace, Shell::Bash).unwrap();
+        assert!(bash
+            .completions_path
+            .ends_with("share/bash-completion/completions"));
+        let output = bash.format_for_shell(Shell::Bash);
+        assert!(output.contains("export XDG_DATA_DIRS="));
+        assert!(output.contains("share/bash-completion/completions\"/*"));
+
+        let fish = Environment::new_from_workspace(&workspace, Shell::Fish).unwrap();
+        assert!(fish
+            .completions_path
+            .ends_with("share/fish/vendor_completions.d"));
+        let output = fish.format_for_shell(Shell::Fish);
+        assert!(output.contains("set -g fish_complete_path '"));
+        assert!(output.contains("share/fish/vendor_functions.d' $fish_function_path"));
+    }
+
     #[test]
     fn test_shell_from_name() {
         assert_eq!(Shell::from_name("zsh"), Some(Shell::Zsh));
         assert_eq!(Shell::from_name("BASH"), Some(Shell::Bash));
         assert_eq!(Shell::from_name("Fish"), Some(Shell::Fish));
-        assert_eq!(Shell::from_name("powershell"), None);
+        assert_eq!(Shell::from_name("nu"), Some(Shell::Nushell));
+        assert_eq!(Shell::from_name("nushell"), Some(Shell::Nushell));
+        assert_eq!(Shell::from_name("pwsh"), Some(Shell::PowerShell));
+        assert_eq!(Shell::from_name("PowerShell"), Some(Shell::PowerShell));
+        assert_eq!(Shell::from_name("elvish"), Some(Shell::Elvish));
+        assert_eq!(Shell::from_name("xonsh"), Some(Shell::Xonsh));
+        assert_eq!(Shell::from_name("tcsh"), None);
     }
 
     #[test]
@@ -174,5 +783,224 @@ mod tests {
         assert_eq!(Shell::Zsh.as_str(), "zsh");
         assert_eq!(Shell::Bash.as_str(), "bash");
         assert_eq!(Shell::Fish.as_str(), "fish");
+        for shell in Shell::ALL {
+            assert_eq!(Shell::from_name(shell.as_str()), Some(shell));
+        }
+    }
+
+    fn sample_environment() -> Environment {
+        Environment {
+            bin_path: PathBuf::from("/state/bin"),
+            man_path: PathBuf::from("/state/share/man"),
+            share_path: PathBuf::from("/state/share"),
+            completions_path: PathBuf::from("/state/share/zsh/site-functions"),
+            variables: vec![
+                (
+                    "GOROOT".to_string(),
+                    EnvValue::Value("/cache/go/contents/go".to_string()),
+                ),
+                (
+                    "PATH".to_string(),
+                    EnvValue::PathList(PathList {
+                        prepend: vec!["/cache/go/contents/go/bin".to_string()],
+                        append: Vec::new(),
+                    }),
+                ),
+                (
+                    "INFOPATH".to_string(),
+                    EnvValue::PathList(PathList {
+                        prepend: Vec::new(),
+                        append: vec!["/opt/info".to_string()],
+                    }),
+                ),
+                (
+                    "GREETING".to_string(),
+                    EnvValue::Value("it's \"$5\"".to_string()),
+                ),
+            ],
+        }
+    }
+
+    #[test]
+    fn test_manifest_variables_posix() {
+        let output = sample_environment().format_for_shell(Shell::Bash);
+
+        assert!(output.contains("export GOROOT=\"/cache/go/contents/go\""));
+        assert!(output.contains("export PATH=\"/cache/go/contents/go/bin${PATH:+:$PATH}\""));
+        assert!(output.contains("export INFOPATH=\"${INFOPATH:+$INFOPATH:}/opt/info\""));
+        assert!(output.contains("export GREETING=\"it's \\\"\\$5\\\"\""));
+        assert_eq!(
+            sample_environment()
+                .format_for_shell(Shell::Zsh)
+                .lines()
+                .filter(|line| line.starts_with("export "))
+                .count(),
+            6
+        );
+    }
+
+    #[test]
+    fn test_manifest_variables_fish() {
+        let output = sample_environment().format_for_shell(Shell::Fish);
+
+        assert!(output.contains("set -gx GOROOT '/cache/go/contents/go'"));
+        assert!(output.contains("set -gx --path PATH '/cache/go/contents/go/bin' $PATH"));
+        assert!(output.contains("set -gx --path INFOPATH $INFOPATH '/opt/info'"));
+        assert!(output.contains("set -gx GREETING 'it\\'s \"$5\"'"));
+    }
+
+    #[test]
+    #[serial]
+    fn test_interpolation() {
+        let temp = setup_test_env();
+        env::set_var("HOME", temp.path());
+        env::remove_var("XDG_DATA_HOME");
+
+        let value = EnvValue::Value("{install_dir}/go:$HOME/go:${XDG_DATA_HOME}/go".to_string());
+        let resolved = value.interpolate(Some(Path::new("/opt/tool"))).unwrap();
+        assert_eq!(
+            resolved,
+            EnvValue::Value(format!(
+                "/opt/tool/go:{home}/go:{home}/.local/share/go",
+                home = temp.path().display()
+            ))
+        );
+
+        let err = value.interpolate(None).unwrap_err();
+        assert!(err.to_string().contains("only available in per-tool env"));
+
+        let err = EnvValue::Value("$USER".to_string())
+            .interpolate(None)
+            .unwrap_err();
+        assert!(err.to_string().contains("only $HOME and $XDG_*"));
+    }
+
+    #[test]
+    #[serial]
+    fn test_variables_read_from_lockfile() {
+        let _temp = setup_test_env();
+        let workspace = Workspace::new().unwrap();
+
+        let mut lockfile = Lockfile::new();
+        lockfile
+            .env
+            .insert("EDITOR".to_string(), EnvValue::Value("hx".to_string()));
+        lockfile
+            .record_tool_install("helix", "latest", "25.01", "github", vec![], vec![], None)
+            .env
+            .insert(
+                "HELIX_RUNTIME".to_string(),
+                EnvValue::Value("/cache/helix/runtime".to_string()),
+            );
+        lockfile
+            .save(&workspace.path(WorkspacePath::Lockfile))
+            .unwrap();
+
+        let env = Environment::new_from_workspace(&workspace, Shell::Zsh).unwrap();
+        assert_eq!(
+            env.variables,
+            vec![
+                ("EDITOR".to_string(), EnvValue::Value("hx".to_string())),
+                (
+                    "HELIX_RUNTIME".to_string(),
+                    EnvValue::Value("/cache/helix/runtime".to_string())
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_format_nushell() {
+        let output = sample_environment().format_for_shell(Shell::Nushell);
+
+        assert!(output.contains(
+            "$env.PATH = ($env.PATH | split row (char esep) | prepend '/state/bin' | uniq)"
+        ));
+        assert!(
+            output.contains("$env.MANPATH = (['/state/share/man' ($env.MANPATH? | default '')]")
+        );
+        assert!(output.contains("$env.GOROOT = '/cache/go/contents/go'"));
+        assert!(output.contains("prepend ['/cache/go/contents/go/bin'] | append [])"));
+        assert!(output.contains("append ['/opt/info'] | str join (char esep))"));
+        assert!(output.contains("$env.GREETING = r#'it's \"$5\"'#"));
+        assert!(!output.contains("export "));
+    }
+
+    #[test]
+    fn test_format_nushell_inlines_completions() {
+        let temp = TempDir::new().unwrap();
+        let mut environment = sample_environment();
+        environment.share_path = temp.path().to_path_buf();
+        let completions = Shell::Nushell.completion_dir(temp.path());
+        fs::create_dir_all(&completions).unwrap();
+        fs::write(completions.join("tool.nu"), "export extern tool []\n").unwrap();
+        fs::write(completions.join("README"), "ignored").unwrap();
+
+        let output = environment.format_for_shell(Shell::Nushell);
+        assert!(output.ends_with("export extern tool []"));
+        assert!(!output.contains("ignored"));
+    }
+
+    #[test]
+    fn test_format_powershell() {
+        let output = sample_environment().format_for_shell(Shell::PowerShell);
+
+        assert!(output.contains("$env:PATH = '/state/bin' + [IO.Path]::PathSeparator + $env:PATH"));
+        assert!(output.contains("Get-ChildItem -Path '/state/share/powershell/completions'"));
+        assert!(output.contains("$env:GOROOT = '/cache/go/contents/go'"));
+        assert!(output.contains(
+            "$env:INFOPATH = (@() + @($env:INFOPATH) + @('/opt/info') | Where-Object { $_ })"
+        ));
+        assert!(output.contains("$env:GREETING = 'it''s \"$5\"'"));
+    }
+
+    #[test]
+    fn test_format_elvish() {
+        let output = sample_environment().format_for_shell(Shell::Elvish);
+
+        assert!(output.contains("set paths = ['/state/bin' $@paths]"));
+        assert!(
+            output.contains("for f [(put '/state/share/elvish/completions'/*[nomatch-ok].elv)]")
+        );
+        assert!(output.contains("use str"));
+        assert!(output.contains(
+            "set E:PATH = (str:join ':' ['/cache/go/contents/go/bin' (if (has-env PATH) { put $E:PATH })])"
+        ));
+        assert!(output.contains("set E:GREETING = 'it''s \"$5\"'"));
+    }
+
+    #[test]
+    fn test_format_xonsh() {
+        let output = sample_environment().format_for_shell(Shell::Xonsh);
+
+        assert!(output.contains("$PATH.insert(0, '/state/bin')"));
+        assert!(output.contains("$XDG_DATA_DIRS = ['/state/share']"));
+        assert!(output
+            .contains("$PATH = ['/cache/go/contents/go/bin'] + list(${...}.get('PATH', [])) + []"));
+        assert!(output.contains("$GOROOT = '/cache/go/contents/go'"));
+        assert!(output.contains("$GREETING = 'it\\'s \"$5\"'"));
+    }
+
+    #[test]
+    fn test_process_variables() {
+        let inherited = |name: &str| match name {
+            "PATH" => Some("/usr/bin".to_string()),
+            "INFOPATH" => Some(String::new()),
+            _ => None,
+        };
+        let variables = sample_environment().process_variables(inherited);
+
+        assert_eq!(
+            variables["PATH"],
+            "/cache/go/contents/go/bin:/state/bin:/usr/bin"
+        );
+        assert_eq!(variables["MANPATH"], "/state/share/man:");
+        assert_eq!(
+            variables["XDG_DATA_DIRS"],
+            "/state/share:/usr/local/share:/usr/share"
+        );
+        assert_eq!(variables["GOROOT"], "/cache/go/contents/go");
+        assert_eq!(variables["INFOPATH"], "/opt/info");
+        assert_eq!(variables["GREETING"], "it's \"$5\"");
     }
 }
@@ -0,0 +1,851 @@
+use anyhow::{bail, Context, Result};
+use bzip2::read::BzDecoder;
+use flate2::read::GzDecoder;
+use std::fs::{self, File};
+use std::io::{self, Read};
+use std::path::{Component, Path, PathBuf};
+use tar::{Archive, EntryType};
+use xz2::read::XzDecoder;
+use zip::ZipArchive;
+use zstd::stream::read::Decoder as ZstdDecoder;
+
+use crate::toolset::ExtractLimits;
+
+/// Default ceiling on the bytes written while unpacking a single asset (2 GiB).
+const DEFAULT_MAX_BYTES: u64 = 2 * 1024 * 1024 * 1024;
+/// Default ceiling on the number of entries unpacked from a single asset.
+const DEFAULT_MAX_ENTRIES: u64 = 100_000;
+
+/// Resource limits enforced during extraction.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct Limits {
+    pub max_bytes: u64,
+    pub max_entries: u64,
+}
+
+impl Default for Limits {
+    fn default() -> Self {
+        Self {
+            max_bytes: DEFAULT_MAX_BYTES,
+            max_entries: DEFAULT_MAX_ENTRIES,
+        }
+    }
+}
+
+impl Limits {
+    pub fn from_manifest(extract: &ExtractLimits) -> Self {
+        let defaults = Self::default();
+        Self {
+            max_bytes: extract.max_bytes.unwrap_or(defaults.max_bytes),
+            max_entries: extract.max_entries.unwrap_or(defaults.max_entries),
+        }
+    }
+}
+
+/// Archive and compression formats understood by [`extract_archive`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum ArchiveFormat {
+    TarGz,
+    TarXz,
+    TarZst,
+    TarBz2,
+    Tar,
+    Zip,
+    SevenZip,
+    Gz,
+    Xz,
+    Zst,
+    Bz2,
+    Raw,
+}
+
+impl ArchiveFormat {
+    pub fn detect(filename: &str) -> Self {
+        let lowered = filename.to_ascii_lowercase();
+        let name = lowered.as_str();
+
+        if name.ends_with(".tar.gz") || name.ends_with(".tgz") {
+            ArchiveFormat::TarGz
+        } else if name.ends_with(".tar.xz") || name.ends_with(".txz") {
+            ArchiveFormat::TarXz
+        } else if name.ends_with(".tar.zst") || name.ends_with(".tzst") {
+            ArchiveFormat::TarZst
+        } else if name.ends_with(".tar.bz2") || name.ends_with(".tbz2") || name.ends_with(".tbz") {
+            ArchiveFormat::TarBz2
+        } else if name.ends_with(".tar") {
+            ArchiveFormat::Tar
+        } else if name.ends_with(".zip") {
+            ArchiveFormat::Zip
+        } else if name.ends_with(".7z") {
+            ArchiveFormat::SevenZip
+        } else if name.ends_with(".gz") {
+            ArchiveFormat::Gz
+        } else if name.ends_with(".xz") {
+            ArchiveFormat::Xz
+        } else if name.ends_with(".zst") {
+            ArchiveFormat::Zst
+        } else if name.ends_with(".bz2") {
+            ArchiveFormat::Bz2
+        } else {
+            ArchiveFormat::Raw
+        }
+    }
+
+    /// Extension stripped from single-file compressed assets to derive the binary name.
+    fn single_file_suffix(self) -> Option<&'static str> {
+        match self {
+            ArchiveFormat::Gz => Some(".gz"),
+            ArchiveFormat::Xz => Some(".xz"),
+            ArchiveFormat::Zst => Some(".zst"),
+            ArchiveFormat::Bz2 => Some(".bz2"),
+            _ => None,
+        }
+    }
+}
+
+/// Unpack `archive_path` into `dest`, refusing anything that would land outside `dest`.
+///
+/// Every entry is written through [`Sink`], which rejects absolute paths, `..` traversal,
+/// symlinks and hardlinks resolving outside the root, and enforces the byte/entry `limits`.
+/// Regular files are normalised to `0o755` when any execute bit is set and `0o644` otherwise.
+pub fn extract_archive(archive_path: &Path, dest: &Path, limits: &Limits) -> Result<()> {
+    let filename = archive_path
+        .file_name()
+        .and_then(|value| value.to_str())
+        .unwrap_or_default();
+    let format = ArchiveFormat::detect(filename);
+    let mut sink = Sink::new(dest, limits)?;
+
+    let open = || {
+        File::open(archive_path)
+            .with_context(|| format!("Failed to open archive {:?}", archive_path))
+    };
+
+    let result = match format {
+        ArchiveFormat::TarGz => unpack_tar(GzDecoder::new(open()?), &mut sink),
+        ArchiveFormat::TarXz => unpack_tar(XzDecoder::new(open()?), &mut sink),
+        ArchiveFormat::TarZst => {
+            let decoder = ZstdDecoder::new(open()?).with_context(|| {
+                format!("Failed to initialise zstd decoder for {:?}", archive_path)
+            })?;
+            unpack_tar(decoder, &mut sink)
+        }
+        ArchiveFormat::TarBz2 => unpack_tar(BzDecoder::new(open()?), &mut sink),
+        ArchiveFormat::Tar => unpack_tar(open()?, &mut sink),
+        ArchiveFormat::Zip => unpack_zip(open()?, &mut sink),
+        ArchiveFormat::SevenZip => unpack_7z(archive_path, &mut sink),
+        ArchiveFormat::Gz => {
+            decompress_single(GzDecoder::new(open()?), format, filename, &mut sink)
+        }
+        ArchiveFormat::Xz => {
+            decompress_single(XzDecoder::new(open()?), format, filename, &mut sink)
+        }
+        ArchiveFormat::Zst => {
+            let decoder = ZstdDecoder::new(open()?).with_context(|| {
+                format!("Failed to initialise zstd decoder for {:?}", archive_path)
+            })?;
+            decompress_single(decoder, format, filename, &mut sink)
+        }
+        ArchiveFormat::Bz2 => {
+            decompress_single(BzDecoder::new(open()?), format, filename, &mut sink)
+        }
+        ArchiveFormat::Raw => {
+            let target = dest.join(
+                archive_path
+                    .file_name()
+                    .context("Archive path is missing a filename")?,
+            );
+            fs::copy(archive_path, &target).with_context(|| {
+                format!("Failed to copy asset {:?} to {:?}", archive_path, target)
+            })?;
+            Ok(())
+        }
+    };
+
+    result.with_context(|| format!("Failed to unpack archive {:?}", archive_path))
+}
+
+fn unpack_tar<R: Read>(reader: R, sink: &mut Sink<'_>) -> Result<()> {
+    let mut archive = Archive::new(reader);
+    let entries = archive.entries().context("Failed to read tar entries")?;
+
+    for entry in entries {
+        let mut entry = entry.context("Failed to read tar entry")?;
+        let name = entry
+            .path()
+            .context("Tar entry has an invalid path")?
+            .into_owned();
+        sink.count_entry()?;
+
+        let entry_type = entry.header().entry_type();
+        let Some(path) = sink.resolve(&name)? else {
+            continue;
+        };
+
+        match entry_type {
+            EntryType::Directory => sink.create_dir(&path)?,
+            EntryType::Regular | EntryType::Continuous => {
+                let executable = entry
+                    .header()
+                    .mode()
+                    .map(|mode| mode & 0o111 != 0)
+                    .unwrap_or(false);
+                sink.write_file(&path, &mut entry, executable)?;
+            }
+            EntryType::Symlink => {
+                let target = entry
+                    .link_name()
+                    .context("Tar symlink has an invalid target")?
+                    .with_context(|| format!("Tar symlink {:?} is missing a target", name))?
+                    .into_owned();
+                sink.symlink(&path, &target)?;
+            }
+            EntryType::Link => {
+                let target = entry
+                    .link_name()
+                    .context("Tar hardlink has an invalid target")?
+                    .with_context(|| format!("Tar hardlink {:?} is missing a target", name))?
+                    .into_owned();
+                sink.hardlink(&path, &target)?;
+            }
+            // Devices, FIFOs and other special files have no place in a tool release.
+            _ => {}
+        }
+    }
+
+    Ok(())
+}
+
+fn unpack_zip(file: File, sink: &mut Sink<'_>) -> Result<()> {
+    let mut archive = ZipArchive::new(file).context("Failed to read zip archive")?;
+
+    for index in 0..archive.len() {
+        let mut entry = archive
+            .by_index(index)
+            .with_context(|| format!("Failed to read zip entry #{index}"))?;
+        sink.count_entry()?;
+
+        let name = PathBuf::from(entry.name());
+        let Some(path) = sink.resolve(&name)? else {
+            continue;
+        };
+
+        let mode = entry.unix_mode();
+        if entry.is_dir() {
+            sink.create_dir(&path)?;
+        } else if mode
+            .map(|mode| mode & 0o170000 == 0o120000)
+            .unwrap_or(false)
+        {
+            let mut target = String::new();
+            entry
+                .read_to_string(&mut target)
+                .with_context(|| format!("Failed to read zip symlink {:?}", name))?;
+            sink.symlink(&path, Path::new(&target))?;
+        } else {
+            let executable = mode.map(|mode| mode & 0o111 != 0).unwrap_or(false);
+            sink.write_file(&path, &mut entry, executable)?;
+        }
+    }
+
+    Ok(())
+}
+
+fn unpack_7z(archive_path: &Path, sink: &mut Sink<'_>) -> Result<()> {
+    // 7z attributes carry the unix mode in the upper 16 bits when this flag is set.
+    const UNIX_EXTENSION: u32 = 0x8000;
+
+    let mut failure = None;
+    let outcome = sevenz_rust::decompress_file_with_extract_fn(
+        archive_path,
+        sink.root.clone(),
+        |entry, reader, _| {
+            let step = (|| -> Result<()> {
+                sink.count_entry()?;
+                let Some(path) = sink.resolve(Path::new(entry.name()))? else {
+                    return Ok(());
+                };
+
+                if entry.is_directory() {
+                    sink.create_dir(&path)
+                } else {
+                    let attributes = entry.windows_attributes();
+                    let executable =
+                        attributes & UNIX_EXTENSION != 0 && (attributes >> 16) & 0o111 != 0;
+                    sink.write_file(&path, reader, executable)
+                }
+            })();
+
+            match step {
+                Ok(()) => Ok(true),
+                Err(err) => {
+                    failure = Some(err);
+                    Err(sevenz_rust::Error::other("extraction aborted"))
+                }
+            }
+        },
+    );
+
+    if let Some(err) = failure {
+        return Err(err);
+    }
+
+    outcome
+        .map_err(|err| anyhow::anyhow!("{err:?}"))
+        .context("Failed to read 7z archive")
+}
+
+/// Decompress a bare `tool.gz`/`.xz`/`.zst`/`.bz2` asset into `dest/<tool>` with the executable bit set.
+fn decompress_single<R: Read>(
+    mut reader: R,
+    format: ArchiveFormat,
+    filename: &str,
+    sink: &mut Sink<'_>,
+) -> Result<()> {
+    let suffix_len = format.single_file_suffix().map(str::len).unwrap_or(0);
+    let stem = &filename[..filename.len() - suffix_len];
+    if stem.is_empty() {
+        bail!("Compressed asset '{filename}' does not have a usable file name");
+    }
+
+    sink.count_entry()?;
+    let path = sink.resolve(Path::new(stem))?.with_context(|| {
+        format!("Compressed asset '{filename}' does not have a usable file name")
+    })?;
+    sink.write_file(&path, &mut reader, true)
+}
+
+/// Writes archive entries beneath a canonical root while enforcing containment and limits.
+struct Sink<'a> {
+    root: PathBuf,
+    limits: &'a Limits,
+    bytes: u64,
+    entries: u64,
+}
+
+impl<'a> Sink<'a> {
+    fn new(dest: &Path, limits: &'a Limits) -> Result<Self> {
+        fs::create_dir_all(dest)
+            .with_context(|| format!("Failed to create extraction directory {:?}", dest))?;
+        let root = dest
+            .canonicalize()
+            .with_context(|| format!("Failed to resolve extraction directory {:?}", dest))?;
+
+        Ok(Self {
+            root,
+            limits,
+            bytes: 0,
+            entries: 0,
+        })
+    }
+
+    fn count_entry(&mut self) -> Result<()> {
+        self.entries += 1;
+        if self.entries > self.limits.max_entries {
+            bail!(
+                "Archive contains more than {} entries; raise `extract.max_entries` for this tool if this is expected",
+                self.limits.max_entries
+            );
+        }
+        Ok(())
+    }
+
+    /// Map an entry name onto the root, rejecting absolute paths and `..` components.
+    ///
+    /// Returns `None` for entries that name the root itself (e.g. `./`).
+    fn resolve(&self, name: &Path) -> Result<Option<PathBuf>> {
+        let mut relative = PathBuf::new();
+        for component in name.components() {
+            match component {
+                Component::Normal(part) => relative.push(part),
+                Component::CurDir => {}
+                Component::ParentDir | Component::RootDir | Component::Prefix(_) => {
+                    bail!("Archive entry {:?} escapes the extraction root", name);
+                }
+            }
+        }
+
+        if relative.as_os_str().is_empty() {
+            return Ok(None);
+        }
+
+        Ok(Some(self.root.join(relative)))
+    }
+
+    /// Ensure `path` resolves (through any symlinks already on disk) to a location inside the root.
+    fn ensure_contained(&self, path: &Path) -> Result<()> {
+        let resolved = path
+            .canonicalize()
+            .with_context(|| format!("Failed to resolve {:?}", path))?;
+        if !resolved.starts_with(&self.root) {
+            bail!(
+                "Archive entry {:?} resolves outside the extraction root",
+                path.strip_prefix(&self.root).unwrap_or(path)
+            );
+        }
+        Ok(())
+    }
+
+    fn prepare_parent(&self, path: &Path) -> Result<PathBuf> {
+        let parent = path
+            .parent()
+            .with_context(|| format!("Archive entry {:?} has no parent directory", path))?;
+        fs::create_dir_all(parent)
+            .with_context(|| format!("Failed to create directory {:?}", parent))?;
+        self.ensure_contained(parent)?;
+        parent
+            .canonicalize()
+            .with_context(|| format!("Failed to resolve {:?}", parent))
+    }
+
+    /// Remove whatever currently occupies `path` so new entries never write through a symlink.
+    fn clear(&self, path: &Path) -> Result<()> {
+        match fs::symlink_metadata(path) {
+            Ok(metadata) if metadata.is_dir() => {
+                bail!("Archive entry {:?} replaces an existing directory", path)
+            }
+            Ok(_) => fs::remove_file(path)
+                .with_context(|| format!("Failed to replace existing entry {:?}", path)),
+            Err(_) => Ok(()),
+        }
+    }
+
+    fn create_dir(&mut self, path: &Path) -> Result<()> {
+        self.prepare_parent(path)?;
+        if fs::symlink_metadata(path)
+            .map(|metadata| !metadata.is_dir())
+            .unwrap_or(false)
+        {
+            self.clear(path)?;
+        }
+        fs::create_dir_all(path)
+            .with_context(|| format!("Failed to create directory {:?}", path))?;
+        self.ensure_contained(path)
+    }
+
+    fn write_file(&mut self, path: &Path, reader: &mut dyn Read, executable: bool) -> Result<()> {
+        let parent = self.prepare_parent(path)?;
+        let target = parent.join(
+            path.file_name()
+                .context("Archive entry is missing a name")?,
+        );
+        self.clear(&target)?;
+
+        let remaining = self.limits.max_bytes.saturating_sub(self.bytes);
+        let mut outfile =
+            File::create(&target).with_context(|| format!("Failed to create file {:?}", target))?;
+        let written = io::copy(&mut reader.take(remaining + 1), &mut outfile)
+            .with_context(|| format!("Failed to extract {:?}", target))?;
+        if written > remaining {
+            drop(outfile);
+            let _ = fs::remove_file(&target);
+            bail!(
+                "Archive expands beyond {} bytes; raise `extract.max_bytes` for this tool if this is expected",
+                self.limits.max_bytes
+            );
+        }
+        self.bytes += written;
+
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            let mode = if executable { 0o755 } else { 0o644 };
+            fs::set_permissions(&target, fs::Permissions::from_mode(mode))
+                .with_context(|| format!("Failed to set permissions on {:?}", target))?;
+        }
+        #[cfg(not(unix))]
+        let _ = executable;
+
+        Ok(())
+    }
+
+    /// Create a symlink whose target must stay inside the root.
+    ///
+    /// Targets must be relative and may only climb (`..`) before descending, so the lexical
+    /// check against the canonical parent cannot be bypassed through intermediate links.
+    fn symlink(&mut self, path: &Path, target: &Path) -> Result<()> {
+        let parent = self.prepare_parent(path)?;
+
+        let mut resolved = parent.clone();
+        let mut descended = false;
+        for component in target.components() {
+            match component {
+                Component::Normal(part) => {
+                    descended = true;
+                    resolved.push(part);
+                }
+                Component::CurDir => {}
+                Component::ParentDir if !descended => {
+                    resolved.pop();
+                }
+                _ => bail!(
+                    "Archive symlink {:?} -> {:?} escapes the extraction root",
+                    path.strip_prefix(&self.root).unwrap_or(path),
+                    target
+                ),
+            }
+        }
+
+        if !resolved.starts_with(&self.root) {
+            bail!(
+                "Archive symlink {:?} -> {:?} escapes the extraction root",
+                path.strip_prefix(&self.root).unwrap_or(path),
+                target
+            );
+        }
+
+        let link = parent.join(
+            path.file_name()
+                .context("Archive entry is missing a name")?,
+        );
+        self.clear(&link)?;
+
+        #[cfg(unix)]
+        {
+            std::os::unix::fs::symlink(target, &link)
+                .with_context(|| format!("Failed to create symlink {:?}", link))?;
+        }
+        #[cfg(not(unix))]
+        {
+            bail!(
+                "Archive symlink {:?} cannot be created on this platform",
+                link
+            );
+        }
+
+        Ok(())
+    }
+
+    /// Materialise a hardlink entry; the target is another entry name relative to the root.
+    fn hardlink(&mut self, path: &Path, target: &Path) -> Result<()> {
+        let source = self
+            .resolve(target)?
+            .with_context(|| format!("Archive hardlink {:?} has an empty target", path))?;
+        self.ensure_contained(&source)?;
+        if !source.is_file() {
+            bail!(
+                "Archive hardlink {:?} does not point at a regular file",
+                path
+            );
+        }
+
+        let parent = self.prepare_parent(path)?;
+        let link = parent.join(
+            path.file_name()
+                .context("Archive entry is missing a name")?,
+        );
+        self.clear(&link)?;
+
+        if fs::hard_link(&source, &link).is_err() {
+            fs::copy(&source, &link)
+                .with_context(|| format!("Failed to copy {:?} to {:?}", source, link))?;
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+    use tempfile::TempDir;
+
+    /// Build a tar stream without `tar::Builder`'s path validation so hostile names survive.
+    fn raw_tar(entries: &[(&str, EntryType, &str, &[u8], u32)]) -> Vec<u8> {
+        let mut builder = tar::Builder::new(Vec::new());
+        for (name, kind, link, data, mode) in entries {
+            let mut header = tar::Header::new_old();
+            header.as_old_mut().name[..name.len()].copy_from_slice(name.as_bytes());
+            header.as_old_mut().linkname[..link.len()].copy_from_slice(link.as_bytes());
+            header.set_entry_type(*kind);
+            header.set_mode(*mode);
+            header.set_size(data.len() as u64);
+            header.set_cksum();
+            builder.append(&header, *data).unwrap();
+        }
+        builder.into_inner().unwrap()
+    }
+
+    fn extract_tar(bytes: &[u8], limits: &Limits) -> (TempDir, Result<()>) {
+        let temp = TempDir::new().unwrap();
+        let archive = temp.path().join("asset.tar");
+        fs::write(&archive, bytes).unwrap();
+        let dest = temp.path().join("root/contents");
+        let result = extract_archive(&archive, &dest, limits);
+        (temp, result)
+    }
+
+    fn zip_bytes(build: impl FnOnce(&mut zip::ZipWriter<io::Cursor<Vec<u8>>>)) -> Vec<u8> {
+        let mut writer = zip::ZipWriter::new(io::Cursor::new(Vec::new()));
+        build(&mut writer);
+        writer.finish().unwrap().into_inner()
+    }
+
+    fn extract_zip(bytes: &[u8]) -> (TempDir, Result<()>) {
+        let temp = TempDir::new().unwrap();
+        let archive = temp.path().join("asset.zip");
+        fs::write(&archive, bytes).unwrap();
+        let dest = temp.path().join("root/contents");
+        let result = extract_archive(&archive, &dest, &Limits::default());
+        (temp, result)
+    }
+
+    #[test]
+    fn archive_format_detection() {
+        assert_eq!(ArchiveFormat::detect("rg.tar.zst"), ArchiveFormat::TarZst);
+        assert_eq!(ArchiveFormat::detect("rg.TBZ2"), ArchiveFormat::TarBz2);
+        assert_eq!(ArchiveFormat::detect("tool.7z"), ArchiveFormat::SevenZip);
+        assert_eq!(ArchiveFormat::detect("tool-linux.gz"), ArchiveFormat::Gz);
+        assert_eq!(ArchiveFormat::detect("tool-linux.zst"), ArchiveFormat::Zst);
+        assert_eq!(ArchiveFormat::detect("tool-linux"), ArchiveFormat::Raw);
+    }
+
+    #[test]
+    fn extract_tar_zst_and_tar_bz2() {
+        let temp = TempDir::new().unwrap();
+        let tar = raw_tar(&[(
+            "tool-1.0/bin/tool",
+            EntryType::Regular,
+            "",
+            b"binary",
+            0o755,
+        )]);
+
+        let zst = temp.path().join("tool.tar.zst");
+        fs::write(&zst, zstd::encode_all(tar.as_slice(), 0).unwrap()).unwrap();
+        let bz2 = temp.path().join("tool.tar.bz2");
+        let mut encoder = bzip2::write::BzEncoder::new(Vec::new(), bzip2::Compression::default());
+        encoder.write_all(&tar).unwrap();
+        fs::write(&bz2, encoder.finish().unwrap()).unwrap();
+
+        for archive in [zst, bz2] {
+            let dest = temp.path().join(format!(
+                "out-{}",
+                archive.file_name().unwrap().to_string_lossy()
+            ));
+            extract_archive(&archive, &dest, &Limits::default()).unwrap();
+            assert_eq!(fs::read(dest.join("tool-1.0/bin/tool")).unwrap(), b"binary");
+        }
+    }
+
+    #[test]
+    fn extract_single_file_gz_is_executable() {
+        let temp = TempDir::new().unwrap();
+        let asset = temp.path().join("tool-x86_64-linux.gz");
+        let mut encoder = flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::default());
+        encoder.write_all(b"#!/bin/sh\necho hi\n").unwrap();
+        fs::write(&asset, encoder.finish().unwrap()).unwrap();
+
+        let dest = temp.path().join("out");
+        extract_archive(&asset, &dest, &Limits::default()).unwrap();
+
+        let binary = dest.join("tool-x86_64-linux");
+        assert_eq!(fs::read(&binary).unwrap(), b"#!/bin/sh\necho hi\n");
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            let mode = fs::metadata(&binary).unwrap().permissions().mode();
+            assert_eq!(mode & 0o777, 0o755);
+        }
+    }
+
+    #[test]
+    fn tar_rejects_parent_traversal() {
+        let bytes = raw_tar(&[("../evil", EntryType::Regular, "", b"pwned", 0o644)]);
+        let (temp, result) = extract_tar(&bytes, &Limits::default());
+        assert!(format!("{:#}", result.unwrap_err()).contains("escapes the extraction root"));
+        assert!(!temp.path().join("root/evil").exists());
+    }
+
+    #[test]
+    fn tar_rejects_absolute_paths() {
+        let bytes = raw_tar(&[("/tmp/dws-evil", EntryType::Regular, "", b"pwned", 0o644)]);
+        let (_temp, result) = extract_tar(&bytes, &Limits::default());
+        assert!(format!("{:#}", result.unwrap_err()).contains("escapes the extraction root"));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn tar_rejects_symlink_escape_and_write_through() {
+        let bytes = raw_tar(&[("link", EntryType::Symlink, "../..", b"", 0o777)]);
+        let (_temp, result) = extract_tar(&bytes, &Limits::default());
+        assert!(format!("{:#}", result.unwrap_err()).contains("escapes the extraction root"));
+
+        let bytes = raw_tar(&[("link", EntryType::Symlink, "/etc", b"", 0o777)]);
+        let (_temp, result) = extract_tar(&bytes, &Limits::default());
+        assert!(format!("{:#}", result.unwrap_err()).contains("escapes the extraction root"));
+
+        // A link to `.` followed by `..` would pass a naive lexical check.
+        let bytes = raw_tar(&[
+            ("here", EntryType::Symlink, ".", b"", 0o777),
+            ("out", EntryType::Symlink, "here/../x", b"", 0o777),
+        ]);
+        let (_temp, result) = extract_tar(&bytes, &Limits::default());
+        assert!(format!("{:#}", result.unwrap_err()).contains("escapes the extraction root"));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn tar_allows_internal_symlinks() {
+        let bytes = raw_tar(&[
+            (
+                "tool-1.0/bin/tool",
+                EntryType::Regular,
+                "",
+                b"binary",
+                0o755,
+            ),
+            ("tool-1.0/tool", EntryType::Symlink, "bin/tool", b"", 0o777),
+            (
+                "tool-1.0/doc/tool",
+                EntryType::Symlink,
+                "../bin/tool",
+                b"",
+                0o777,
+            ),
+        ]);
+        let (temp, result) = extract_tar(&bytes, &Limits::default());
+        result.unwrap();
+        let root = temp.path().join("root/contents/tool-1.0");
+        assert_eq!(fs::read(root.join("tool")).unwrap(), b"binary");
+        assert_eq!(fs::read(root.join("doc/tool")).unwrap(), b"binary");
+    }
+
+    #[test]
+    fn tar_rejects_hardlink_outside_root() {
+        let bytes = raw_tar(&[(
+            "passwd",
+            EntryType::Link,
+            "../../../../etc/passwd",
+            b"",
+            0o644,
+        )]);
+        let (temp, result) = extract_tar(&bytes, &Limits::default());
+        assert!(format!("{:#}", result.unwrap_err()).contains("escapes the extraction root"));
+        assert!(!temp.path().join("root/contents/passwd").exists());
+    }
+
+    #[test]
+    fn tar_hardlink_inside_root_is_materialised() {
+        let bytes = raw_tar(&[
+            ("bin/tool", EntryType::Regular, "", b"binary", 0o755),
+            ("bin/alias", EntryType::Link, "bin/tool", b"", 0o755),
+        ]);
+        let (temp, result) = extract_tar(&bytes, &Limits::default());
+        result.unwrap();
+        assert_eq!(
+            fs::read(temp.path().join("root/contents/bin/alias")).unwrap(),
+            b"binary"
+        );
+    }
+
+    #[test]
+    fn tar_enforces_size_limit() {
+        let payload = vec![0u8; 4096];
+        let bytes = raw_tar(&[("big", EntryType::Regular, "", &payload, 0o644)]);
+        let limits = Limits {
+            max_bytes: 1024,
+            max_entries: 10,
+        };
+        let (temp, result) = extract_tar(&bytes, &limits);
+        assert!(format!("{:#}", result.unwrap_err()).contains("extract.max_bytes"));
+        assert!(!temp.path().join("root/contents/big").exists());
+    }
+
+    #[test]
+    fn gzip_bomb_is_capped() {
+        let temp = TempDir::new().unwrap();
+        let asset = temp.path().join("bomb.gz");
+        let mut encoder = flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::best());
+        encoder.write_all(&vec![0u8; 1024 * 1024]).unwrap();
+        fs::write(&asset, encoder.finish().unwrap()).unwrap();
+
+        let limits = Limits {
+            max_bytes: 64 * 1024,
+            max_entries: 10,
+        };
+        let err = extract_archive(&asset, &temp.path().join("out"), &limits).unwrap_err();
+        assert!(format!("{err:#}").contains("extract.max_bytes"));
+    }
+
+    #[test]
+    fn tar_enforces_entry_limit() {
+        let bytes = raw_tar(&[
+            ("a", EntryType::Regular, "", b"1", 0o644),
+            ("b", EntryType::Regular, "", b"2", 0o644),
+            ("c", EntryType::Regular, "", b"3", 0o644),
+        ]);
+        let limits = Limits {
+            max_bytes: DEFAULT_MAX_BYTES,
+            max_entries: 2,
+        };
+        let (_temp, result) = extract_tar(&bytes, &limits);
+        assert!(format!("{:#}", result.unwrap_err()).contains("extract.max_entries"));
+    }
+
+    #[test]
+    fn zip_rejects_parent_traversal() {
+        let bytes = zip_bytes(|writer| {
+            writer
+                .start_file("../../evil", zip::write::FileOptions::default())
+                .unwrap();
+            writer.write_all(b"pwned").unwrap();
+        });
+        let (temp, result) = extract_zip(&bytes);
+        assert!(format!("{:#}", result.unwrap_err()).contains("escapes the extraction root"));
+        assert!(!temp.path().join("evil").exists());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn zip_rejects_escaping_symlink() {
+        let bytes = zip_bytes(|writer| {
+            writer
+                .add_symlink("link", "../../outside", zip::write::FileOptions::default())
+                .unwrap();
+        });
+        let (_temp, result) = extract_zip(&bytes);
+        assert!(format!("{:#}", result.unwrap_err()).contains("escapes the extraction root"));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn executable_bits_are_normalised_across_formats() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let tar = raw_tar(&[
+            ("bin/tool", EntryType::Regular, "", b"binary", 0o4750),
+            ("README", EntryType::Regular, "", b"docs", 0o600),
+        ]);
+        let (tar_temp, result) = extract_tar(&tar, &Limits::default());
+        result.unwrap();
+
+        let zip = zip_bytes(|writer| {
+            writer
+                .start_file(
+                    "bin/tool",
+                    zip::write::FileOptions::default().unix_permissions(0o700),
+                )
+                .unwrap();
+            writer.write_all(b"binary").unwrap();
+            writer
+                .start_file(
+                    "README",
+                    zip::write::FileOptions::default().unix_permissions(0o600),
+                )
+                .unwrap();
+            writer.write_all(b"docs").unwrap();
+        });
+        let (zip_temp, result) = extract_zip(&zip);
+        result.unwrap();
+
+        for temp in [&tar_temp, &zip_temp] {
+            let root = temp.path().join("root/contents");
+            let mode =
+                |path: &str| fs::metadata(root.join(path)).unwrap().permissions().mode() & 0o7777;
+            assert_eq!(mode("bin/tool"), 0o755);
+            assert_eq!(mode("README"), 0o644);
+        }
+    }
+}
@@ -1,4 +1,5 @@
 use anyhow::{bail, Context, Result};
+use globset::{GlobBuilder, GlobMatcher};
 use regex::Regex;
 use reqwest::blocking::{Client, Response};
 use reqwest::header::{ACCEPT, USER_AGENT};
@@ -7,17 +8,13 @@ use sha2::{Digest, Sha256};
 use std::cmp::{Ordering, Reverse};
 use std::env;
 use std::fs::{self, File};
-use std::io::{self, Read, Write};
+use std::io::{Read, Write};
 use std::path::{Path, PathBuf};
 use std::sync::Arc;
-use tar::Archive;
 use walkdir::WalkDir;
-use xz2::read::XzDecoder;
-use zip::ZipArchive;
-
-use flate2::read::GzDecoder;
 
 use super::InstallContext;
+use crate::environment::Shell;
 use crate::toolset::{ExtraKind, ToolExtra};
 
 const API_ROOT: &str = "https://api.github.com";
@@ -314,14 +311,33 @@ impl<'a> PartialOrd for AssetRank<'a> {
 }
 
 fn extension_score(name: &str) -> i32 {
-    if name.ends_with(".tar.gz") || name.ends_with(".tar.xz") {
+    let lowered = name.to_ascii_lowercase();
+    let name = lowered.as_str();
+    if name.ends_with(".tar.gz")
+        || name.ends_with(".tar.xz")
+        || name.ends_with(".tar.zst")
+        || name.ends_with(".tar.bz2")
+    {
         30
-    } else if name.ends_with(".tgz") {
+    } else if name.ends_with(".tgz")
+        || name.ends_with(".txz")
+        || name.ends_with(".tzst")
+        || name.ends_with(".tbz2")
+        || name.ends_with(".tbz")
+    {
         25
     } else if name.ends_with(".zip") {
         20
     } else if name.ends_with(".tar") {
         15
+    } else if name.ends_with(".7z") {
+        12
+    } else if name.ends_with(".gz")
+        || name.ends_with(".xz")
+        || name.ends_with(".zst")
+        || name.ends_with(".bz2")
+    {
+        10
     } else {
         5
     }
@@ -384,102 +400,44 @@ pub fn compute_sha256(path: &Path) -> Result<[u8; 32]> {
     Ok(hasher.finalize().into())
 }
 
-pub fn extract_archive(archive_path: &Path, dest: &Path) -> Result<()> {
-    let filename = archive_path
-        .file_name()
-        .and_then(|value| value.to_str())
-        .unwrap_or_default()
-        .to_ascii_lowercase();
-
-    if filename.ends_with(".tar.gz") || filename.ends_with(".tgz") {
-        let file = File::open(archive_path)
-            .with_context(|| format!("Failed to open archive {:?}", archive_path))?;
-        let decoder = GzDecoder::new(file);
-        let mut archive = Archive::new(decoder);
-        archive
-            .unpack(dest)
-            .with_context(|| format!("Failed to unpack tar.gz archive {:?}", archive_path))?;
-    } else if filename.ends_with(".tar.xz") || filename.ends_with(".txz") {
-        let file = File::open(archive_path)
-            .with_context(|| format!("Failed to open archive {:?}", archive_path))?;
-        let decoder = XzDecoder::new(file);
-        let mut archive = Archive::new(decoder);
-        archive
-            .unpack(dest)
-            .with_context(|| format!("Failed to unpack tar.xz archive {:?}", archive_path))?;
-    } else if filename.ends_with(".tar") {
-        let file = File::open(archive_path)
-            .with_context(|| format!("Failed to open archive {:?}", archive_path))?;
-        let mut archive = Archive::new(file);
-        archive
-            .unpack(dest)
-            .with_context(|| format!("Failed to unpack tar archive {:?}", archive_path))?;
-    } else if filename.ends_with(".zip") {
-        let file = File::open(archive_path)
-            .with_context(|| format!("Failed to open zip archive {:?}", archive_path))?;
-        let mut archive = ZipArchive::new(file)
-            .with_context(|| format!("Failed to read zip archive {:?}", archive_path))?;
-
-        for index in 0..archive.len() {
-            let mut entry = archive.by_index(index).with_context(|| {
-                format!("Failed to read zip entry #{index} from {:?}", archive_path)
-            })?;
-
-            let Some(enclosed) = entry.enclosed_name().map(|path| dest.join(path)) else {
-                continue;
-            };
-
-            if entry.name().ends_with('/') {
-                fs::create_dir_all(&enclosed)
-                    .with_context(|| format!("Failed to create directory {:?}", enclosed))?;
-            } else {
-                if let Some(parent) = enclosed.parent() {
-                    fs::create_dir_all(parent).with_context(|| {
-                        format!("Failed to create parent directory {:?}", parent)
-                    })?;
-                }
-
-                let mut outfile = File::create(&enclosed)
-                    .with_context(|| format!("Failed to create file {:?}", enclosed))?;
-                io::copy(&mut entry, &mut outfile)
-                    .with_context(|| format!("Failed to extract zip entry {:?}", enclosed))?;
-
-                #[cfg(unix)]
-                {
-                    use std::os::unix::fs::PermissionsExt;
-                    if let Some(mode) = entry.unix_mode() {
-                        fs::set_permissions(&enclosed, fs::Permissions::from_mode(mode))
-                            .with_context(|| {
-                                format!("Failed to set permissions on {:?}", enclosed)
-                            })?;
-                    }
-                }
-            }
-        }
-    } else {
-        let target = dest.join(
-            archive_path
-                .file_name()
-                .context("Archive path is missing a filename")?,
-        );
-        fs::copy(archive_path, &target)
-            .with_context(|| format!("Failed to copy asset {:?} to {:?}", archive_path, target))?;
-    }
-
-    Ok(())
-}
-
-pub fn resolve_binary_path(extract_root: &Path, source: &str) -> Result<PathBuf> {
+/// Locate a manifest `source` inside the extracted contents.
+///
+/// With `strip_components` set, the first N path components of every archive entry are ignored
+/// (matching `tar --strip-components`). Otherwise the source is tried relative to the extraction
+/// root and, when the archive has a single top-level directory (the usual versioned wrapper such
+/// as `ripgrep-14.0.0-x86_64-unknown-linux-musl/`), relative to that directory. Single-component
+/// sources finally fall back to a unique basename search.
+pub fn resolve_binary_path(
+    extract_root: &Path,
+    source: &str,
+    strip_components: Option<usize>,
+) -> Result<PathBuf> {
     let relative = Path::new(source);
     if relative.is_absolute() {
         bail!("Binary source path '{}' must be relative", source);
     }
 
+    if let Some(strip) = strip_components {
+        return resolve_stripped(extract_root, relative, strip).with_context(|| {
+            format!(
+                "Binary '{}' not found with strip_components = {}",
+                source, strip
+            )
+        });
+    }
+
     let direct = extract_root.join(relative);
     if direct.exists() {
         return Ok(direct);
     }
 
+    if let Some(wrapper) = single_root_dir(extract_root)? {
+        let nested = wrapper.join(relative);
+        if nested.exists() {
+            return Ok(nested);
+        }
+    }
+
     if relative.components().count() == 1 {
         let needle = relative.as_os_str();
         let mut matches = Vec::new();
@@ -515,8 +473,133 @@ pub fn resolve_binary_path(extract_root: &Path, source: &str) -> Result<PathBuf>
     );
 }
 
-pub fn resolve_extra_path(extract_root: &Path, extra: &ToolExtra) -> Result<PathBuf> {
-    resolve_binary_path(extract_root, &extra.source)
+/// Whether a manifest `source` should be treated as a glob rather than a literal path.
+pub fn is_glob_source(source: &str) -> bool {
+    source.contains(['*', '?', '[', '{'])
+}
+
+/// Expand a manifest `source` into the files it refers to.
+///
+/// Literal sources resolve through [`resolve_binary_path`]; globs are matched against paths
+/// relative to the same root (after `strip_components` or the single wrapper directory) and
+/// must match at least one file. Each match is returned with its root-relative path so callers
+/// can derive link targets exactly as they would for a literal source.
+pub fn expand_source(
+    extract_root: &Path,
+    source: &str,
+    strip_components: Option<usize>,
+) -> Result<Vec<(PathBuf, String)>> {
+    if !is_glob_source(source) {
+        let path = resolve_binary_path(extract_root, source, strip_components)?;
+        return Ok(vec![(path, source.to_string())]);
+    }
+
+    let matcher = GlobBuilder::new(source)
+        .literal_separator(true)
+        .build()
+        .with_context(|| format!("Invalid glob pattern '{}'", source))?
+        .compile_matcher();
+
+    let mut matches = match strip_components {
+        Some(strip) => glob_matches(extract_root, &matcher, strip)?,
+        None => {
+            let mut found = glob_matches(extract_root, &matcher, 0)?;
+            if found.is_empty() {
+                if let Some(wrapper) = single_root_dir(extract_root)? {
+                    found = glob_matches(&wrapper, &matcher, 0)?;
+                }
+            }
+            found
+        }
+    };
+
+    if matches.is_empty() {
+        bail!(
+            "Glob '{}' did not match any files in extracted contents under {:?}",
+            source,
+            extract_root
+        );
+    }
+
+    matches.sort();
+    Ok(matches)
+}
+
+fn glob_matches(
+    root: &Path,
+    matcher: &GlobMatcher,
+    strip: usize,
+) -> Result<Vec<(PathBuf, String)>> {
+    let mut matches = Vec::new();
+    for entry in WalkDir::new(root).min_depth(strip + 1) {
+        let entry = entry?;
+        if entry.file_type().is_dir() {
+            continue;
+        }
+
+        let relative = entry
+            .path()
+            .strip_prefix(root)
+            .unwrap_or(entry.path())
+            .components()
+            .skip(strip)
+            .collect::<PathBuf>();
+        if matcher.is_match(&relative) {
+            let relative = relative.to_string_lossy().replace('\\', "/");
+            matches.push((entry.into_path(), relative));
+        }
+    }
+    Ok(matches)
+}
+
+/// Return the sole top-level directory of the extracted contents, if there is exactly one entry.
+fn single_root_dir(extract_root: &Path) -> Result<Option<PathBuf>> {
+    let mut entries = fs::read_dir(extract_root)
+        .with_context(|| format!("Failed to read extracted contents at {:?}", extract_root))?;
+
+    let Some(first) = entries.next().transpose()? else {
+        return Ok(None);
+    };
+
+    if entries.next().is_some() || !first.file_type()?.is_dir() {
+        return Ok(None);
+    }
+
+    Ok(Some(first.path()))
+}
+
+fn resolve_stripped(extract_root: &Path, relative: &Path, strip: usize) -> Result<PathBuf> {
+    let depth = strip + relative.components().count();
+    let mut matches = Vec::new();
+
+    for entry in WalkDir::new(extract_root)
+        .min_depth(depth)
+        .max_depth(depth)
+        .into_iter()
+    {
+        let entry = entry?;
+        let stripped = entry
+            .path()
+            .strip_prefix(extract_root)
+            .unwrap_or(entry.path())
+            .components()
+            .skip(strip)
+            .collect::<PathBuf>();
+        if stripped == relative {
+            matches.push(entry.into_path());
+        }
+    }
+
+    match matches.len() {
+        1 => Ok(matches.remove(0)),
+        0 => bail!("no entry matches {:?} under {:?}", relative, extract_root),
+        _ => bail!(
+            "{:?} matched {} entries under {:?}",
+            relative,
+            matches.len(),
+            extract_root
+        ),
+    }
 }
 
 pub fn resolve_extra_target(
@@ -561,11 +644,9 @@ fn manpage_target(share_dir: &Path, resolved_source: &Path) -> Result<PathBuf> {
 }
 
 fn completion_target(share_dir: &Path, shell: &str, resolved_source: &Path) -> Result<PathBuf> {
-    let dir = match shell {
-        "zsh" => share_dir.join("zsh").join("site-functions"),
-        "bash" => share_dir.join("bash-completions"),
-        "fish" => share_dir.join("fish").join("vendor_completions.d"),
-        other => bail!("Unsupported completion shell '{}'.", other),
+    let dir = match Shell::from_name(shell) {
+        Some(shell) => shell.completion_dir(share_dir),
+        None => bail!("Unsupported completion shell '{}'.", shell),
     };
 
     fs::create_dir_all(&dir)
@@ -596,6 +677,7 @@ fn other_extra_target(share_dir: &Path, tool_slug: &str, source: &str) -> Result
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::installers::BlobStore;
     use std::sync::Arc;
     use tempfile::TempDir;
 
@@ -747,6 +829,7 @@ mod tests {
             cache_tools_dir: temp.path().join("cache"),
             bin_dir: temp.path().join("bin"),
             share_dir: temp.path().join("share"),
+            blob_store: BlobStore::new(temp.path().join("blobs/sha256")),
             github_api: Arc::new(DummyGithubApi),
         };
         fs::create_dir_all(&context.share_dir).unwrap();
@@ -773,6 +856,7 @@ mod tests {
             cache_tools_dir: temp.path().join("cache"),
             bin_dir: temp.path().join("bin"),
             share_dir: temp.path().join("share"),
+            blob_store: BlobStore::new(temp.path().join("blobs/sha256")),
             github_api: Arc::new(DummyGithubApi),
         };
         fs::create_dir_all(&context.share_dir).unwrap();
@@ -801,6 +885,7 @@ mod tests {
             cache_tools_dir: temp.path().join("cache"),
             bin_dir: temp.path().join("bin"),
             share_dir: temp.path().join("share"),
+            blob_store: BlobStore::new(temp.path().join("blobs/sha256")),
             github_api: Arc::new(DummyGithubApi),
         };
         fs::create_dir_all(&context.share_dir).unwrap();
@@ -825,7 +910,7 @@ mod tests {
         let temp = TempDir::new().unwrap();
         let root = temp.path().join("contents");
         fs::create_dir_all(&root).unwrap();
-        let err = resolve_binary_path(&root, "missing").unwrap_err();
+        let err = resolve_binary_path(&root, "missing", None).unwrap_err();
         assert!(err.to_string().contains("not found"));
     }
 
@@ -840,4 +925,68 @@ mod tests {
             "b94d27b9934d3e08a52e52d7da7dabfac484efe37a5380ee9088f7ace2efcde9"
         );
     }
+
+    #[test]
+    fn extension_score_prefers_tarballs_over_single_files() {
+        assert!(extension_score("a.tar.zst") > extension_score("a.zip"));
+        assert!(extension_score("a.tar.bz2") > extension_score("a.7z"));
+        assert!(extension_score("a.7z") > extension_score("a.gz"));
+        assert!(extension_score("a.gz") > extension_score("a"));
+    }
+
+    #[test]
+    fn resolve_binary_path_detects_single_wrapper_dir() {
+        let temp = TempDir::new().unwrap();
+        let root = temp.path().join("contents");
+        let wrapper = root.join("ripgrep-14.0.0-x86_64-unknown-linux-musl");
+        fs::create_dir_all(wrapper.join("doc")).unwrap();
+        fs::write(wrapper.join("doc/rg.1"), b"man").unwrap();
+
+        let resolved = resolve_binary_path(&root, "doc/rg.1", None).unwrap();
+        assert_eq!(resolved, wrapper.join("doc/rg.1"));
+    }
+
+    #[test]
+    fn resolve_binary_path_honours_strip_components() {
+        let temp = TempDir::new().unwrap();
+        let root = temp.path().join("contents");
+        fs::create_dir_all(root.join("pkg/tool-2.0/complete")).unwrap();
+        fs::write(root.join("pkg/tool-2.0/complete/_tool"), b"#compdef tool").unwrap();
+        fs::write(root.join("LICENSE"), b"mit").unwrap();
+
+        let resolved = resolve_binary_path(&root, "complete/_tool", Some(2)).unwrap();
+        assert_eq!(resolved, root.join("pkg/tool-2.0/complete/_tool"));
+
+        let err = resolve_binary_path(&root, "complete/_tool", Some(1)).unwrap_err();
+        assert!(err.to_string().contains("strip_components = 1"));
+    }
+
+    #[test]
+    fn expand_source_matches_globs_inside_wrapper() {
+        let temp = TempDir::new().unwrap();
+        let root = temp.path().join("contents");
+        let wrapper = root.join("bat-v0.24.0-x86_64-unknown-linux-musl");
+        fs::create_dir_all(wrapper.join("autocomplete")).unwrap();
+        fs::create_dir_all(wrapper.join("man/man1")).unwrap();
+        fs::write(wrapper.join("autocomplete/bat.zsh"), b"zsh").unwrap();
+        fs::write(wrapper.join("autocomplete/bat.fish"), b"fish").unwrap();
+        fs::write(wrapper.join("man/man1/bat.1"), b"man").unwrap();
+
+        let zsh = expand_source(&root, "autocomplete/*.zsh", None).unwrap();
+        assert_eq!(
+            zsh,
+            vec![(
+                wrapper.join("autocomplete/bat.zsh"),
+                "autocomplete/bat.zsh".to_string()
+            )]
+        );
+
+        let man = expand_source(&root, "man/**/*.1", None).unwrap();
+        assert_eq!(man.len(), 1);
+        assert_eq!(man[0].1, "man/man1/bat.1");
+
+        // `*` must not cross directory boundaries.
+        let err = expand_source(&root, "man/*.1", None).unwrap_err();
+        assert!(err.to_string().contains("did not match any files"));
+    }
 }
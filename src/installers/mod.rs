@@ -1,13 +1,22 @@
+use crate::environment::EnvValue;
+use crate::integrity;
 use crate::lockfile::{AssetRecord, BinaryLink, ExtraLink, Lockfile};
-use crate::toolset::{InstallerKind, ToolBinary, ToolDefinition, ToolExtra};
+use crate::toolset::{BinWrapper, InstallerKind, ToolBinary, ToolDefinition, ToolExtra};
 use anyhow::{bail, Context, Result};
+use std::collections::{BTreeMap, HashMap};
 use std::fs;
 use std::path::{Path, PathBuf};
 use std::sync::Arc;
 use tokio::runtime::Runtime;
 
+mod archive;
 mod github;
+mod store;
+pub(crate) mod wrapper;
 pub(crate) use self::github::GithubApi;
+#[cfg(test)]
+pub(crate) use self::github::{GithubAsset, GithubRelease};
+pub(crate) use self::store::BlobStore;
 
 pub(crate) fn default_github_api() -> Result<Arc<dyn GithubApi>> {
     github::default_api()
@@ -22,12 +31,36 @@ pub(crate) struct InstallContext {
     pub cache_tools_dir: PathBuf,
     pub bin_dir: PathBuf,
     pub share_dir: PathBuf,
+    pub blob_store: BlobStore,
     pub github_api: Arc<dyn GithubApi>,
 }
 
 pub(crate) trait ToolInstaller {
     fn requires_runtime(&self) -> bool;
-    fn install(&self, runtime: Option<&mut Runtime>, lockfile: &mut Lockfile) -> Result<()>;
+
+    /// The release tag [`ToolInstaller::fetch`] would install, looked up without downloading.
+    fn resolve_version(&self) -> Result<String>;
+
+    /// Download, verify and unpack the tool into the cache without touching any links.
+    fn fetch(&self, runtime: Option<&mut Runtime>) -> Result<FetchedTool>;
+
+    /// Link a fetched tool into the bin and share directories and record it in `lockfile`.
+    fn link(&self, fetched: FetchedTool, lockfile: &mut Lockfile) -> Result<()>;
+
+    /// Fetch and link in one step.
+    #[cfg(test)]
+    fn install(&self, runtime: Option<&mut Runtime>, lockfile: &mut Lockfile) -> Result<()> {
+        let fetched = self.fetch(runtime)?;
+        self.link(fetched, lockfile)
+    }
+}
+
+/// A tool release sitting unpacked in the cache, ready to be linked.
+pub(crate) struct FetchedTool {
+    manifest_version: String,
+    resolved_version: String,
+    extract_dir: PathBuf,
+    asset: AssetRecord,
 }
 
 pub(crate) struct InstallerDispatch {
@@ -43,6 +76,9 @@ struct GithubInstaller {
     extras: Vec<ToolExtra>,
     asset_filters: Vec<String>,
     checksum: [u8; 32],
+    strip_components: Option<usize>,
+    limits: archive::Limits,
+    env: BTreeMap<String, EnvValue>,
     context: InstallContext,
 }
 
@@ -75,131 +111,77 @@ impl GithubInstaller {
             extras: def.extras.clone(),
             asset_filters: def.asset_filter.clone(),
             checksum,
+            strip_components: def.strip_components,
+            limits: archive::Limits::from_manifest(&def.extract),
+            env: def.env.clone(),
             context,
         })
     }
-}
-
-impl ToolInstaller for GithubInstaller {
-    fn requires_runtime(&self) -> bool {
-        // Future async metadata/download will require a runtime.
-        false
-    }
-    fn install(&self, _runtime: Option<&mut Runtime>, lockfile: &mut Lockfile) -> Result<()> {
-        let release = self
-            .context
-            .github_api
-            .fetch_release(&self.project, self.version.as_deref())?;
-
-        let selected = release.select_asset(&self.asset_filters).with_context(|| {
-            format!(
-                "Failed to select asset for tool '{}' using patterns {:?}",
-                self.name, self.asset_filters
-            )
-        })?;
-
-        let manifest_version = self.version.clone().unwrap_or_else(|| "latest".to_string());
-        let resolved_version = release.tag_name.clone();
 
+    /// Link the tool's binaries and extras, pushing each link as soon as it exists so a caller
+    /// can remove them again when a later one fails.
+    fn link_files(
+        &self,
+        extract_dir: &Path,
+        binary_links: &mut Vec<BinaryLink>,
+        extra_links: &mut Vec<ExtraLink>,
+    ) -> Result<()> {
         let tool_slug = sanitize_component(&self.name);
-        let version_slug = sanitize_component(&resolved_version);
-        let version_dir = self
-            .context
-            .cache_tools_dir
-            .join(&tool_slug)
-            .join(&version_slug);
-        fs::create_dir_all(&version_dir).with_context(|| {
-            format!(
-                "Failed to create cache directory for tool '{}' at {:?}",
-                self.name, version_dir
-            )
-        })?;
-
-        let asset_path = version_dir.join(&selected.asset.name);
-
-        let mut digest = if asset_path.exists() {
-            github::compute_sha256(&asset_path)?
-        } else {
-            self.context
-                .github_api
-                .download_asset(&selected.asset.browser_download_url, &asset_path)?
-        };
 
-        if digest != self.checksum {
-            if asset_path.exists() {
-                fs::remove_file(&asset_path).with_context(|| {
+        // Resolve every link name up front: two matches sharing a basename would otherwise
+        // silently replace each other in the bin directory.
+        let mut resolved: Vec<(&ToolBinary, PathBuf, String)> = Vec::new();
+        let mut sources: HashMap<String, PathBuf> = HashMap::new();
+        for bin in &self.bins {
+            let matches = github::expand_source(extract_dir, &bin.source, self.strip_components)
+                .with_context(|| {
                     format!(
-                        "Failed to remove asset with invalid checksum at {:?}",
-                        asset_path
+                        "Failed to locate binary '{}' within archive for tool '{}'",
+                        bin.source, self.name
                     )
                 })?;
-            }
 
-            digest = self
-                .context
-                .github_api
-                .download_asset(&selected.asset.browser_download_url, &asset_path)?;
-
-            if digest != self.checksum {
+            if bin.link.is_some() && matches.len() > 1 {
                 bail!(
-                    "Checksum mismatch for asset '{}': expected {}, got {}",
-                    selected.asset.name,
-                    github::format_digest(&self.checksum),
-                    github::format_digest(&digest)
+                    "Binary '{}' in tool '{}' sets `link` but matched {} files",
+                    bin.source,
+                    self.name,
+                    matches.len()
                 );
             }
-        }
 
-        let extract_dir = version_dir.join("contents");
-        if extract_dir.exists() {
-            fs::remove_dir_all(&extract_dir).with_context(|| {
-                format!(
-                    "Failed to clear previous extraction directory for tool '{}' at {:?}",
-                    self.name, extract_dir
-                )
-            })?;
+            for (source_path, _) in matches {
+                let link_name = bin
+                    .link
+                    .clone()
+                    .or_else(|| {
+                        source_path
+                            .file_name()
+                            .map(|value| value.to_string_lossy().to_string())
+                    })
+                    .with_context(|| {
+                        format!(
+                            "Unable to determine link name for binary '{}' in tool '{}'",
+                            bin.source, self.name
+                        )
+                    })?;
+
+                if let Some(previous) = sources.insert(link_name.clone(), source_path.clone()) {
+                    bail!(
+                        "Binaries {:?} and {:?} of tool '{}' would both be linked as '{}'; set `link` to tell them apart",
+                        previous,
+                        source_path,
+                        self.name,
+                        link_name
+                    );
+                }
+                resolved.push((bin, source_path, link_name));
+            }
         }
 
-        fs::create_dir_all(&extract_dir).with_context(|| {
-            format!(
-                "Failed to create extraction directory for tool '{}' at {:?}",
-                self.name, extract_dir
-            )
-        })?;
-
-        github::extract_archive(&asset_path, &extract_dir).with_context(|| {
-            format!(
-                "Failed to extract asset '{}' for tool '{}'",
-                selected.asset.name, self.name
-            )
-        })?;
-
-        let mut binary_links = Vec::new();
-        for bin in &self.bins {
-            let source_path =
-                github::resolve_binary_path(&extract_dir, &bin.source).with_context(|| {
-                    format!(
-                        "Failed to locate binary '{}' within archive for tool '{}'",
-                        bin.source, self.name
-                    )
-                })?;
-
-            let link_name = bin
-                .link
-                .clone()
-                .or_else(|| {
-                    source_path
-                        .file_name()
-                        .map(|value| value.to_string_lossy().to_string())
-                })
-                .with_context(|| {
-                    format!(
-                        "Unable to determine link name for binary '{}' in tool '{}'",
-                        bin.source, self.name
-                    )
-                })?;
-
+        for (bin, source_path, link_name) in resolved {
             let target_path = self.context.bin_dir.join(&link_name);
+            let sha256 = integrity::record_digest(&source_path)?;
 
             if target_path.exists() || target_path.symlink_metadata().is_ok() {
                 fs::remove_file(&target_path).with_context(|| {
@@ -207,27 +189,92 @@ impl ToolInstaller for GithubInstaller {
                 })?;
             }
 
-            create_symlink(&source_path, &target_path)?;
+            let wrapper = match &bin.wrapper {
+                Some(wrapper) => {
+                    let resolved = wrapper.interpolate(extract_dir).with_context(|| {
+                        format!(
+                            "Failed to resolve wrapper for binary '{}' in tool '{}'",
+                            bin.source, self.name
+                        )
+                    })?;
+                    let script = wrapper::render(&self.name, &source_path, &resolved);
+                    wrapper::write(&target_path, &script)?;
+                    Some(resolved)
+                }
+                None => {
+                    create_symlink(&source_path, &target_path)?;
+                    None
+                }
+            };
 
             binary_links.push(BinaryLink {
                 link: link_name,
+                sha256,
                 source: source_path,
                 target: target_path,
+                wrapper,
             });
         }
 
-        let mut extra_links = Vec::new();
+        // Extras flatten into shared directories (`man<N>/`, `site-functions/`), so targets are
+        // checked for collisions up front just like bin link names.
+        let mut resolved: Vec<(String, PathBuf, PathBuf)> = Vec::new();
+        let mut sources: HashMap<PathBuf, PathBuf> = HashMap::new();
         for extra in &self.extras {
-            let resolved_source =
-                github::resolve_extra_path(&extract_dir, extra).with_context(|| {
+            let matches = github::expand_source(extract_dir, &extra.source, self.strip_components)
+                .with_context(|| {
                     format!(
                         "Failed to locate extra '{}' (kind='{}') for tool '{}'",
                         extra.source, extra.kind, self.name
                     )
                 })?;
+            let is_glob = github::is_glob_source(&extra.source);
+
+            for (resolved_source, relative) in matches {
+                // Glob matches are linked as if each had been listed on its own; an explicit
+                // `target` then names the directory that receives them.
+                let expanded;
+                let extra = if is_glob {
+                    let file_name = resolved_source
+                        .file_name()
+                        .map(|value| value.to_string_lossy().to_string())
+                        .unwrap_or_default();
+                    expanded = ToolExtra {
+                        source: relative,
+                        target: extra
+                            .target
+                            .as_ref()
+                            .map(|dir| format!("{}/{}", dir.trim_end_matches('/'), file_name)),
+                        ..extra.clone()
+                    };
+                    &expanded
+                } else {
+                    extra
+                };
+
+                let target_path = github::resolve_extra_target(
+                    &self.context,
+                    &tool_slug,
+                    extra,
+                    &resolved_source,
+                )?;
+
+                if let Some(previous) = sources.insert(target_path.clone(), resolved_source.clone())
+                {
+                    bail!(
+                        "Extras {:?} and {:?} of tool '{}' would both be linked at {:?}; set `target` to tell them apart",
+                        previous,
+                        resolved_source,
+                        self.name,
+                        target_path
+                    );
+                }
+                resolved.push((extra.kind.to_string(), resolved_source, target_path));
+            }
+        }
 
-            let target_path =
-                github::resolve_extra_target(&self.context, &tool_slug, extra, &resolved_source)?;
+        for (kind, resolved_source, target_path) in resolved {
+            let sha256 = integrity::record_digest(&resolved_source)?;
 
             if target_path.exists() || target_path.symlink_metadata().is_ok() {
                 fs::remove_file(&target_path).with_context(|| {
@@ -235,23 +282,120 @@ impl ToolInstaller for GithubInstaller {
                 })?;
             }
 
+            if let Some(parent) = target_path.parent() {
+                fs::create_dir_all(parent)
+                    .with_context(|| format!("Failed to create directory {:?}", parent))?;
+            }
+
             create_symlink(&resolved_source, &target_path)?;
 
             extra_links.push(ExtraLink {
-                kind: extra.kind.to_string(),
+                kind,
+                sha256,
                 source: resolved_source,
                 target: target_path,
             });
         }
+        Ok(())
+    }
+}
+
+impl ToolInstaller for GithubInstaller {
+    fn requires_runtime(&self) -> bool {
+        // Future async metadata/download will require a runtime.
+        false
+    }
+    fn resolve_version(&self) -> Result<String> {
+        let release = self
+            .context
+            .github_api
+            .fetch_release(&self.project, self.version.as_deref())?;
+        Ok(release.tag_name)
+    }
 
-        lockfile.record_tool_install(
-            &self.name,
-            &manifest_version,
-            &resolved_version,
-            "github",
-            binary_links,
-            extra_links,
-            Some(AssetRecord {
+    fn fetch(&self, _runtime: Option<&mut Runtime>) -> Result<FetchedTool> {
+        let release = self
+            .context
+            .github_api
+            .fetch_release(&self.project, self.version.as_deref())?;
+
+        let selected = release.select_asset(&self.asset_filters).with_context(|| {
+            format!(
+                "Failed to select asset for tool '{}' using patterns {:?}",
+                self.name, self.asset_filters
+            )
+        })?;
+
+        let manifest_version = self.version.clone().unwrap_or_else(|| "latest".to_string());
+        let resolved_version = release.tag_name.clone();
+
+        let tool_slug = sanitize_component(&self.name);
+        let version_slug = sanitize_component(&resolved_version);
+        let version_dir = self
+            .context
+            .cache_tools_dir
+            .join(&tool_slug)
+            .join(&version_slug);
+        fs::create_dir_all(&version_dir).with_context(|| {
+            format!(
+                "Failed to create cache directory for tool '{}' at {:?}",
+                self.name, version_dir
+            )
+        })?;
+
+        let asset_path = version_dir.join(&selected.asset.name);
+        let blob_store = &self.context.blob_store;
+        let expected_digest = github::format_digest(&self.checksum);
+
+        let digest = if blob_store.contains(&expected_digest) {
+            blob_store.link_into(&expected_digest, &asset_path)?;
+            self.checksum
+        } else {
+            let mut digest = if asset_path.exists() {
+                github::compute_sha256(&asset_path)?
+            } else {
+                self.context
+                    .github_api
+                    .download_asset(&selected.asset.browser_download_url, &asset_path)?
+            };
+
+            if digest != self.checksum {
+                if asset_path.exists() {
+                    fs::remove_file(&asset_path).with_context(|| {
+                        format!(
+                            "Failed to remove asset with invalid checksum at {:?}",
+                            asset_path
+                        )
+                    })?;
+                }
+
+                digest = self
+                    .context
+                    .github_api
+                    .download_asset(&selected.asset.browser_download_url, &asset_path)?;
+
+                if digest != self.checksum {
+                    bail!(
+                        "Checksum mismatch for asset '{}': expected {}, got {}",
+                        selected.asset.name,
+                        expected_digest,
+                        github::format_digest(&digest)
+                    );
+                }
+            }
+
+            blob_store.insert(&expected_digest, &asset_path)?;
+            digest
+        };
+
+        let extract_dir = version_dir.join("contents");
+        unpack(&self.name, &asset_path, &extract_dir, &self.limits)?;
+
+        Ok(FetchedTool {
+            manifest_version,
+            resolved_version,
+            extract_dir: extract_dir.clone(),
+            asset: AssetRecord {
                 name: selected.asset.name.clone(),
                 url: selected.asset.browser_download_url.clone(),
                 checksum: github::format_digest(&digest),
@@ -259,12 +403,55 @@ impl ToolInstaller for GithubInstaller {
                 extract_dir,
                 pattern_index: Some(selected.pattern_index),
                 pattern: Some(selected.pattern.to_string()),
-            }),
-        );
+            },
+        })
+    }
+
+    fn link(&self, fetched: FetchedTool, lockfile: &mut Lockfile) -> Result<()> {
+        let extract_dir = &fetched.extract_dir;
+
+        let mut env = BTreeMap::new();
+        for (key, value) in &self.env {
+            let value = value.interpolate(Some(extract_dir)).with_context(|| {
+                format!("Failed to resolve env `{}` for tool '{}'", key, self.name)
+            })?;
+            env.insert(key.clone(), value);
+        }
+
+        let mut binary_links = Vec::new();
+        let mut extra_links = Vec::new();
+        if let Err(err) = self.link_files(extract_dir, &mut binary_links, &mut extra_links) {
+            // No receipt records these links yet, so nothing else would remove them.
+            let targets = binary_links
+                .iter()
+                .map(|link| &link.target)
+                .chain(extra_links.iter().map(|link| &link.target));
+            for target in targets {
+                let _ = fs::remove_file(target);
+            }
+            return Err(err);
+        }
+
+        lockfile
+            .record_tool_install(
+                &self.name,
+                &fetched.manifest_version,
+                &fetched.resolved_version,
+                "github",
+                binary_links,
+                extra_links,
+                Some(fetched.asset),
+            )
+            .env = env;
         Ok(())
     }
 }
 
+/// Whether dws has an installer for `kind` yet (see [`create_installer`]).
+pub(crate) fn is_supported(kind: InstallerKind) -> bool {
+    matches!(kind, InstallerKind::Github)
+}
+
 pub(crate) fn create_installer(
     definition: &ToolDefinition,
     context: InstallContext,
@@ -281,6 +468,132 @@ pub(crate) fn create_installer(
     }
 }
 
+/// Unpack `archive_path` into `extract_dir`.
+///
+/// Extraction goes to a sibling `contents.partial` that only replaces the live contents once it
+/// succeeded, so a failed reinstall of the same version leaves the current links working.
+fn unpack(
+    tool: &str,
+    archive_path: &Path,
+    extract_dir: &Path,
+    limits: &archive::Limits,
+) -> Result<()> {
+    let partial_dir = extract_dir.with_file_name("contents.partial");
+    if partial_dir.exists() {
+        fs::remove_dir_all(&partial_dir).with_context(|| {
+            format!(
+                "Failed to clear previous extraction directory for tool '{}' at {:?}",
+                tool, partial_dir
+            )
+        })?;
+    }
+
+    fs::create_dir_all(&partial_dir).with_context(|| {
+        format!(
+            "Failed to create extraction directory for tool '{}' at {:?}",
+            tool, partial_dir
+        )
+    })?;
+
+    archive::extract_archive(archive_path, &partial_dir, limits).with_context(|| {
+        format!(
+            "Failed to extract asset {:?} for tool '{}'",
+            archive_path.file_name().unwrap_or_default(),
+            tool
+        )
+    })?;
+
+    if extract_dir.exists() {
+        fs::remove_dir_all(extract_dir).with_context(|| {
+            format!(
+                "Failed to clear previous extraction directory for tool '{}' at {:?}",
+                tool, extract_dir
+            )
+        })?;
+    }
+    fs::rename(&partial_dir, extract_dir).with_context(|| {
+        format!(
+            "Failed to move extracted files for tool '{}' into {:?}",
+            tool, extract_dir
+        )
+    })?;
+    Ok(())
+}
+
+/// Rebuild the `contents/` directory of an installed tool from the archive its receipt records.
+///
+/// The archive is taken from the cache (or the blob store) when it still matches the recorded
+/// checksum and downloaded from `asset.url` otherwise.
+pub(crate) fn restore_contents(
+    definition: &ToolDefinition,
+    asset: &AssetRecord,
+    context: &InstallContext,
+) -> Result<()> {
+    let checksum = asset
+        .checksum
+        .trim_start_matches("sha256:")
+        .to_ascii_lowercase();
+    let archive_path = &asset.archive_path;
+    let cached = archive_path.is_file()
+        && github::compute_sha256(archive_path).map(|digest| github::format_digest(&digest))?
+            == checksum;
+
+    if !cached {
+        if context.blob_store.contains(&checksum) {
+            context.blob_store.link_into(&checksum, archive_path)?;
+        } else {
+            if let Some(parent) = archive_path.parent() {
+                fs::create_dir_all(parent)
+                    .with_context(|| format!("Failed to create directory {:?}", parent))?;
+            }
+            let digest = github::format_digest(
+                &context
+                    .github_api
+                    .download_asset(&asset.url, archive_path)?,
+            );
+            if digest != checksum {
+                let _ = fs::remove_file(archive_path);
+                bail!(
+                    "Checksum mismatch for asset '{}': expected {}, got {}",
+                    asset.name,
+                    checksum,
+                    digest
+                );
+            }
+            context.blob_store.insert(&checksum, archive_path)?;
+        }
+    }
+
+    unpack(
+        &definition.name,
+        archive_path,
+        &asset.extract_dir,
+        &archive::Limits::from_manifest(&definition.extract),
+    )
+}
+
+/// Recreate the link for a recorded binary or extra, replacing whatever is at `target`.
+///
+/// Binaries with a recorded wrapper get their script regenerated instead of a symlink.
+pub(crate) fn relink(
+    source: &Path,
+    target: &Path,
+    wrapper: Option<(&str, &BinWrapper)>,
+) -> Result<()> {
+    if target.symlink_metadata().is_ok() {
+        fs::remove_file(target)
+            .with_context(|| format!("Failed to remove existing link at {:?}", target))?;
+    }
+    if let Some(parent) = target.parent() {
+        fs::create_dir_all(parent)
+            .with_context(|| format!("Failed to create directory {:?}", parent))?;
+    }
+    match wrapper {
+        Some((tool, wrapper)) => wrapper::write(target, &wrapper::render(tool, source, wrapper)),
+        None => create_symlink(source, target),
+    }
+}
+
 fn create_symlink(source: &Path, target: &Path) -> Result<()> {
     #[cfg(unix)]
     {
@@ -327,16 +640,23 @@ pub(crate) fn sanitize_component(value: &str) -> String {
 #[cfg(test)]
 mod tests {
     use super::github::{GithubAsset, GithubRelease};
-    use super::{create_installer, sanitize_component, GithubApi, InstallContext};
+    use super::{
+        create_installer, sanitize_component, wrapper, BlobStore, GithubApi, InstallContext,
+    };
+    use crate::environment::EnvValue;
     use crate::lockfile::Lockfile;
-    use crate::toolset::{InstallerKind, ToolBinary, ToolDefinition};
+    use crate::toolset::{
+        BinWrapper, ExtraKind, ExtractLimits, InstallerKind, ToolBinary, ToolDefinition, ToolExtra,
+    };
     use anyhow::{Context as AnyhowContext, Result as TestResult};
     use flate2::write::GzEncoder;
     use flate2::Compression;
     use sha2::{Digest as ShaDigestTrait, Sha256};
+    use std::collections::BTreeMap;
     use std::fs;
     use std::io::Cursor;
     use std::path::{Path, PathBuf};
+    use std::sync::atomic::{AtomicUsize, Ordering as AtomicOrdering};
     use std::sync::Arc;
     use tar::{Builder, Header};
     use tempfile::TempDir;
@@ -377,23 +697,28 @@ mod tests {
                 vec![ToolBinary {
                     source: "tool".to_string(),
                     link: None,
+                    wrapper: None,
                 }]
             } else {
                 bins.into_iter()
                     .map(|name| ToolBinary {
                         source: name,
                         link: None,
+                        wrapper: None,
                     })
                     .collect()
             },
             extras: Vec::new(),
             asset_filter,
             checksum,
+            strip_components: None,
+            extract: ExtractLimits::default(),
             app: None,
             team_id: None,
             self_update: false,
             platforms: Vec::new(),
             hosts: Vec::new(),
+            env: BTreeMap::new(),
         }
     }
 
@@ -418,6 +743,7 @@ mod tests {
             cache_tools_dir: PathBuf::from("/tmp/cache/tools"),
             bin_dir: PathBuf::from("/tmp/state/bin"),
             share_dir: PathBuf::from("/tmp/state/share"),
+            blob_store: BlobStore::new(PathBuf::from("/tmp/cache/blobs/sha256")),
             github_api: Arc::new(NoopGithubApi),
         }
     }
@@ -550,6 +876,7 @@ mod tests {
             cache_tools_dir: cache_tools_dir.clone(),
             bin_dir: bin_dir.clone(),
             share_dir: share_dir.clone(),
+            blob_store: BlobStore::new(temp.path().join("cache/blobs/sha256")),
             github_api: Arc::new(github_api),
         };
 
@@ -563,15 +890,19 @@ mod tests {
             bin: vec![ToolBinary {
                 source: "tool".to_string(),
                 link: None,
+                wrapper: None,
             }],
             extras: Vec::new(),
             asset_filter: vec!["tool".to_string()],
             checksum: Some(checksum),
+            strip_components: None,
+            extract: ExtractLimits::default(),
             app: None,
             team_id: None,
             self_update: false,
             platforms: Vec::new(),
             hosts: Vec::new(),
+            env: BTreeMap::new(),
         };
 
         let mut lockfile = Lockfile::new();
@@ -596,6 +927,305 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn github_installer_links_each_glob_match() -> TestResult<()> {
+        let temp = TempDir::new()?;
+        let share_dir = temp.path().join("state/share");
+        let bin_dir = temp.path().join("state/bin");
+        fs::create_dir_all(&bin_dir)?;
+
+        let asset_bytes = build_tar_gz(&[
+            ("tool-1.0/tool", b"#!/bin/sh\necho hi\n"),
+            ("tool-1.0/autocomplete/_tool", b"#compdef tool"),
+            (
+                "tool-1.0/autocomplete/_tool-helper",
+                b"#compdef tool-helper",
+            ),
+            ("tool-1.0/autocomplete/tool.bash", b"complete"),
+        ])?;
+        let digest: [u8; 32] = Sha256::digest(&asset_bytes).into();
+        let release = mock_release(
+            "tool.tar.gz",
+            "https://example.com/tool.tar.gz",
+            asset_bytes.len() as u64,
+        );
+
+        let context = InstallContext {
+            cache_tools_dir: temp.path().join("cache/tools"),
+            bin_dir: bin_dir.clone(),
+            share_dir: share_dir.clone(),
+            blob_store: BlobStore::new(temp.path().join("cache/blobs/sha256")),
+            github_api: Arc::new(MockGithubApi {
+                release,
+                asset_bytes,
+                digest,
+            }),
+        };
+
+        let mut definition = sample_definition(InstallerKind::Github, vec!["tool".to_string()]);
+        definition.checksum = Some(format!("sha256:{}", hex::encode(digest)));
+        definition.extras = vec![ToolExtra {
+            source: "autocomplete/_*".to_string(),
+            kind: ExtraKind::Completion,
+            shell: Some("zsh".to_string()),
+            target: None,
+        }];
+
+        let mut lockfile = Lockfile::new();
+        create_installer(&definition, context.clone())?
+            .expect("github installer should be created")
+            .installer
+            .install(None, &mut lockfile)?;
+
+        let receipt = lockfile.tool_receipts().next().unwrap();
+        assert_eq!(receipt.extras.len(), 2);
+        let site_functions = share_dir.join("zsh/site-functions");
+        assert!(site_functions.join("_tool").exists());
+        assert!(site_functions.join("_tool-helper").exists());
+
+        definition.extras[0].source = "completions/*.fish".to_string();
+        let err = create_installer(&definition, context)?
+            .unwrap()
+            .installer
+            .install(None, &mut Lockfile::new())
+            .unwrap_err();
+        assert!(format!("{err:#}").contains("did not match any files"));
+
+        Ok(())
+    }
+
+    #[test]
+    fn github_installer_rejects_glob_matches_sharing_a_link_name() -> TestResult<()> {
+        let temp = TempDir::new()?;
+        let bin_dir = temp.path().join("state/bin");
+        fs::create_dir_all(&bin_dir)?;
+
+        let asset_bytes = build_tar_gz(&[
+            ("tool-1.0/bin/tool", b"#!/bin/sh\necho bin\n"),
+            ("tool-1.0/libexec/tool", b"#!/bin/sh\necho libexec\n"),
+        ])?;
+        let digest: [u8; 32] = Sha256::digest(&asset_bytes).into();
+        let release = mock_release(
+            "tool.tar.gz",
+            "https://example.com/tool.tar.gz",
+            asset_bytes.len() as u64,
+        );
+        let context = InstallContext {
+            cache_tools_dir: temp.path().join("cache/tools"),
+            bin_dir: bin_dir.clone(),
+            share_dir: temp.path().join("state/share"),
+            blob_store: BlobStore::new(temp.path().join("cache/blobs/sha256")),
+            github_api: Arc::new(MockGithubApi {
+                release,
+                asset_bytes,
+                digest,
+            }),
+        };
+
+        let mut definition = sample_definition(InstallerKind::Github, vec!["*/tool".to_string()]);
+        definition.checksum = Some(format!("sha256:{}", hex::encode(digest)));
+        let err = create_installer(&definition, context)?
+            .expect("github installer should be created")
+            .installer
+            .install(None, &mut Lockfile::new())
+            .unwrap_err();
+
+        let message = format!("{err:#}");
+        assert!(message.contains("bin/tool"), "{message}");
+        assert!(message.contains("libexec/tool"), "{message}");
+        assert!(!bin_dir.join("tool").exists());
+
+        Ok(())
+    }
+
+    #[test]
+    fn github_installer_rejects_glob_extras_sharing_a_target() -> TestResult<()> {
+        let temp = TempDir::new()?;
+        let share_dir = temp.path().join("state/share");
+        let bin_dir = temp.path().join("state/bin");
+        fs::create_dir_all(&bin_dir)?;
+
+        let asset_bytes = build_tar_gz(&[
+            ("tool-1.0/tool", b"#!/bin/sh\necho hi\n"),
+            ("tool-1.0/man/man1/tool.1", b".TH TOOL 1"),
+            ("tool-1.0/man/ja/man1/tool.1", b".TH TOOL 1 ja"),
+        ])?;
+        let digest: [u8; 32] = Sha256::digest(&asset_bytes).into();
+        let release = mock_release(
+            "tool.tar.gz",
+            "https://example.com/tool.tar.gz",
+            asset_bytes.len() as u64,
+        );
+        let context = InstallContext {
+            cache_tools_dir: temp.path().join("cache/tools"),
+            bin_dir: bin_dir.clone(),
+            share_dir: share_dir.clone(),
+            blob_store: BlobStore::new(temp.path().join("cache/blobs/sha256")),
+            github_api: Arc::new(MockGithubApi {
+                release,
+                asset_bytes,
+                digest,
+            }),
+        };
+
+        let mut definition = sample_definition(InstallerKind::Github, vec!["tool".to_string()]);
+        definition.checksum = Some(format!("sha256:{}", hex::encode(digest)));
+        definition.extras = vec![ToolExtra {
+            source: "man/**/*.1".to_string(),
+            kind: ExtraKind::Man,
+            shell: None,
+            target: None,
+        }];
+        let err = create_installer(&definition, context)?
+            .expect("github installer should be created")
+            .installer
+            .install(None, &mut Lockfile::new())
+            .unwrap_err();
+
+        let message = format!("{err:#}");
+        assert!(message.contains("man/man1/tool.1"), "{message}");
+        assert!(message.contains("man/ja/man1/tool.1"), "{message}");
+        assert!(!share_dir.join("man/man1/tool.1").exists());
+
+        Ok(())
+    }
+
+    #[test]
+    fn github_installer_generates_wrapper_script() -> TestResult<()> {
+        let temp = TempDir::new()?;
+        let bin_dir = temp.path().join("state/bin");
+        fs::create_dir_all(&bin_dir)?;
+
+        let asset_bytes = build_tar_gz(&[("tool", b"#!/bin/sh\necho hi\n")])?;
+        let digest: [u8; 32] = Sha256::digest(&asset_bytes).into();
+        let release = mock_release(
+            "tool.tar.gz",
+            "https://example.com/tool.tar.gz",
+            asset_bytes.len() as u64,
+        );
+        let context = InstallContext {
+            cache_tools_dir: temp.path().join("cache/tools"),
+            bin_dir: bin_dir.clone(),
+            share_dir: temp.path().join("state/share"),
+            blob_store: BlobStore::new(temp.path().join("cache/blobs/sha256")),
+            github_api: Arc::new(MockGithubApi {
+                release,
+                asset_bytes,
+                digest,
+            }),
+        };
+
+        let mut definition = sample_definition(InstallerKind::Github, vec!["tool".to_string()]);
+        definition.checksum = Some(format!("sha256:{}", hex::encode(digest)));
+        let mut env = BTreeMap::new();
+        env.insert(
+            "TOOL_RUNTIME".to_string(),
+            EnvValue::Value("{install_dir}/runtime".to_string()),
+        );
+        definition.bin[0].wrapper = Some(BinWrapper {
+            env,
+            args: vec!["--stdio".to_string()],
+        });
+
+        let mut lockfile = Lockfile::new();
+        create_installer(&definition, context)?
+            .expect("github installer should be created")
+            .installer
+            .install(None, &mut lockfile)?;
+
+        let receipt = lockfile.tool_receipts().next().unwrap();
+        let link = &receipt.binaries[0];
+        let extract_dir = &receipt.asset.as_ref().unwrap().extract_dir;
+        let recorded = link.wrapper.as_ref().expect("wrapper recorded");
+        assert_eq!(
+            recorded.env.get("TOOL_RUNTIME"),
+            Some(&EnvValue::Value(format!(
+                "{}/runtime",
+                extract_dir.display()
+            )))
+        );
+
+        let target = bin_dir.join("tool");
+        assert!(!target.symlink_metadata()?.file_type().is_symlink());
+        let script = fs::read_to_string(&target)?;
+        assert!(wrapper::is_wrapper(&script));
+        assert_eq!(wrapper::exec_target(&script), Some(link.source.clone()));
+        assert!(script.contains("'--stdio' \"$@\""));
+
+        Ok(())
+    }
+
+    struct CountingGithubApi {
+        inner: MockGithubApi,
+        downloads: AtomicUsize,
+    }
+
+    impl GithubApi for CountingGithubApi {
+        fn fetch_release(&self, project: &str, tag: Option<&str>) -> anyhow::Result<GithubRelease> {
+            self.inner.fetch_release(project, tag)
+        }
+
+        fn download_asset(&self, url: &str, dest: &Path) -> anyhow::Result<[u8; 32]> {
+            self.downloads.fetch_add(1, AtomicOrdering::SeqCst);
+            self.inner.download_asset(url, dest)
+        }
+    }
+
+    #[test]
+    fn github_installer_shares_blob_between_tools() -> TestResult<()> {
+        let temp = TempDir::new()?;
+        let cache_tools_dir = temp.path().join("cache/tools");
+        let bin_dir = temp.path().join("state/bin");
+        let share_dir = temp.path().join("state/share");
+        fs::create_dir_all(&bin_dir)?;
+
+        let asset_bytes = build_tar_gz(&[("tool", b"#!/bin/sh\necho hi\n")])?;
+        let digest_array: [u8; 32] = Sha256::digest(&asset_bytes).into();
+        let checksum = format!("sha256:{}", hex::encode(digest_array));
+
+        let release = mock_release(
+            "tool.tar.gz",
+            "https://example.com/tool.tar.gz",
+            asset_bytes.len() as u64,
+        );
+        let github_api = Arc::new(CountingGithubApi {
+            inner: MockGithubApi {
+                release,
+                asset_bytes,
+                digest: digest_array,
+            },
+            downloads: AtomicUsize::new(0),
+        });
+
+        let blob_store = BlobStore::new(temp.path().join("cache/blobs/sha256"));
+        let context = InstallContext {
+            cache_tools_dir: cache_tools_dir.clone(),
+            bin_dir,
+            share_dir,
+            blob_store: blob_store.clone(),
+            github_api: github_api.clone(),
+        };
+
+        let mut lockfile = Lockfile::new();
+        for (name, link) in [("first", "first-tool"), ("second", "second-tool")] {
+            let mut definition = sample_definition(InstallerKind::Github, Vec::new());
+            definition.name = name.to_string();
+            definition.checksum = Some(checksum.clone());
+            definition.bin[0].link = Some(link.to_string());
+            create_installer(&definition, context.clone())?
+                .expect("github installer should be created")
+                .installer
+                .install(None, &mut lockfile)?;
+        }
+
+        assert_eq!(github_api.downloads.load(AtomicOrdering::SeqCst), 1);
+        assert!(blob_store.contains(&hex::encode(digest_array)));
+        assert!(cache_tools_dir.join("first/v1.0.0/tool.tar.gz").exists());
+        assert!(cache_tools_dir.join("second/v1.0.0/tool.tar.gz").exists());
+
+        Ok(())
+    }
+
     #[test]
     fn github_installer_rejects_checksum_mismatch() -> TestResult<()> {
         let temp = TempDir::new()?;
@@ -628,6 +1258,7 @@ mod tests {
             cache_tools_dir,
             bin_dir,
             share_dir,
+            blob_store: BlobStore::new(temp.path().join("cache/blobs/sha256")),
             github_api: Arc::new(github_api),
         };
 
@@ -641,6 +1272,7 @@ mod tests {
             bin: vec![ToolBinary {
                 source: "tool".to_string(),
                 link: None,
+                wrapper: None,
             }],
             extras: Vec::new(),
             asset_filter: vec!["tool".to_string()],
@@ -648,11 +1280,14 @@ mod tests {
                 "sha256:0000000000000000000000000000000000000000000000000000000000000000"
                     .to_string(),
             ),
+            strip_components: None,
+            extract: ExtractLimits::default(),
             app: None,
             team_id: None,
             self_update: false,
             platforms: Vec::new(),
             hosts: Vec::new(),
+            env: BTreeMap::new(),
         };
 
         let mut lockfile = Lockfile::new();
@@ -699,6 +1334,7 @@ mod tests {
             cache_tools_dir,
             bin_dir,
             share_dir,
+            blob_store: BlobStore::new(temp.path().join("cache/blobs/sha256")),
             github_api: Arc::new(github_api),
         };
 
@@ -712,15 +1348,19 @@ mod tests {
             bin: vec![ToolBinary {
                 source: "tool".to_string(),
                 link: None,
+                wrapper: None,
             }],
             extras: Vec::new(),
             asset_filter: vec!["tool".to_string()],
             checksum: Some(checksum),
+            strip_components: None,
+            extract: ExtractLimits::default(),
             app: None,
             team_id: None,
             self_update: false,
             platforms: Vec::new(),
             hosts: Vec::new(),
+            env: BTreeMap::new(),
         };
 
         let mut lockfile = Lockfile::new();
@@ -0,0 +1,175 @@
+use anyhow::{Context, Result};
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Content-addressed store for downloaded assets.
+///
+/// Blobs live at `$XDG_CACHE_HOME/dws/blobs/sha256/<digest>` and are shared between every tool,
+/// version and profile that references the same archive. Version directories under
+/// `cache_tools_dir` hold hardlinks to the blobs (falling back to copies across filesystems), so
+/// clearing a version directory never discards the download itself.
+#[derive(Debug, Clone)]
+pub(crate) struct BlobStore {
+    root: PathBuf,
+}
+
+impl BlobStore {
+    pub fn new(root: PathBuf) -> Self {
+        Self { root }
+    }
+
+    /// Path of the blob for the given hex digest.
+    pub fn path_for(&self, digest: &str) -> PathBuf {
+        self.root.join(digest.to_ascii_lowercase())
+    }
+
+    pub fn contains(&self, digest: &str) -> bool {
+        self.path_for(digest).is_file()
+    }
+
+    /// Move a verified file into the store and replace it with a link to the blob.
+    ///
+    /// When the blob already exists the file is discarded in favour of the stored copy.
+    pub fn insert(&self, digest: &str, path: &Path) -> Result<PathBuf> {
+        fs::create_dir_all(&self.root)
+            .with_context(|| format!("Failed to create blob store directory {:?}", self.root))?;
+
+        let blob = self.path_for(digest);
+        if blob.is_file() {
+            self.link_into(digest, path)?;
+            return Ok(blob);
+        }
+
+        let temp = blob.with_extension("partial");
+        if temp.exists() {
+            fs::remove_file(&temp)
+                .with_context(|| format!("Failed to remove stale partial blob {:?}", temp))?;
+        }
+
+        if fs::hard_link(path, &temp).is_err() {
+            fs::copy(path, &temp)
+                .with_context(|| format!("Failed to copy {:?} into blob store", path))?;
+        }
+
+        fs::rename(&temp, &blob)
+            .with_context(|| format!("Failed to move blob into place at {:?}", blob))?;
+
+        Ok(blob)
+    }
+
+    /// Materialise the blob for `digest` at `dest`, replacing anything already there.
+    pub fn link_into(&self, digest: &str, dest: &Path) -> Result<()> {
+        let blob = self.path_for(digest);
+
+        if let Some(parent) = dest.parent() {
+            fs::create_dir_all(parent)
+                .with_context(|| format!("Failed to create directory {:?}", parent))?;
+        }
+
+        if dest.exists() || dest.symlink_metadata().is_ok() {
+            fs::remove_file(dest)
+                .with_context(|| format!("Failed to remove existing asset at {:?}", dest))?;
+        }
+
+        if fs::hard_link(&blob, dest).is_err() {
+            fs::copy(&blob, dest)
+                .with_context(|| format!("Failed to copy blob {:?} to {:?}", blob, dest))?;
+        }
+
+        Ok(())
+    }
+
+    /// Blobs whose reference count is zero.
+    ///
+    /// `references` maps hex digests to the number of receipts that point at them.
+    pub fn unreferenced(&self, references: &HashMap<String, usize>) -> Result<Vec<PathBuf>> {
+        let mut unused = Vec::new();
+        if !self.root.exists() {
+            return Ok(unused);
+        }
+
+        for entry in fs::read_dir(&self.root)
+            .with_context(|| format!("Failed to read blob store {:?}", self.root))?
+        {
+            let entry = entry?;
+            if !entry.file_type()?.is_file() {
+                continue;
+            }
+
+            let name = entry.file_name().to_string_lossy().to_ascii_lowercase();
+            let referenced = references.get(&name).copied().unwrap_or(0) > 0;
+            if !referenced {
+                unused.push(entry.path());
+            }
+        }
+
+        Ok(unused)
+    }
+
+    /// Remove blobs whose reference count is zero. Returns the paths that were removed.
+    pub fn collect_garbage(&self, references: &HashMap<String, usize>) -> Result<Vec<PathBuf>> {
+        let removed = self.unreferenced(references)?;
+        for path in &removed {
+            fs::remove_file(path)
+                .with_context(|| format!("Failed to remove unreferenced blob {:?}", path))?;
+        }
+        Ok(removed)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    const DIGEST: &str = "b94d27b9934d3e08a52e52d7da7dabfac484efe37a5380ee9088f7ace2efcde9";
+
+    #[test]
+    fn insert_moves_file_into_store_and_links_back() {
+        let temp = TempDir::new().unwrap();
+        let store = BlobStore::new(temp.path().join("blobs/sha256"));
+        let asset = temp.path().join("tools/tool/v1/tool.tar.gz");
+        fs::create_dir_all(asset.parent().unwrap()).unwrap();
+        fs::write(&asset, b"hello world").unwrap();
+
+        let blob = store.insert(DIGEST, &asset).unwrap();
+        assert_eq!(blob, store.path_for(DIGEST));
+        assert!(store.contains(DIGEST));
+        assert_eq!(fs::read(&asset).unwrap(), b"hello world");
+    }
+
+    #[test]
+    fn link_into_survives_version_directory_removal() {
+        let temp = TempDir::new().unwrap();
+        let store = BlobStore::new(temp.path().join("blobs/sha256"));
+        let first = temp.path().join("tools/a/v1/asset.tar.gz");
+        fs::create_dir_all(first.parent().unwrap()).unwrap();
+        fs::write(&first, b"hello world").unwrap();
+        store.insert(DIGEST, &first).unwrap();
+
+        fs::remove_dir_all(temp.path().join("tools/a")).unwrap();
+        assert!(store.contains(DIGEST));
+
+        let second = temp.path().join("tools/b/v2/asset.tar.gz");
+        store.link_into(DIGEST, &second).unwrap();
+        assert_eq!(fs::read(&second).unwrap(), b"hello world");
+    }
+
+    #[test]
+    fn collect_garbage_removes_unreferenced_blobs() {
+        let temp = TempDir::new().unwrap();
+        let store = BlobStore::new(temp.path().join("blobs/sha256"));
+        fs::create_dir_all(temp.path().join("blobs/sha256")).unwrap();
+        fs::write(store.path_for(DIGEST), b"keep").unwrap();
+        fs::write(store.path_for("deadbeef"), b"drop").unwrap();
+
+        let mut references = HashMap::new();
+        references.insert(DIGEST.to_string(), 2);
+        references.insert("deadbeef".to_string(), 0);
+
+        let removed = store.collect_garbage(&references).unwrap();
+        assert_eq!(removed, vec![store.path_for("deadbeef")]);
+        assert!(store.contains(DIGEST));
+    }
+}
@@ -0,0 +1,148 @@
+use anyhow::{Context, Result};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::environment::EnvValue;
+use crate::toolset::BinWrapper;
+
+/// First comment line of every generated wrapper; used to recognise scripts dws owns.
+pub(crate) const WRAPPER_MARKER: &str = "# dws wrapper";
+
+/// Render a POSIX `sh` launcher that exports `wrapper.env` and execs `source`.
+pub(crate) fn render(tool: &str, source: &Path, wrapper: &BinWrapper) -> String {
+    let mut script =
+        format!("#!/bin/sh\n{WRAPPER_MARKER} for tool '{tool}'; regenerated by `dws sync`.\n");
+
+    for (name, value) in &wrapper.env {
+        let value = match value {
+            EnvValue::Value(value) => sh_quote(value),
+            EnvValue::PathList(list) => {
+                let prepend = list.prepend.join(":");
+                let append = list.append.join(":");
+                match (prepend.is_empty(), append.is_empty()) {
+                    (false, false) => format!(
+                        "{}\"${{{name}:+:${name}}}\"{}",
+                        sh_quote(&prepend),
+                        sh_quote(&format!(":{append}"))
+                    ),
+                    (false, true) => format!("{}\"${{{name}:+:${name}}}\"", sh_quote(&prepend)),
+                    (true, false) => format!("\"${{{name}:+${name}:}}\"{}", sh_quote(&append)),
+                    (true, true) => format!("\"${{{name}:-}}\""),
+                }
+            }
+        };
+        script.push_str(&format!("export {name}={value}\n"));
+    }
+
+    script.push_str("exec ");
+    script.push_str(&sh_quote(&source.to_string_lossy()));
+    for arg in &wrapper.args {
+        script.push(' ');
+        script.push_str(&sh_quote(arg));
+    }
+    script.push_str(" \"$@\"\n");
+    script
+}
+
+/// Write `script` to `target` with mode `0o755`.
+pub(crate) fn write(target: &Path, script: &str) -> Result<()> {
+    fs::write(target, script)
+        .with_context(|| format!("Failed to write wrapper script {:?}", target))?;
+
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        fs::set_permissions(target, fs::Permissions::from_mode(0o755))
+            .with_context(|| format!("Failed to mark wrapper {:?} executable", target))?;
+    }
+
+    Ok(())
+}
+
+/// Whether `script` was generated by [`render`].
+pub(crate) fn is_wrapper(script: &str) -> bool {
+    script
+        .lines()
+        .nth(1)
+        .is_some_and(|line| line.starts_with(WRAPPER_MARKER))
+}
+
+/// Extract the executable a generated wrapper launches.
+pub(crate) fn exec_target(script: &str) -> Option<PathBuf> {
+    let line = script.lines().find(|line| line.starts_with("exec '"))?;
+    let mut target = String::new();
+    let mut rest = &line["exec ".len()..];
+
+    // Re-assemble the first shell word, which `sh_quote` may have split around `'` characters.
+    loop {
+        if let Some(quoted) = rest.strip_prefix('\'') {
+            let end = quoted.find('\'')?;
+            target.push_str(&quoted[..end]);
+            rest = &quoted[end + 1..];
+        } else if let Some(escaped) = rest.strip_prefix("\\'") {
+            target.push('\'');
+            rest = escaped;
+        } else {
+            break;
+        }
+    }
+
+    Some(PathBuf::from(target))
+}
+
+fn sh_quote(value: &str) -> String {
+    format!("'{}'", value.replace('\'', "'\\''"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::environment::PathList;
+    use std::collections::BTreeMap;
+
+    #[test]
+    fn render_exports_env_and_forwards_arguments() {
+        let mut env = BTreeMap::new();
+        env.insert(
+            "LD_LIBRARY_PATH".to_string(),
+            EnvValue::PathList(PathList {
+                prepend: vec!["/opt/tool/lib".to_string()],
+                append: Vec::new(),
+            }),
+        );
+        env.insert(
+            "TOOL_HOME".to_string(),
+            EnvValue::Value("/opt/tool".to_string()),
+        );
+        let wrapper = BinWrapper {
+            env,
+            args: vec!["--data-dir".to_string(), "it's here".to_string()],
+        };
+
+        let script = render("tool", Path::new("/opt/tool/bin/tool"), &wrapper);
+        assert!(script.starts_with("#!/bin/sh\n# dws wrapper"));
+        assert!(script.contains(
+            "export LD_LIBRARY_PATH='/opt/tool/lib'\"${LD_LIBRARY_PATH:+:$LD_LIBRARY_PATH}\"\n"
+        ));
+        assert!(script.contains("export TOOL_HOME='/opt/tool'\n"));
+        assert!(script.ends_with("exec '/opt/tool/bin/tool' '--data-dir' 'it'\\''s here' \"$@\"\n"));
+        assert!(is_wrapper(&script));
+        assert_eq!(
+            exec_target(&script),
+            Some(PathBuf::from("/opt/tool/bin/tool"))
+        );
+    }
+
+    #[test]
+    fn exec_target_handles_quotes_in_path() {
+        let script = render(
+            "tool",
+            Path::new("/opt/o'neil/tool"),
+            &BinWrapper::default(),
+        );
+        assert_eq!(
+            exec_target(&script),
+            Some(PathBuf::from("/opt/o'neil/tool"))
+        );
+    }
+}
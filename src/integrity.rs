@@ -0,0 +1,316 @@
+use crate::installers::wrapper;
+use crate::lockfile::{ReceiptStatus, ToolReceipt};
+use anyhow::{Context, Result};
+use sha2::{Digest, Sha256};
+use std::fmt;
+use std::fs::{self, File};
+use std::io::{self, ErrorKind};
+use std::path::{Path, PathBuf};
+
+/// `sha256:<hex>` of the file at `path`, streamed so large binaries are not read into memory.
+pub fn sha256_file(path: &Path) -> io::Result<String> {
+    let mut hasher = Sha256::new();
+    io::copy(&mut File::open(path)?, &mut hasher)?;
+    Ok(format!("sha256:{}", hex::encode(hasher.finalize())))
+}
+
+/// Digest to record for a linked file at install time; directories are not hashed.
+pub fn record_digest(path: &Path) -> Result<Option<String>> {
+    if !path.is_file() {
+        return Ok(None);
+    }
+    sha256_file(path)
+        .map(Some)
+        .with_context(|| format!("Failed to hash {:?}", path))
+}
+
+/// Something [`verify_receipt`] found wrong with a tool's files.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Finding {
+    /// A linked file is gone or unreadable
+    Missing(PathBuf),
+    /// A linked file no longer matches the digest recorded at install time
+    Modified(PathBuf),
+    /// The cached download is gone, so only the extracted files were checked
+    ArchiveMissing(PathBuf),
+    /// The cached download no longer matches the release checksum
+    ArchiveModified(PathBuf),
+}
+
+impl fmt::Display for Finding {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Finding::Missing(path) => write!(f, "missing: {}", path.display()),
+            Finding::Modified(path) => write!(f, "modified since install: {}", path.display()),
+            Finding::ArchiveMissing(path) => {
+                write!(f, "cached archive missing: {}", path.display())
+            }
+            Finding::ArchiveModified(path) => {
+                write!(
+                    f,
+                    "cached archive does not match its checksum: {}",
+                    path.display()
+                )
+            }
+        }
+    }
+}
+
+/// Outcome of re-hashing one receipt's files.
+#[derive(Debug, Clone)]
+pub struct Verification {
+    pub name: String,
+    pub version: String,
+    pub status: ReceiptStatus,
+    /// Files compared against a recorded digest
+    pub checked: usize,
+    pub findings: Vec<Finding>,
+}
+
+/// Re-hash the binaries, extras and cached archive of `receipt`.
+///
+/// Files without a recorded digest (directories, receipts from older lockfiles) are only checked
+/// for existence. A receipt with nothing to compare stays [`ReceiptStatus::Unverified`].
+pub fn verify_receipt(receipt: &ToolReceipt) -> Verification {
+    let mut checked = 0;
+    let mut findings = Vec::new();
+
+    let files = receipt
+        .binaries
+        .iter()
+        .map(|bin| (&bin.source, &bin.sha256))
+        .chain(
+            receipt
+                .extras
+                .iter()
+                .map(|extra| (&extra.source, &extra.sha256)),
+        );
+    for (path, recorded) in files {
+        if fs::symlink_metadata(path).is_err() {
+            findings.push(Finding::Missing(path.clone()));
+            continue;
+        }
+        let Some(recorded) = recorded else {
+            continue;
+        };
+        checked += 1;
+        match sha256_file(path) {
+            Ok(actual) if actual == *recorded => {}
+            Ok(_) => findings.push(Finding::Modified(path.clone())),
+            Err(_) => findings.push(Finding::Missing(path.clone())),
+        }
+    }
+
+    if let Some(asset) = &receipt.asset {
+        let archive = &asset.archive_path;
+        if archive.is_file() {
+            checked += 1;
+            let expected = format!(
+                "sha256:{}",
+                asset
+                    .checksum
+                    .trim_start_matches("sha256:")
+                    .to_ascii_lowercase()
+            );
+            if sha256_file(archive).ok().as_ref() != Some(&expected) {
+                findings.push(Finding::ArchiveModified(archive.clone()));
+            }
+        } else {
+            findings.push(Finding::ArchiveMissing(archive.clone()));
+        }
+    }
+
+    let status = if findings
+        .iter()
+        .any(|finding| matches!(finding, Finding::Modified(_) | Finding::ArchiveModified(_)))
+    {
+        ReceiptStatus::ChecksumMismatch
+    } else if findings
+        .iter()
+        .any(|finding| matches!(finding, Finding::Missing(_)))
+    {
+        ReceiptStatus::MissingSource
+    } else if checked == 0 {
+        ReceiptStatus::Unverified
+    } else {
+        ReceiptStatus::Ok
+    };
+
+    Verification {
+        name: receipt.name.clone(),
+        version: receipt.resolved_version.clone(),
+        status,
+        checked,
+        findings,
+    }
+}
+
+/// Compare the symlink at `target` against the `source` a receipt recorded for it.
+pub(crate) fn check_symlink(source: &Path, target: &Path) -> LinkState {
+    match fs::symlink_metadata(target) {
+        Ok(metadata) => {
+            if !metadata.file_type().is_symlink() {
+                return LinkState::NotSymlink;
+            }
+        }
+        Err(err) => {
+            if err.kind() == ErrorKind::NotFound {
+                return LinkState::MissingTarget;
+            }
+            return LinkState::IoError(err);
+        }
+    }
+
+    match fs::read_link(target) {
+        Ok(actual) => {
+            let resolved = if actual.is_absolute() {
+                actual
+            } else if let Some(parent) = target.parent() {
+                parent.join(actual)
+            } else {
+                actual
+            };
+
+            if resolved != source {
+                return LinkState::WrongTarget { actual: resolved };
+            }
+        }
+        Err(err) => return LinkState::IoError(err),
+    }
+
+    if !(source.exists() || source.symlink_metadata().is_ok()) {
+        return LinkState::MissingSource;
+    }
+
+    LinkState::Ok
+}
+
+/// Wrapper scripts are regular files, so they are checked by reading the `exec` line back.
+///
+/// `NotSymlink` is reported when the target is a symlink or a file dws did not generate.
+pub(crate) fn check_wrapper(source: &Path, target: &Path) -> LinkState {
+    match fs::symlink_metadata(target) {
+        Ok(metadata) if metadata.file_type().is_symlink() => return LinkState::NotSymlink,
+        Ok(_) => {}
+        Err(err) if err.kind() == ErrorKind::NotFound => return LinkState::MissingTarget,
+        Err(err) => return LinkState::IoError(err),
+    }
+
+    let script = match fs::read_to_string(target) {
+        Ok(script) => script,
+        Err(err) if err.kind() == ErrorKind::InvalidData => return LinkState::NotSymlink,
+        Err(err) => return LinkState::IoError(err),
+    };
+
+    if !wrapper::is_wrapper(&script) {
+        return LinkState::NotSymlink;
+    }
+
+    match wrapper::exec_target(&script) {
+        Some(actual) if actual == source => {}
+        Some(actual) => return LinkState::WrongTarget { actual },
+        None => return LinkState::NotSymlink,
+    }
+
+    if !source.exists() {
+        return LinkState::MissingSource;
+    }
+
+    LinkState::Ok
+}
+
+/// How a recorded link looks on disk.
+#[derive(Debug)]
+pub(crate) enum LinkState {
+    Ok,
+    MissingTarget,
+    NotSymlink,
+    WrongTarget { actual: PathBuf },
+    MissingSource,
+    IoError(io::Error),
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lockfile::{AssetRecord, BinaryLink, Lockfile};
+    use tempfile::TempDir;
+
+    fn installed(temp: &TempDir) -> ToolReceipt {
+        let contents = temp.path().join("contents");
+        fs::create_dir_all(&contents).unwrap();
+        let binary = contents.join("tool");
+        fs::write(&binary, "#!/bin/sh\n").unwrap();
+        let archive = temp.path().join("tool.tar.gz");
+        fs::write(&archive, "archive").unwrap();
+
+        let mut lockfile = Lockfile::new();
+        lockfile.record_tool_install(
+            "tool",
+            "latest",
+            "v1.0.0",
+            "github",
+            vec![BinaryLink {
+                link: "tool".to_string(),
+                source: binary.clone(),
+                target: temp.path().join("bin/tool"),
+                wrapper: None,
+                sha256: record_digest(&binary).unwrap(),
+            }],
+            Vec::new(),
+            Some(AssetRecord {
+                checksum: sha256_file(&archive)
+                    .unwrap()
+                    .trim_start_matches("sha256:")
+                    .to_string(),
+                archive_path: archive,
+                extract_dir: contents,
+                ..AssetRecord::default()
+            }),
+        );
+        lockfile.tool_receipts[0].clone()
+    }
+
+    #[test]
+    fn intact_files_verify() {
+        let temp = TempDir::new().unwrap();
+        let verification = verify_receipt(&installed(&temp));
+        assert_eq!(verification.status, ReceiptStatus::Ok);
+        assert_eq!(verification.checked, 2);
+        assert!(verification.findings.is_empty());
+    }
+
+    #[test]
+    fn detects_modified_binary_and_archive() {
+        let temp = TempDir::new().unwrap();
+        let receipt = installed(&temp);
+        fs::write(&receipt.binaries[0].source, "#!/bin/sh\nevil\n").unwrap();
+        fs::write(temp.path().join("tool.tar.gz"), "tampered").unwrap();
+
+        let verification = verify_receipt(&receipt);
+        assert_eq!(verification.status, ReceiptStatus::ChecksumMismatch);
+        assert_eq!(
+            verification.findings,
+            [
+                Finding::Modified(receipt.binaries[0].source.clone()),
+                Finding::ArchiveModified(temp.path().join("tool.tar.gz")),
+            ]
+        );
+    }
+
+    #[test]
+    fn missing_files_and_unrecorded_digests() {
+        let temp = TempDir::new().unwrap();
+        let mut receipt = installed(&temp);
+        fs::remove_file(temp.path().join("tool.tar.gz")).unwrap();
+        receipt.binaries[0].sha256 = None;
+
+        // Nothing left to compare: the archive is gone and the binary has no digest.
+        let verification = verify_receipt(&receipt);
+        assert_eq!(verification.status, ReceiptStatus::Unverified);
+
+        fs::remove_file(&receipt.binaries[0].source).unwrap();
+        let verification = verify_receipt(&receipt);
+        assert_eq!(verification.status, ReceiptStatus::MissingSource);
+    }
+}
@@ -8,16 +8,26 @@ mod config;
 mod dotfiles;
 mod environment;
 mod installers;
+mod integrity;
 mod lockfile;
+mod plan;
+mod process_lock;
 mod profile;
+mod project;
+mod repair;
+mod secrets;
+mod template;
 mod toolset;
+mod transaction;
 mod workspace;
 
 // Re-export main types
 pub use config::Config;
-pub use dotfiles::{DotfileEntry, Dotfiles};
+pub use dotfiles::{ConflictPolicy, DotfileEntry, Dotfiles, EntryPlan, InstallOutcome};
 pub use environment::{Environment, Shell};
 pub use lockfile::Lockfile;
+pub use plan::{Action, ActionKind, Plan};
+pub use process_lock::{LockMode, ProcessLock};
 pub use profile::Profile;
 pub use toolset::{InstallerKind, ToolDefinition, ToolEntry, ToolSet};
 pub use workspace::{Workspace, WorkspacePath};
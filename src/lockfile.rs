@@ -1,7 +1,19 @@
-use anyhow::{Context, Result};
+use anyhow::{bail, Context, Result};
 use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
 use std::fs;
 use std::path::{Path, PathBuf};
+use toml::{Table, Value};
+
+use crate::environment::EnvValue;
+use crate::toolset::BinWrapper;
+use crate::ui;
+
+/// Lockfile schema written by this version of dws.
+pub const LOCKFILE_VERSION: u32 = 3;
+
+/// Upgrades a lockfile document by one schema version, indexed by the version it upgrades from.
+const MIGRATIONS: [fn(&mut Table) -> Result<()>; 2] = [migrate_v1, migrate_v2];
 
 /// Lockfile format (similar to Cargo.lock)
 /// Records the resolved state of the installed workspace
@@ -11,9 +23,15 @@ pub struct Lockfile {
     version: u32,
     /// Metadata about when this was generated
     pub metadata: Metadata,
+    /// Profile-level environment variables, interpolated at install time
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub env: BTreeMap<String, EnvValue>,
     /// Config file symlinks (dotfiles)
     #[serde(default)]
     pub config_symlinks: Vec<SymlinkEntry>,
+    /// Pre-existing files moved aside to make room for config symlinks
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub backups: Vec<BackupEntry>,
     /// Tool installation receipts (schema v2 placeholder)
     #[serde(default)]
     pub tool_receipts: Vec<ToolReceipt>,
@@ -23,14 +41,36 @@ pub struct Lockfile {
 pub struct Metadata {
     /// When this workspace was installed/updated
     pub installed_at: String,
+    /// When this file was last written
+    #[serde(default)]
+    pub generated_at: String,
+    /// Version of dws that last wrote this file
+    #[serde(default)]
+    pub dws_version: String,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct SymlinkEntry {
     /// Source path (in workspace)
     pub source: PathBuf,
-    /// Target path (in XDG_CONFIG_HOME)
+    /// Target path (under XDG_CONFIG_HOME, HOME, XDG_DATA_HOME or XDG_STATE_HOME)
     pub target: PathBuf,
+    /// `sha256:<hex>` of the output when `source` is a template rendered into a regular file
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub rendered: Option<String>,
+    /// Overlay directory (e.g. `config@linux`) that supplied `source`; unset for the base
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub overlay: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BackupEntry {
+    /// Where the file lived before dws linked over it
+    pub original: PathBuf,
+    /// Where it was moved to (under $XDG_STATE_HOME/dws/backups)
+    pub backup: PathBuf,
+    /// When the backup was taken
+    pub created_at: String,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -41,6 +81,12 @@ pub struct BinaryLink {
     pub source: PathBuf,
     /// Absolute target path of the symlink in the workspace bin directory
     pub target: PathBuf,
+    /// Present when `target` is a generated wrapper script rather than a symlink
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub wrapper: Option<BinWrapper>,
+    /// `sha256:<hex>` of `source` when it was linked
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sha256: Option<String>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, Default)]
@@ -63,6 +109,46 @@ pub struct AssetRecord {
     pub pattern: Option<String>,
 }
 
+/// Integrity of a receipt's files as of the last check.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ReceiptStatus {
+    /// Installed from a checksummed download, or verified since
+    #[default]
+    Ok,
+    /// No checksum was recorded (receipts migrated from v1 lockfiles)
+    Unverified,
+    /// `dws verify` found a file that no longer matches its recorded digest
+    ChecksumMismatch,
+    /// `dws verify` found a linked file or directory missing
+    MissingSource,
+}
+
+impl ReceiptStatus {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            ReceiptStatus::Ok => "ok",
+            ReceiptStatus::Unverified => "unverified",
+            ReceiptStatus::ChecksumMismatch => "checksum_mismatch",
+            ReceiptStatus::MissingSource => "missing_source",
+        }
+    }
+
+    /// Whether the last verification found the installed files damaged.
+    pub fn is_damaged(self) -> bool {
+        matches!(
+            self,
+            ReceiptStatus::ChecksumMismatch | ReceiptStatus::MissingSource
+        )
+    }
+}
+
+impl std::fmt::Display for ReceiptStatus {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ToolReceipt {
     /// Tool name
@@ -82,6 +168,12 @@ pub struct ToolReceipt {
     pub extras: Vec<ExtraLink>,
     #[serde(default, skip_serializing_if = "Option::is_none")]
     pub asset: Option<AssetRecord>,
+    /// Tool-specific environment variables with `{install_dir}` already expanded
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub env: BTreeMap<String, EnvValue>,
+    /// Integrity status of the linked files
+    #[serde(default)]
+    pub status: ReceiptStatus,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -89,6 +181,9 @@ pub struct ExtraLink {
     pub kind: String,
     pub source: PathBuf,
     pub target: PathBuf,
+    /// `sha256:<hex>` of `source` when it was linked; not recorded for directories
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sha256: Option<String>,
 }
 
 impl Default for Lockfile {
@@ -102,23 +197,69 @@ impl Lockfile {
     pub fn new() -> Self {
         let now = chrono::Utc::now().to_rfc3339();
         Self {
-            version: 2,
-            metadata: Metadata { installed_at: now },
+            version: LOCKFILE_VERSION,
+            metadata: Metadata {
+                installed_at: now.clone(),
+                generated_at: now,
+                dws_version: env!("CARGO_PKG_VERSION").to_string(),
+            },
+            env: BTreeMap::new(),
             config_symlinks: Vec::new(),
+            backups: Vec::new(),
             tool_receipts: Vec::new(),
         }
     }
 
     /// Load lockfile from disk
+    ///
+    /// Lockfiles from older schemas are migrated in memory only; the file itself is upgraded the
+    /// next time a command holding the exclusive process lock saves it (see [`Lockfile::save`]).
+    /// Lockfiles from a newer dws are refused rather than read partially.
     pub fn load(path: &Path) -> Result<Self> {
         let contents = fs::read_to_string(path)
             .with_context(|| format!("Failed to read lockfile from {:?}", path))?;
 
-        toml::from_str(&contents)
-            .with_context(|| format!("Failed to parse lockfile from {:?}", path))
+        let mut document: Table = toml::from_str(&contents)
+            .with_context(|| format!("Failed to parse lockfile from {:?}", path))?;
+        let version = schema_version(&document)
+            .with_context(|| format!("Failed to parse lockfile from {:?}", path))?;
+
+        if version > LOCKFILE_VERSION {
+            bail!(
+                "Lockfile {:?} uses schema v{version}, but dws {} only understands up to v{LOCKFILE_VERSION}. \
+                 It was written by a newer dws; upgrade with `dws self update` and try again.",
+                path,
+                env!("CARGO_PKG_VERSION")
+            );
+        }
+        if version == LOCKFILE_VERSION {
+            return Value::Table(document)
+                .try_into()
+                .with_context(|| format!("Failed to parse lockfile from {:?}", path));
+        }
+
+        for from in version..LOCKFILE_VERSION {
+            MIGRATIONS[from as usize - 1](&mut document).with_context(|| {
+                format!(
+                    "Failed to migrate lockfile {:?} from schema v{from} to v{}",
+                    path,
+                    from + 1
+                )
+            })?;
+        }
+        document.insert("version".to_string(), Value::from(LOCKFILE_VERSION));
+
+        let mut lockfile: Lockfile = Value::Table(document)
+            .try_into()
+            .with_context(|| format!("Failed to parse migrated lockfile from {:?}", path))?;
+        lockfile.stamp();
+        Ok(lockfile)
     }
 
     /// Save lockfile to disk
+    ///
+    /// When the file being replaced uses an older schema it is first copied to
+    /// `<path>.v<N>.bak`, so the pre-migration state survives the upgrade.
     pub fn save(&self, path: &Path) -> Result<()> {
         // Ensure parent directory exists
         if let Some(parent) = path.parent() {
@@ -126,17 +267,70 @@ impl Lockfile {
                 .with_context(|| format!("Failed to create lockfile directory {:?}", parent))?;
         }
 
-        let contents = toml::to_string_pretty(self).context("Failed to serialize lockfile")?;
-
-        fs::write(path, contents)
-            .with_context(|| format!("Failed to write lockfile to {:?}", path))?;
-
+        let migrated_from = back_up_older_schema(path)?;
+
+        let mut stamped = self.clone();
+        stamped.stamp();
+        let contents = toml::to_string_pretty(&stamped).context("Failed to serialize lockfile")?;
+
+        // Write next to the lockfile and rename over it so a crash never leaves a truncated file.
+        let temp = with_suffix(path, ".tmp");
+        fs::write(&temp, contents)
+            .with_context(|| format!("Failed to write lockfile to {:?}", temp))?;
+        fs::rename(&temp, path)
+            .with_context(|| format!("Failed to move lockfile into place at {:?}", path))?;
+
+        if let Some((version, backup)) = migrated_from {
+            ui::warn(format!(
+                "Migrated lockfile {} from schema v{version} to v{LOCKFILE_VERSION} (previous copy at {})",
+                path.display(),
+                backup.display()
+            ));
+        }
         Ok(())
     }
 
+    /// Record this dws as the writer of the current schema.
+    fn stamp(&mut self) {
+        self.version = LOCKFILE_VERSION;
+        self.metadata.generated_at = chrono::Utc::now().to_rfc3339();
+        self.metadata.dws_version = env!("CARGO_PKG_VERSION").to_string();
+    }
+
     /// Add a config symlink entry
-    pub fn add_config_symlink(&mut self, source: PathBuf, target: PathBuf) {
-        self.config_symlinks.push(SymlinkEntry { source, target });
+    ///
+    /// Returns the new entry so callers can attach optional details such as `overlay`.
+    pub fn add_config_symlink(&mut self, source: PathBuf, target: PathBuf) -> &mut SymlinkEntry {
+        self.config_symlinks.push(SymlinkEntry {
+            source,
+            target,
+            rendered: None,
+            overlay: None,
+        });
+        self.config_symlinks
+            .last_mut()
+            .expect("entry was just recorded")
+    }
+
+    /// Add a rendered template entry with the digest of the written output
+    pub fn add_rendered_config(
+        &mut self,
+        source: PathBuf,
+        target: PathBuf,
+        digest: String,
+    ) -> &mut SymlinkEntry {
+        let entry = self.add_config_symlink(source, target);
+        entry.rendered = Some(digest);
+        entry
+    }
+
+    /// Record a file moved aside by the `backup` conflict policy
+    pub fn add_backup(&mut self, original: PathBuf, backup: PathBuf) {
+        self.backups.push(BackupEntry {
+            original,
+            backup,
+            created_at: chrono::Utc::now().to_rfc3339(),
+        });
     }
 
     /// Add a tool receipt (skeleton placeholder for Phase 0)
@@ -161,6 +355,8 @@ impl Lockfile {
             binaries,
             extras,
             asset,
+            env: BTreeMap::new(),
+            status: ReceiptStatus::Ok,
         });
     }
 
@@ -169,6 +365,7 @@ impl Lockfile {
     /// This should be preferred over calling `add_tool_receipt` directly in installer backends.
     /// `manifest_version` is the version string as specified in the manifest (may be "latest").
     /// `resolved_version` is the concrete tag/version determined during installation.
+    /// Returns the new receipt so callers can attach optional details such as `env`.
     #[allow(clippy::too_many_arguments)]
     pub fn record_tool_install(
         &mut self,
@@ -179,7 +376,7 @@ impl Lockfile {
         binaries: Vec<BinaryLink>,
         extras: Vec<ExtraLink>,
         asset: Option<AssetRecord>,
-    ) {
+    ) -> &mut ToolReceipt {
         let installed_at = chrono::Utc::now().to_rfc3339();
         self.add_tool_receipt(
             name.to_string(),
@@ -191,6 +388,9 @@ impl Lockfile {
             extras,
             asset,
         );
+        self.tool_receipts
+            .last_mut()
+            .expect("receipt was just recorded")
     }
 
     /// Iterate over all config symlink entries
@@ -203,6 +403,11 @@ impl Lockfile {
         self.tool_receipts.iter()
     }
 
+    /// Iterate mutably over all tool receipt entries
+    pub fn tool_receipts_mut(&mut self) -> impl Iterator<Item = &mut ToolReceipt> {
+        self.tool_receipts.iter_mut()
+    }
+
     /// Retain only tool receipt entries that satisfy the provided predicate.
     pub fn retain_tool_receipts<F>(&mut self, mut predicate: F)
     where
@@ -212,6 +417,144 @@ impl Lockfile {
     }
 }
 
+/// Copy the lockfile at `path` to `<path>.v<N>.bak` when it uses an older schema.
+///
+/// Returns the old version and the backup path. Unreadable files are left for `save` to replace.
+fn back_up_older_schema(path: &Path) -> Result<Option<(u32, PathBuf)>> {
+    let Ok(contents) = fs::read_to_string(path) else {
+        return Ok(None);
+    };
+    let Some(version) = toml::from_str::<Table>(&contents)
+        .ok()
+        .and_then(|document| schema_version(&document).ok())
+        .filter(|version| *version < LOCKFILE_VERSION)
+    else {
+        return Ok(None);
+    };
+
+    let backup = with_suffix(path, &format!(".v{version}.bak"));
+    fs::write(&backup, contents)
+        .with_context(|| format!("Failed to back up lockfile {:?} to {:?}", path, backup))?;
+    Ok(Some((version, backup)))
+}
+
+/// `path` with `suffix` appended to its file name.
+fn with_suffix(path: &Path, suffix: &str) -> PathBuf {
+    let mut name = path.file_name().unwrap_or_default().to_os_string();
+    name.push(suffix);
+    path.with_file_name(name)
+}
+
+/// The `version` key of a lockfile document. v1 lockfiles may predate the key.
+fn schema_version(document: &Table) -> Result<u32> {
+    match document.get("version") {
+        None => Ok(1),
+        Some(Value::Integer(version)) if *version >= 1 => u32::try_from(*version)
+            .with_context(|| format!("Unsupported lockfile schema version {version}")),
+        Some(other) => bail!("Invalid lockfile schema version {other}"),
+    }
+}
+
+/// v1 → v2: group the flat `tool_symlinks` list into per-tool `tool_receipts`.
+///
+/// v1 entries carry `source` and `target`, plus the owning `tool` and its `version` when known.
+/// Without a tool name the link name stands in for it.
+fn migrate_v1(document: &mut Table) -> Result<()> {
+    let installed_at = document
+        .get("metadata")
+        .and_then(|metadata| metadata.get("installed_at"))
+        .and_then(Value::as_str)
+        .map(str::to_string)
+        .unwrap_or_else(|| chrono::Utc::now().to_rfc3339());
+
+    let links = match document.remove("tool_symlinks") {
+        Some(Value::Array(links)) => links,
+        Some(other) => bail!(
+            "`tool_symlinks` must be an array, found {}",
+            other.type_str()
+        ),
+        None => Vec::new(),
+    };
+
+    let mut receipts: BTreeMap<String, Table> = BTreeMap::new();
+    for link in links {
+        let field = |key: &str| link.get(key).and_then(Value::as_str).map(str::to_string);
+        let (Some(source), Some(target)) = (field("source"), field("target")) else {
+            bail!("`tool_symlinks` entries need a `source` and a `target`");
+        };
+        let link_name = Path::new(&target)
+            .file_name()
+            .map(|name| name.to_string_lossy().into_owned())
+            .unwrap_or_else(|| target.clone());
+        let name = field("tool").unwrap_or_else(|| link_name.clone());
+        let version = field("version").unwrap_or_else(|| "unknown".to_string());
+
+        let receipt = receipts.entry(name.clone()).or_insert_with(|| {
+            Table::from_iter([
+                ("name".to_string(), Value::from(name)),
+                ("manifest_version".to_string(), Value::from(version.clone())),
+                ("resolved_version".to_string(), Value::from(version)),
+                ("installer_kind".to_string(), Value::from("unknown")),
+                (
+                    "installed_at".to_string(),
+                    Value::from(installed_at.clone()),
+                ),
+                ("binaries".to_string(), Value::Array(Vec::new())),
+            ])
+        });
+        if let Some(Value::Array(binaries)) = receipt.get_mut("binaries") {
+            binaries.push(Value::Table(Table::from_iter([
+                ("link".to_string(), Value::from(link_name)),
+                ("source".to_string(), Value::from(source)),
+                ("target".to_string(), Value::from(target)),
+            ])));
+        }
+    }
+
+    document.insert(
+        "tool_receipts".to_string(),
+        Value::Array(receipts.into_values().map(Value::Table).collect()),
+    );
+    Ok(())
+}
+
+/// v2 → v3: add `metadata.generated_at`, `metadata.dws_version` and each receipt's `status`.
+///
+/// Receipts without a recorded asset never had a checksum verified, so they start unverified.
+fn migrate_v2(document: &mut Table) -> Result<()> {
+    let metadata = document
+        .entry("metadata")
+        .or_insert_with(|| Value::Table(Table::new()));
+    let Value::Table(metadata) = metadata else {
+        bail!("`metadata` must be a table");
+    };
+    let installed_at = metadata
+        .get("installed_at")
+        .cloned()
+        .unwrap_or_else(|| Value::from(chrono::Utc::now().to_rfc3339()));
+    metadata
+        .entry("installed_at")
+        .or_insert_with(|| installed_at.clone());
+    metadata.entry("generated_at").or_insert(installed_at);
+    metadata
+        .entry("dws_version")
+        .or_insert_with(|| Value::from("unknown"));
+
+    if let Some(Value::Array(receipts)) = document.get_mut("tool_receipts") {
+        for receipt in receipts.iter_mut().filter_map(Value::as_table_mut) {
+            let status = if receipt.contains_key("asset") {
+                "ok"
+            } else {
+                "unverified"
+            };
+            receipt
+                .entry("status")
+                .or_insert_with(|| Value::from(status));
+        }
+    }
+    Ok(())
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -220,7 +563,7 @@ mod tests {
     #[test]
     fn test_lockfile_new() {
         let lockfile = Lockfile::new();
-        assert_eq!(lockfile.version, 2);
+        assert_eq!(lockfile.version, LOCKFILE_VERSION);
         assert!(!lockfile.metadata.installed_at.is_empty());
         assert!(lockfile.config_symlinks.is_empty());
         assert!(lockfile.tool_receipts.is_empty());
@@ -246,6 +589,8 @@ mod tests {
                 link: "rg".to_string(),
                 source: PathBuf::from("/cache/rg"),
                 target: PathBuf::from("/bin/rg"),
+                wrapper: None,
+                sha256: None,
             }],
             Vec::new(),
             None,
@@ -276,6 +621,8 @@ mod tests {
                 link: "tool".to_string(),
                 source: PathBuf::from("/c"),
                 target: PathBuf::from("/bin/tool"),
+                wrapper: None,
+                sha256: None,
             }],
             Vec::new(),
             None,
@@ -307,6 +654,8 @@ mod tests {
                 link: "rg".to_string(),
                 source: PathBuf::from("/cache/rg"),
                 target: PathBuf::from("/bin/rg"),
+                wrapper: None,
+                sha256: None,
             }],
             Vec::new(),
             None,
@@ -321,6 +670,8 @@ mod tests {
                 link: "fd".to_string(),
                 source: PathBuf::from("/cache/fd"),
                 target: PathBuf::from("/bin/fd"),
+                wrapper: None,
+                sha256: None,
             }],
             Vec::new(),
             None,
@@ -361,6 +712,8 @@ mod tests {
                 link: "rg".to_string(),
                 source: PathBuf::from("/cache/rg"),
                 target: PathBuf::from("/bin/rg"),
+                wrapper: None,
+                sha256: None,
             }],
             Vec::new(),
             None,
@@ -375,6 +728,8 @@ mod tests {
                 link: "fd".to_string(),
                 source: PathBuf::from("/cache/fd"),
                 target: PathBuf::from("/bin/fd"),
+                wrapper: None,
+                sha256: None,
             }],
             Vec::new(),
             None,
@@ -401,6 +756,8 @@ mod tests {
                 link: "exa".to_string(),
                 source: PathBuf::from("/cache/exa"),
                 target: PathBuf::from("/bin/exa"),
+                wrapper: None,
+                sha256: None,
             }],
             Vec::new(),
             Some(AssetRecord {
@@ -426,4 +783,109 @@ mod tests {
         assert_eq!(asset.name, "exa.tar.gz");
         assert_eq!(asset.pattern_index, Some(0));
     }
+
+    #[test]
+    fn test_load_migrates_v1_tool_symlinks() {
+        let temp = TempDir::new().unwrap();
+        let path = temp.path().join("dws.lock");
+        let v1 = r#"
+version = 1
+
+[metadata]
+installed_at = "2024-05-01T10:00:00Z"
+
+[[config_symlinks]]
+source = "/profile/config/zsh"
+target = "/home/.config/zsh"
+
+[[tool_symlinks]]
+tool = "ripgrep"
+version = "14.0.0"
+source = "/cache/tools/ripgrep/14.0.0/rg"
+target = "/state/bin/rg"
+
+[[tool_symlinks]]
+source = "/cache/tools/fd/fd"
+target = "/state/bin/fd"
+"#;
+        fs::write(&path, v1).unwrap();
+
+        let lockfile = Lockfile::load(&path).unwrap();
+        assert_eq!(lockfile.version, LOCKFILE_VERSION);
+        assert_eq!(lockfile.config_symlinks.len(), 1);
+        assert_eq!(lockfile.metadata.installed_at, "2024-05-01T10:00:00Z");
+        let names: Vec<&str> = lockfile.tool_receipts().map(|r| r.name.as_str()).collect();
+        assert_eq!(names, ["fd", "ripgrep"]);
+        let ripgrep = &lockfile.tool_receipts[1];
+        assert_eq!(ripgrep.resolved_version, "14.0.0");
+        assert_eq!(ripgrep.binaries[0].link, "rg");
+        assert_eq!(ripgrep.status, ReceiptStatus::Unverified);
+
+        // Loading migrates in memory only; the file is upgraded on the next save.
+        assert_eq!(fs::read_to_string(&path).unwrap(), v1);
+        assert!(!temp.path().join("dws.lock.v1.bak").exists());
+
+        lockfile.save(&path).unwrap();
+        assert_eq!(
+            fs::read_to_string(temp.path().join("dws.lock.v1.bak")).unwrap(),
+            v1
+        );
+        let migrated = fs::read_to_string(&path).unwrap();
+        assert!(migrated.contains("version = 3"));
+        assert!(!migrated.contains("tool_symlinks"));
+    }
+
+    #[test]
+    fn test_load_migrates_v2_receipts() {
+        let temp = TempDir::new().unwrap();
+        let path = temp.path().join("dws.lock");
+        fs::write(
+            &path,
+            r#"
+version = 2
+
+[metadata]
+installed_at = "2025-01-01T00:00:00Z"
+
+[[tool_receipts]]
+name = "rg"
+manifest_version = "latest"
+resolved_version = "14.0.0"
+installer_kind = "github"
+installed_at = "2025-01-01T00:00:00Z"
+
+[tool_receipts.asset]
+name = "rg.tar.gz"
+url = "https://example.com/rg.tar.gz"
+checksum = "sha256:00"
+archive_path = "/cache/tools/rg/14.0.0/rg.tar.gz"
+extract_dir = "/cache/tools/rg/14.0.0/contents"
+"#,
+        )
+        .unwrap();
+
+        let lockfile = Lockfile::load(&path).unwrap();
+        assert_eq!(lockfile.tool_receipts[0].status, ReceiptStatus::Ok);
+        assert_eq!(lockfile.metadata.dws_version, env!("CARGO_PKG_VERSION"));
+        lockfile.save(&path).unwrap();
+        assert!(temp.path().join("dws.lock.v2.bak").exists());
+
+        // Already current: saving again does not back up.
+        fs::remove_file(temp.path().join("dws.lock.v2.bak")).unwrap();
+        Lockfile::load(&path).unwrap().save(&path).unwrap();
+        assert!(!temp.path().join("dws.lock.v2.bak").exists());
+    }
+
+    #[test]
+    fn test_load_refuses_newer_schema() {
+        let temp = TempDir::new().unwrap();
+        let path = temp.path().join("dws.lock");
+        let newer = "version = 4\n\n[metadata]\ninstalled_at = \"2030-01-01T00:00:00Z\"\n";
+        fs::write(&path, newer).unwrap();
+
+        let err = Lockfile::load(&path).unwrap_err().to_string();
+        assert!(err.contains("schema v4"), "{err}");
+        assert!(err.contains("upgrade"), "{err}");
+        assert_eq!(fs::read_to_string(&path).unwrap(), newer);
+    }
 }
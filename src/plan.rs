@@ -0,0 +1,168 @@
+use crate::ui;
+use serde::Serialize;
+use std::fmt::Display;
+
+/// Kinds of change a mutating command can make, in the order plans are printed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum ActionKind {
+    /// Clone a profile repository
+    Clone,
+    /// Create a profile from the built-in template
+    CreateProfile,
+    /// Discard local changes in the profile repository
+    ResetRepository,
+    /// Make a profile the active one
+    Activate,
+    /// Remove a config link (or rendered file) dws installed earlier
+    Unlink,
+    /// Create a config symlink
+    Link,
+    /// Write a rendered template or decrypted secret
+    Render,
+    /// Move an existing target into the backups directory
+    Backup,
+    /// Leave an existing target alone and don't install the entry
+    Skip,
+    /// Move an existing target into the profile
+    Adopt,
+    /// Delete an existing target
+    Overwrite,
+    /// Download and link a tool
+    InstallTool,
+    /// Replace an installed tool version
+    UpgradeTool,
+    /// Fetch a tool archive missing from the cache and unpack it
+    Download,
+    /// Unpack a tool's cached archive again
+    Extract,
+    /// Recreate a link that is missing, points elsewhere or was replaced by a file
+    Relink,
+    /// Unlink a tool the profile no longer defines
+    RemoveTool,
+    /// Delete cached downloads no receipt uses
+    PruneCache,
+    /// Append shell integration to an rc file
+    ModifyRc,
+}
+
+impl ActionKind {
+    fn label(self) -> &'static str {
+        match self {
+            ActionKind::Clone => "Clone",
+            ActionKind::CreateProfile => "Create",
+            ActionKind::ResetRepository => "Reset",
+            ActionKind::Activate => "Activate",
+            ActionKind::Unlink => "Unlink",
+            ActionKind::Link => "Link",
+            ActionKind::Render => "Render",
+            ActionKind::Backup => "Back up",
+            ActionKind::Skip => "Skip",
+            ActionKind::Adopt => "Adopt",
+            ActionKind::Overwrite => "Overwrite",
+            ActionKind::InstallTool => "Install",
+            ActionKind::UpgradeTool => "Upgrade",
+            ActionKind::Download => "Download",
+            ActionKind::Extract => "Extract",
+            ActionKind::Relink => "Relink",
+            ActionKind::RemoveTool => "Remove",
+            ActionKind::PruneCache => "Prune",
+            ActionKind::ModifyRc => "Modify",
+        }
+    }
+}
+
+/// A single planned change.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Action {
+    pub action: ActionKind,
+    /// Path, tool or profile the action applies to
+    pub target: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub detail: Option<String>,
+}
+
+/// Everything a command would change, computed without touching disk.
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct Plan {
+    pub command: String,
+    pub profile: String,
+    pub actions: Vec<Action>,
+}
+
+impl Plan {
+    pub fn new(command: impl Into<String>, profile: impl Into<String>) -> Self {
+        Self {
+            command: command.into(),
+            profile: profile.into(),
+            actions: Vec::new(),
+        }
+    }
+
+    pub fn push(&mut self, action: ActionKind, target: impl Display, detail: Option<String>) {
+        self.actions.push(Action {
+            action,
+            target: target.to_string(),
+            detail,
+        });
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.actions.is_empty()
+    }
+
+    /// Actions of `kind`, in the order they were planned.
+    pub fn actions_of(&self, kind: ActionKind) -> impl Iterator<Item = &Action> {
+        self.actions
+            .iter()
+            .filter(move |action| action.action == kind)
+    }
+
+    /// Print the plan grouped by action.
+    pub fn print(&self) {
+        if self.is_empty() {
+            ui::info(format!(
+                "Nothing to do: profile '{}' is up to date",
+                self.profile
+            ));
+            return;
+        }
+
+        let mut kinds: Vec<ActionKind> = self.actions.iter().map(|action| action.action).collect();
+        kinds.sort();
+        kinds.dedup();
+        for kind in kinds {
+            let lines: Vec<String> = self
+                .actions_of(kind)
+                .map(|action| match &action.detail {
+                    Some(detail) => format!("{} ({detail})", action.target),
+                    None => action.target.clone(),
+                })
+                .collect();
+            ui::status(kind.label(), lines.join("\n"));
+        }
+        ui::info(format!(
+            "Dry run: {} change(s) planned for `dws {}`; nothing was modified",
+            self.actions.len(),
+            self.command
+        ));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn serializes_actions_in_kebab_case() {
+        let mut plan = Plan::new("install", "default");
+        plan.push(ActionKind::InstallTool, "ripgrep", Some("14.1.0".into()));
+        plan.push(ActionKind::Link, "/home/me/.config/git", None);
+
+        let json = serde_json::to_value(&plan).unwrap();
+        assert_eq!(json["actions"][0]["action"], "install-tool");
+        assert_eq!(json["actions"][1]["action"], "link");
+        assert!(json["actions"][1].get("detail").is_none());
+        assert_eq!(plan.actions_of(ActionKind::Link).count(), 1);
+    }
+}
@@ -0,0 +1,321 @@
+use crate::ui;
+use anyhow::{bail, Context, Result};
+use serde::{Deserialize, Serialize};
+use std::fs::{self, File, OpenOptions, TryLockError};
+use std::io::{Read, Seek, Write};
+#[cfg(unix)]
+use std::os::unix::fs::MetadataExt;
+use std::path::Path;
+#[cfg(not(target_os = "linux"))]
+use std::process::Command;
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// How long to wait for another dws process when `[lock] timeout` is not set.
+pub const DEFAULT_LOCK_TIMEOUT: Duration = Duration::from_secs(60);
+
+/// Delay between attempts while another process holds the lock.
+const POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+/// `[lock]` table in `config.toml`.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct LockSettings {
+    /// Seconds to wait for another dws process before giving up (0 fails immediately).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub timeout: Option<u64>,
+}
+
+impl LockSettings {
+    pub fn is_unset(&self) -> bool {
+        self.timeout.is_none()
+    }
+}
+
+/// Access a command needs to the workspace state.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LockMode {
+    /// Read-only commands; any number may run together.
+    Shared,
+    /// Commands that write the lockfile, links or cache; they run alone.
+    Exclusive,
+}
+
+/// The process that holds the lock exclusively, as recorded in the lock file.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Holder {
+    pid: u32,
+    /// Start time of `pid`, so a recycled PID is not mistaken for the holder
+    started: String,
+}
+
+impl Holder {
+    fn current() -> Option<Self> {
+        let pid = std::process::id();
+        process_start_time(pid).map(|started| Self { pid, started })
+    }
+
+    fn parse(contents: &str) -> Option<Self> {
+        let mut lines = contents.lines();
+        let pid = lines.next()?.trim().parse().ok()?;
+        let started = lines.next()?.trim().to_string();
+        Some(Self { pid, started })
+    }
+
+    /// Whether the recorded process is gone (or its PID now belongs to another process).
+    fn is_gone(&self) -> bool {
+        process_start_time(self.pid).as_deref() != Some(self.started.as_str())
+    }
+}
+
+/// Advisory lock serialising dws processes that share a state directory.
+///
+/// The lock is an `flock` on a file in `$XDG_STATE_HOME/dws`, released when the guard is dropped
+/// or the process exits. Exclusive holders write their PID and start time into the file so that
+/// waiting processes can name them. The file is never removed: a holder that crashed has already
+/// lost its lock, so the next process to acquire it simply overwrites the stale record.
+#[derive(Debug)]
+pub struct ProcessLock {
+    file: File,
+    mode: LockMode,
+}
+
+impl ProcessLock {
+    /// Take the lock at `path`, waiting up to `timeout` for other dws processes to finish.
+    pub fn acquire(path: &Path, mode: LockMode, timeout: Duration) -> Result<Self> {
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)
+                .with_context(|| format!("Failed to create state directory {:?}", parent))?;
+        }
+
+        let start = Instant::now();
+        let mut announced = false;
+        loop {
+            let file = open(path)?;
+            let attempt = match mode {
+                LockMode::Shared => file.try_lock_shared(),
+                LockMode::Exclusive => file.try_lock(),
+            };
+            match attempt {
+                // A file replaced since we opened it is not the lock other processes contend on.
+                Ok(()) if is_current(&file, path) => {
+                    let mut lock = Self { file, mode };
+                    lock.record_holder()?;
+                    return Ok(lock);
+                }
+                Ok(()) => continue,
+                Err(TryLockError::WouldBlock) => {}
+                Err(TryLockError::Error(err)) => {
+                    return Err(err).with_context(|| format!("Failed to lock {:?}", path));
+                }
+            }
+
+            // The holder is only looked up to name it, which can mean spawning `ps`, so that
+            // happens when announcing the wait and at the timeout rather than on every poll.
+            if start.elapsed() >= timeout {
+                bail!(
+                    "{} is running; try again once it finishes (waited {}, see `--lock-timeout` or [lock] timeout)",
+                    running(path),
+                    ui::format_duration(start.elapsed())
+                );
+            }
+            if !announced {
+                ui::warn(format!(
+                    "{} is running; waiting up to {} for it to finish",
+                    running(path),
+                    ui::format_duration(timeout)
+                ));
+                announced = true;
+            }
+            thread::sleep(POLL_INTERVAL.min(timeout.saturating_sub(start.elapsed())));
+        }
+    }
+
+    /// Replace whatever holder the file names: exclusive holders record themselves, and shared
+    /// holders clear a holder left behind by a process that exited without unlocking cleanly.
+    fn record_holder(&mut self) -> Result<()> {
+        self.file.set_len(0)?;
+        let Some(holder) = Holder::current().filter(|_| self.mode == LockMode::Exclusive) else {
+            return Ok(());
+        };
+        self.file.rewind()?;
+        write!(self.file, "{}\n{}\n", holder.pid, holder.started)?;
+        self.file.flush()?;
+        Ok(())
+    }
+}
+
+impl Drop for ProcessLock {
+    fn drop(&mut self) {
+        if self.mode == LockMode::Exclusive {
+            let _ = self.file.set_len(0);
+        }
+        let _ = self.file.unlock();
+    }
+}
+
+fn open(path: &Path) -> Result<File> {
+    OpenOptions::new()
+        .read(true)
+        .write(true)
+        .create(true)
+        .truncate(false)
+        .open(path)
+        .with_context(|| format!("Failed to open lock file {:?}", path))
+}
+
+/// Whether `file` is still the file at `path`.
+#[cfg(unix)]
+fn is_current(file: &File, path: &Path) -> bool {
+    match (file.metadata(), fs::metadata(path)) {
+        (Ok(open), Ok(on_disk)) => open.dev() == on_disk.dev() && open.ino() == on_disk.ino(),
+        _ => false,
+    }
+}
+
+/// Without inode numbers a replaced lock file cannot be told apart; dws never replaces it.
+#[cfg(not(unix))]
+fn is_current(_file: &File, _path: &Path) -> bool {
+    true
+}
+
+fn read_holder(path: &Path) -> Option<Holder> {
+    let mut contents = String::new();
+    File::open(path).ok()?.read_to_string(&mut contents).ok()?;
+    Holder::parse(&contents)
+}
+
+/// Who holds the contended lock at `path`, for messages.
+///
+/// The kernel releases the lock of a process that dies, so a contended lock is always live. A
+/// recorded holder that is gone was an earlier exclusive holder and now only someone sharing the
+/// lock is left; it is not named.
+fn running(path: &Path) -> String {
+    describe(
+        read_holder(path)
+            .filter(|holder| !holder.is_gone())
+            .as_ref(),
+    )
+}
+
+fn describe(holder: Option<&Holder>) -> String {
+    match holder {
+        Some(holder) => format!("another dws process (pid {})", holder.pid),
+        None => "another dws process".to_string(),
+    }
+}
+
+/// When `pid` started, in a platform-specific format, or `None` if it is not running.
+#[cfg(target_os = "linux")]
+fn process_start_time(pid: u32) -> Option<String> {
+    // Field 22 of /proc/<pid>/stat, counted after the parenthesised command name.
+    let stat = fs::read_to_string(format!("/proc/{pid}/stat")).ok()?;
+    let fields = &stat[stat.rfind(')')? + 1..];
+    fields.split_whitespace().nth(19).map(str::to_string)
+}
+
+/// When `pid` started, in a platform-specific format, or `None` if it is not running.
+#[cfg(not(target_os = "linux"))]
+fn process_start_time(pid: u32) -> Option<String> {
+    let output = Command::new("ps")
+        .args(["-o", "lstart=", "-p", &pid.to_string()])
+        .output()
+        .ok()?;
+    let started = String::from_utf8_lossy(&output.stdout).trim().to_string();
+    (output.status.success() && !started.is_empty()).then_some(started)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+    use std::process::Command;
+    use tempfile::TempDir;
+
+    fn lock_path(temp: &TempDir) -> PathBuf {
+        temp.path().join("dws/process.lock")
+    }
+
+    #[test]
+    fn exclusive_lock_names_the_holder() {
+        let temp = TempDir::new().unwrap();
+        let path = lock_path(&temp);
+        let _held = ProcessLock::acquire(&path, LockMode::Exclusive, Duration::ZERO).unwrap();
+
+        let err = ProcessLock::acquire(&path, LockMode::Shared, Duration::ZERO).unwrap_err();
+        assert!(err.to_string().contains(&format!(
+            "another dws process (pid {}) is running",
+            std::process::id()
+        )));
+    }
+
+    #[test]
+    fn shared_locks_coexist_but_exclude_writers() {
+        let temp = TempDir::new().unwrap();
+        let path = lock_path(&temp);
+        let first = ProcessLock::acquire(&path, LockMode::Shared, Duration::ZERO).unwrap();
+        let second = ProcessLock::acquire(&path, LockMode::Shared, Duration::ZERO).unwrap();
+
+        let err = ProcessLock::acquire(&path, LockMode::Exclusive, Duration::ZERO).unwrap_err();
+        assert!(err.to_string().contains("another dws process is running"));
+
+        drop((first, second));
+        ProcessLock::acquire(&path, LockMode::Exclusive, Duration::ZERO).unwrap();
+    }
+
+    #[test]
+    fn waits_for_the_holder_to_finish() {
+        let temp = TempDir::new().unwrap();
+        let path = lock_path(&temp);
+        let held = ProcessLock::acquire(&path, LockMode::Exclusive, Duration::ZERO).unwrap();
+
+        let release = thread::spawn(move || {
+            thread::sleep(Duration::from_millis(200));
+            drop(held);
+        });
+        ProcessLock::acquire(&path, LockMode::Exclusive, Duration::from_secs(10)).unwrap();
+        release.join().unwrap();
+    }
+
+    /// A PID that belonged to a process which has since exited.
+    fn dead_holder() -> String {
+        let mut child = Command::new("true").spawn().unwrap();
+        let pid = child.id();
+        child.wait().unwrap();
+        format!("{pid}\nstarted-long-ago\n")
+    }
+
+    #[test]
+    fn overwrites_holder_left_by_a_dead_process() {
+        let temp = TempDir::new().unwrap();
+        let path = lock_path(&temp);
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+
+        fs::write(&path, dead_holder()).unwrap();
+        let shared = ProcessLock::acquire(&path, LockMode::Shared, Duration::ZERO).unwrap();
+        assert_eq!(read_holder(&path), None);
+        drop(shared);
+
+        fs::write(&path, dead_holder()).unwrap();
+        let lock = ProcessLock::acquire(&path, LockMode::Exclusive, Duration::ZERO).unwrap();
+        assert_eq!(read_holder(&path), Holder::current());
+        drop(lock);
+        assert_eq!(read_holder(&path), None);
+    }
+
+    #[test]
+    fn contended_lock_is_never_removed() {
+        let temp = TempDir::new().unwrap();
+        let path = lock_path(&temp);
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+
+        // A reader still holds the lock after the exclusive holder it followed crashed.
+        let reader = open(&path).unwrap();
+        reader.try_lock_shared().unwrap();
+        fs::write(&path, dead_holder()).unwrap();
+
+        let err = ProcessLock::acquire(&path, LockMode::Exclusive, Duration::ZERO).unwrap_err();
+        assert!(err.to_string().contains("another dws process is running"));
+        assert!(is_current(&reader, &path));
+    }
+}
@@ -26,6 +26,21 @@ impl Profile {
         self.root.join("config")
     }
 
+    /// Files linked into `$HOME` (for tools that ignore XDG)
+    pub fn home_dir(&self) -> PathBuf {
+        self.root.join("home")
+    }
+
+    /// Files linked into `$XDG_DATA_HOME`
+    pub fn data_dir(&self) -> PathBuf {
+        self.root.join("data")
+    }
+
+    /// Files linked into `$XDG_STATE_HOME`
+    pub fn state_dir(&self) -> PathBuf {
+        self.root.join("state")
+    }
+
     pub fn config_file(&self) -> PathBuf {
         self.root.join("dws.toml")
     }
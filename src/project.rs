@@ -0,0 +1,324 @@
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::environment::{fish_quote, posix_escape, Shell};
+use crate::toolset::ToolSet;
+
+/// Project manifest file name, looked up from the working directory towards `/`.
+pub const PROJECT_FILE: &str = ".dws.toml";
+
+/// Variable holding the root of the active project environment.
+const PROJECT_VAR: &str = "DWS_PROJECT";
+/// Variable holding the bin directory the hook prepended to PATH, so it can be removed again.
+const PROJECT_BIN_VAR: &str = "DWS_PROJECT_BIN";
+
+/// A directory containing a `.dws.toml` manifest.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Project {
+    pub root: PathBuf,
+}
+
+impl Project {
+    /// Find the nearest project enclosing `start`.
+    pub fn discover(start: &Path) -> Option<Self> {
+        start
+            .ancestors()
+            .find(|dir| dir.join(PROJECT_FILE).is_file())
+            .map(|root| Self {
+                root: root.to_path_buf(),
+            })
+    }
+
+    /// Resolve the project enclosing `path`, failing when there is no manifest.
+    pub fn locate(path: &Path) -> Result<Self> {
+        let path = path
+            .canonicalize()
+            .with_context(|| format!("Failed to resolve {:?}", path))?;
+        Self::discover(&path)
+            .with_context(|| format!("No {PROJECT_FILE} found in {:?} or its parents", path))
+    }
+
+    pub fn manifest(&self) -> PathBuf {
+        self.root.join(PROJECT_FILE)
+    }
+
+    /// Stable identifier used for the project's state directory.
+    pub fn key(&self) -> String {
+        let digest = Sha256::digest(self.root.to_string_lossy().as_bytes());
+        hex::encode(&digest[..8])
+    }
+
+    /// SHA256 of the manifest contents; trust is revoked whenever it changes.
+    pub fn manifest_digest(&self) -> Result<String> {
+        let contents = fs::read(self.manifest())
+            .with_context(|| format!("Failed to read {:?}", self.manifest()))?;
+        Ok(hex::encode(Sha256::digest(contents)))
+    }
+
+    /// Tools declared by the manifest after platform and host filters.
+    pub fn tools(&self) -> Result<ToolSet> {
+        ToolSet::load_file(&self.manifest())
+    }
+}
+
+/// Trust decision for a project manifest.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Trust {
+    Allowed,
+    /// Allowed before, but the manifest has been edited since.
+    Changed,
+    Unknown,
+}
+
+/// Projects the user approved with `dws allow`, keyed by root and pinned to a manifest digest.
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct TrustStore {
+    #[serde(default)]
+    allowed: BTreeMap<PathBuf, String>,
+}
+
+impl TrustStore {
+    pub fn load(path: &Path) -> Result<Self> {
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+        let contents =
+            fs::read_to_string(path).with_context(|| format!("Failed to read {:?}", path))?;
+        toml::from_str(&contents).with_context(|| format!("Failed to parse {:?}", path))
+    }
+
+    pub fn save(&self, path: &Path) -> Result<()> {
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)
+                .with_context(|| format!("Failed to create directory {:?}", parent))?;
+        }
+        let contents = toml::to_string_pretty(self).context("Failed to serialize trust store")?;
+        fs::write(path, contents).with_context(|| format!("Failed to write {:?}", path))
+    }
+
+    pub fn allow(&mut self, project: &Project) -> Result<()> {
+        self.allowed
+            .insert(project.root.clone(), project.manifest_digest()?);
+        Ok(())
+    }
+
+    /// Forget a project; returns whether it was allowed before.
+    pub fn revoke(&mut self, project: &Project) -> bool {
+        self.allowed.remove(&project.root).is_some()
+    }
+
+    pub fn trust(&self, project: &Project) -> Result<Trust> {
+        Ok(match self.allowed.get(&project.root) {
+            Some(digest) if *digest == project.manifest_digest()? => Trust::Allowed,
+            Some(_) => Trust::Changed,
+            None => Trust::Unknown,
+        })
+    }
+}
+
+/// Directory-change hook emitted by `dws env` for shells that have one.
+pub fn hook_script(shell: Shell) -> Option<&'static str> {
+    match shell {
+        Shell::Zsh => Some(
+            "_dws_hook() { eval \"$(dws hook-env --shell zsh)\"; }\n\
+             typeset -ag chpwd_functions\n\
+             if (( ! ${chpwd_functions[(I)_dws_hook]} )); then chpwd_functions+=(_dws_hook); fi\n\
+             _dws_hook",
+        ),
+        // PROMPT_COMMAND also runs before the first prompt, so no initial call is needed.
+        Shell::Bash => Some(
+            "_dws_hook() {\n\
+             \x20 local status=$?\n\
+             \x20 if [[ \"$PWD\" != \"${_dws_hook_pwd-}\" ]]; then\n\
+             \x20   _dws_hook_pwd=$PWD\n\
+             \x20   eval \"$(dws hook-env --shell bash)\"\n\
+             \x20 fi\n\
+             \x20 return $status\n\
+             }\n\
+             if [[ \";${PROMPT_COMMAND:-};\" != *\";_dws_hook;\"* ]]; then\n\
+             \x20 PROMPT_COMMAND=\"_dws_hook${PROMPT_COMMAND:+;$PROMPT_COMMAND}\"\n\
+             fi",
+        ),
+        Shell::Fish => Some(
+            "function __dws_hook --on-variable PWD\n\
+             \x20   dws hook-env --shell fish | source\n\
+             end\n\
+             __dws_hook",
+        ),
+        _ => None,
+    }
+}
+
+/// Render the PATH change for moving from the previously active project bin dir to `next`.
+///
+/// `next` is the project root and the bin directory to prepend. Returns an empty string when
+/// nothing changes so the hook stays silent on every directory change.
+pub fn render_hook_env(
+    shell: Shell,
+    current_path: &str,
+    previous_bin: Option<&str>,
+    next: Option<(&Path, &Path)>,
+) -> String {
+    let next_bin = next.map(|(_, bin)| bin.to_string_lossy().into_owned());
+    if next_bin.is_some()
+        && next_bin.as_deref() == previous_bin
+        && current_path.split(':').next() == previous_bin
+    {
+        return String::new();
+    }
+    if next.is_none() && previous_bin.is_none() {
+        return String::new();
+    }
+
+    let mut entries: Vec<&str> = current_path
+        .split(':')
+        .filter(|entry| !entry.is_empty() && Some(*entry) != previous_bin)
+        .collect();
+    if let Some(bin) = next_bin.as_deref() {
+        entries.insert(0, bin);
+    }
+
+    let mut lines = Vec::new();
+    match shell {
+        Shell::Fish => {
+            let quoted: Vec<String> = entries.iter().map(|entry| fish_quote(entry)).collect();
+            lines.push(format!("set -gx PATH {}", quoted.join(" ")));
+            match next {
+                Some((root, bin)) => {
+                    lines.push(format!(
+                        "set -gx {PROJECT_VAR} {}",
+                        fish_quote(&root.to_string_lossy())
+                    ));
+                    lines.push(format!(
+                        "set -gx {PROJECT_BIN_VAR} {}",
+                        fish_quote(&bin.to_string_lossy())
+                    ));
+                }
+                None => lines.push(format!("set -e {PROJECT_VAR}; set -e {PROJECT_BIN_VAR}")),
+            }
+        }
+        _ => {
+            lines.push(format!(
+                "export PATH=\"{}\"",
+                posix_escape(&entries.join(":"))
+            ));
+            match next {
+                Some((root, bin)) => {
+                    lines.push(format!(
+                        "export {PROJECT_VAR}=\"{}\"",
+                        posix_escape(&root.to_string_lossy())
+                    ));
+                    lines.push(format!(
+                        "export {PROJECT_BIN_VAR}=\"{}\"",
+                        posix_escape(&bin.to_string_lossy())
+                    ));
+                }
+                None => lines.push(format!("unset {PROJECT_VAR} {PROJECT_BIN_VAR}")),
+            }
+        }
+    }
+    lines.join("\n")
+}
+
+/// Bin directory the hook prepended for the previously active project, if any.
+pub fn previous_bin() -> Option<String> {
+    std::env::var(PROJECT_BIN_VAR)
+        .ok()
+        .filter(|value| !value.is_empty())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn discover_walks_up_to_manifest() {
+        let temp = TempDir::new().unwrap();
+        let nested = temp.path().join("src/deep");
+        fs::create_dir_all(&nested).unwrap();
+        assert_eq!(Project::discover(&nested), None);
+
+        fs::write(temp.path().join(PROJECT_FILE), "").unwrap();
+        let project = Project::discover(&nested).unwrap();
+        assert_eq!(project.root, temp.path());
+        assert_eq!(project.key().len(), 16);
+    }
+
+    #[test]
+    fn trust_is_pinned_to_manifest_digest() {
+        let temp = TempDir::new().unwrap();
+        fs::write(temp.path().join(PROJECT_FILE), "[tools]\n").unwrap();
+        let project = Project::discover(temp.path()).unwrap();
+        let store_path = temp.path().join("allowed.toml");
+
+        let mut store = TrustStore::load(&store_path).unwrap();
+        assert_eq!(store.trust(&project).unwrap(), Trust::Unknown);
+        store.allow(&project).unwrap();
+        store.save(&store_path).unwrap();
+
+        let mut store = TrustStore::load(&store_path).unwrap();
+        assert_eq!(store.trust(&project).unwrap(), Trust::Allowed);
+
+        fs::write(project.manifest(), "[tools.jq]\n").unwrap();
+        assert_eq!(store.trust(&project).unwrap(), Trust::Changed);
+
+        assert!(store.revoke(&project));
+        assert_eq!(store.trust(&project).unwrap(), Trust::Unknown);
+    }
+
+    #[test]
+    fn hook_env_prepends_and_restores_path() {
+        let root = Path::new("/work/legacy");
+        let bin = Path::new("/state/projects/abc/bin");
+
+        let enter = render_hook_env(Shell::Bash, "/usr/bin:/bin", None, Some((root, bin)));
+        assert!(enter.contains("export PATH=\"/state/projects/abc/bin:/usr/bin:/bin\""));
+        assert!(enter.contains("export DWS_PROJECT=\"/work/legacy\""));
+
+        let stay = render_hook_env(
+            Shell::Bash,
+            "/state/projects/abc/bin:/usr/bin:/bin",
+            Some("/state/projects/abc/bin"),
+            Some((root, bin)),
+        );
+        assert!(stay.is_empty());
+
+        let leave = render_hook_env(
+            Shell::Zsh,
+            "/state/projects/abc/bin:/usr/bin:/bin",
+            Some("/state/projects/abc/bin"),
+            None,
+        );
+        assert!(leave.contains("export PATH=\"/usr/bin:/bin\""));
+        assert!(leave.contains("unset DWS_PROJECT DWS_PROJECT_BIN"));
+
+        assert!(render_hook_env(Shell::Bash, "/usr/bin", None, None).is_empty());
+    }
+
+    #[test]
+    fn hook_env_fish_uses_path_list() {
+        let output = render_hook_env(
+            Shell::Fish,
+            "/old/bin:/usr/bin",
+            Some("/old/bin"),
+            Some((Path::new("/work/app"), Path::new("/new/bin"))),
+        );
+        assert!(output.contains("set -gx PATH '/new/bin' '/usr/bin'"));
+        assert!(output.contains("set -gx DWS_PROJECT_BIN '/new/bin'"));
+    }
+
+    #[test]
+    fn hook_scripts_cover_directory_change_events() {
+        assert!(hook_script(Shell::Zsh).unwrap().contains("chpwd_functions"));
+        assert!(hook_script(Shell::Bash).unwrap().contains("PROMPT_COMMAND"));
+        assert!(hook_script(Shell::Fish)
+            .unwrap()
+            .contains("--on-variable PWD"));
+        assert!(hook_script(Shell::Xonsh).is_none());
+    }
+}
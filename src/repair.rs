@@ -0,0 +1,358 @@
+use crate::installers::BlobStore;
+use crate::integrity::{self, check_symlink, check_wrapper, LinkState};
+use crate::lockfile::{AssetRecord, Lockfile, ToolReceipt};
+use crate::plan::{ActionKind, Plan};
+use crate::toolset::BinWrapper;
+use std::path::{Path, PathBuf};
+
+/// A fix `dws repair` applies for drift that `dws status` reports.
+#[derive(Debug, Clone)]
+pub(crate) enum Repair {
+    /// Drop a receipt and its links
+    RemoveReceipt {
+        tool: String,
+        version: String,
+        reason: &'static str,
+    },
+    /// Unpack a tool's `contents/` again, downloading the archive first when it is not cached
+    Restore {
+        tool: String,
+        version: String,
+        download: bool,
+    },
+    /// Recreate a link that is missing, points elsewhere or was replaced by a file
+    Relink {
+        /// Tool owning the link; `None` for config symlinks
+        tool: Option<String>,
+        source: PathBuf,
+        target: PathBuf,
+        wrapper: Option<BinWrapper>,
+        problem: &'static str,
+    },
+}
+
+impl Repair {
+    fn push_to(&self, plan: &mut Plan) {
+        match self {
+            Repair::RemoveReceipt {
+                tool,
+                version,
+                reason,
+            } => plan.push(
+                ActionKind::RemoveTool,
+                tool,
+                Some(format!("{version}, {reason}")),
+            ),
+            Repair::Restore {
+                tool,
+                version,
+                download,
+            } => {
+                let (kind, detail) = if *download {
+                    (ActionKind::Download, "archive no longer cached")
+                } else {
+                    (ActionKind::Extract, "from cached archive")
+                };
+                plan.push(kind, format!("{tool} {version}"), Some(detail.to_string()));
+            }
+            Repair::Relink {
+                source,
+                target,
+                problem,
+                ..
+            } => plan.push(
+                ActionKind::Relink,
+                target.display(),
+                Some(format!("{problem}, -> {}", source.display())),
+            ),
+        }
+    }
+}
+
+/// Everything [`diagnose`] would fix, as a plan for `dws repair`.
+pub(crate) fn plan(repairs: &[Repair], profile: &str) -> Plan {
+    let mut plan = Plan::new("repair", profile);
+    for repair in repairs {
+        repair.push_to(&mut plan);
+    }
+    plan
+}
+
+/// Compare the lockfile against disk and list the fixes for what has drifted.
+///
+/// Only drift dws can undo from its own records is considered: links that are missing, point
+/// elsewhere or (for tools) were replaced by a file, and tool contents that can be unpacked again.
+/// Config targets replaced by a regular file belong to the user and are left alone. Receipts of
+/// tools `is_defined` rejects, and receipts whose files are gone with no recorded download, are
+/// dropped.
+pub(crate) fn diagnose(
+    lockfile: &Lockfile,
+    is_defined: impl Fn(&str) -> bool,
+    blobs: &BlobStore,
+) -> Vec<Repair> {
+    let mut repairs = Vec::new();
+
+    for entry in lockfile.config_symlinks() {
+        if entry.rendered.is_some() {
+            continue;
+        }
+        let problem = match check_symlink(&entry.source, &entry.target) {
+            LinkState::MissingTarget => "missing",
+            LinkState::WrongTarget { .. } => "pointed elsewhere",
+            _ => continue,
+        };
+        repairs.push(Repair::Relink {
+            tool: None,
+            source: entry.source.clone(),
+            target: entry.target.clone(),
+            wrapper: None,
+            problem,
+        });
+    }
+
+    for receipt in lockfile.tool_receipts() {
+        if !is_defined(&receipt.name) {
+            repairs.push(remove(receipt, "no longer defined"));
+            continue;
+        }
+
+        if contents_missing(receipt) {
+            let Some(asset) = &receipt.asset else {
+                repairs.push(remove(
+                    receipt,
+                    "files are gone and no download is recorded",
+                ));
+                continue;
+            };
+            repairs.push(Repair::Restore {
+                tool: receipt.name.clone(),
+                version: receipt.resolved_version.clone(),
+                download: !archive_cached(asset, blobs),
+            });
+        }
+
+        let links = receipt
+            .binaries
+            .iter()
+            .map(|bin| (&bin.source, &bin.target, bin.wrapper.as_ref()))
+            .chain(
+                receipt
+                    .extras
+                    .iter()
+                    .map(|extra| (&extra.source, &extra.target, None)),
+            );
+        for (source, target, wrapper) in links {
+            let state = if wrapper.is_some() {
+                check_wrapper(source, target)
+            } else {
+                check_symlink(source, target)
+            };
+            let problem = match state {
+                LinkState::MissingTarget => "missing",
+                LinkState::WrongTarget { .. } => "pointed elsewhere",
+                LinkState::NotSymlink if !target.is_dir() => "replaced by a file",
+                _ => continue,
+            };
+            repairs.push(Repair::Relink {
+                tool: Some(receipt.name.clone()),
+                source: source.clone(),
+                target: target.clone(),
+                wrapper: wrapper.cloned(),
+                problem,
+            });
+        }
+    }
+
+    repairs
+}
+
+fn remove(receipt: &ToolReceipt, reason: &'static str) -> Repair {
+    Repair::RemoveReceipt {
+        tool: receipt.name.clone(),
+        version: receipt.resolved_version.clone(),
+        reason,
+    }
+}
+
+/// Whether the unpacked files a receipt links to are gone.
+fn contents_missing(receipt: &ToolReceipt) -> bool {
+    let missing = |path: &Path| path.symlink_metadata().is_err();
+    receipt
+        .asset
+        .as_ref()
+        .is_some_and(|asset| missing(&asset.extract_dir))
+        || receipt.binaries.iter().any(|bin| missing(&bin.source))
+        || receipt.extras.iter().any(|extra| missing(&extra.source))
+}
+
+/// Whether the archive can be unpacked again without downloading it.
+fn archive_cached(asset: &AssetRecord, blobs: &BlobStore) -> bool {
+    let checksum = asset
+        .checksum
+        .trim_start_matches("sha256:")
+        .to_ascii_lowercase();
+    blobs.contains(&checksum)
+        || (asset.archive_path.is_file()
+            && integrity::sha256_file(&asset.archive_path).ok()
+                == Some(format!("sha256:{checksum}")))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lockfile::{BinaryLink, ExtraLink};
+    use std::fs;
+    use std::os::unix::fs::symlink;
+    use tempfile::TempDir;
+
+    fn record(lockfile: &mut Lockfile, temp: &TempDir, name: &str, archive: Option<&[u8]>) {
+        let contents = temp.path().join(format!("cache/{name}/contents"));
+        fs::create_dir_all(&contents).unwrap();
+        fs::write(contents.join(name), "sh\n").unwrap();
+        fs::write(contents.join("man.1"), "man\n").unwrap();
+        let asset = archive.map(|bytes| {
+            let archive_path = temp.path().join(format!("cache/{name}/{name}.tar.gz"));
+            fs::write(&archive_path, bytes).unwrap();
+            AssetRecord {
+                checksum: integrity::sha256_file(&archive_path).unwrap()[7..].to_string(),
+                archive_path,
+                extract_dir: contents.clone(),
+                ..AssetRecord::default()
+            }
+        });
+        lockfile.record_tool_install(
+            name,
+            "latest",
+            "v1.0.0",
+            "github",
+            vec![BinaryLink {
+                link: name.to_string(),
+                source: contents.join(name),
+                target: temp.path().join("bin").join(name),
+                wrapper: None,
+                sha256: None,
+            }],
+            vec![ExtraLink {
+                kind: "man".to_string(),
+                source: contents.join("man.1"),
+                target: temp.path().join(format!("share/man/man1/{name}.1")),
+                sha256: None,
+            }],
+            asset,
+        );
+    }
+
+    fn link_all(lockfile: &Lockfile) {
+        for receipt in lockfile.tool_receipts() {
+            for (source, target) in receipt
+                .binaries
+                .iter()
+                .map(|bin| (&bin.source, &bin.target))
+                .chain(receipt.extras.iter().map(|e| (&e.source, &e.target)))
+            {
+                fs::create_dir_all(target.parent().unwrap()).unwrap();
+                symlink(source, target).unwrap();
+            }
+        }
+    }
+
+    fn blobs(temp: &TempDir) -> BlobStore {
+        BlobStore::new(temp.path().join("blobs"))
+    }
+
+    #[test]
+    fn intact_install_needs_no_repair() {
+        let temp = TempDir::new().unwrap();
+        let mut lockfile = Lockfile::new();
+        record(&mut lockfile, &temp, "tool", Some(b"archive"));
+        link_all(&lockfile);
+
+        assert!(diagnose(&lockfile, |_| true, &blobs(&temp)).is_empty());
+    }
+
+    #[test]
+    fn relinks_missing_wrong_and_replaced_links() {
+        let temp = TempDir::new().unwrap();
+        let mut lockfile = Lockfile::new();
+        record(&mut lockfile, &temp, "tool", Some(b"archive"));
+        link_all(&lockfile);
+        let config_target = temp.path().join("home/.gitconfig");
+        lockfile.add_config_symlink(temp.path().join("profile/gitconfig"), config_target.clone());
+        let receipt = lockfile.tool_receipts[0].clone();
+        fs::remove_file(&receipt.binaries[0].target).unwrap();
+        symlink(temp.path().join("elsewhere"), &receipt.binaries[0].target).unwrap();
+        fs::remove_file(&receipt.extras[0].target).unwrap();
+        fs::write(&receipt.extras[0].target, "local").unwrap();
+
+        let problems: Vec<_> = diagnose(&lockfile, |_| true, &blobs(&temp))
+            .into_iter()
+            .map(|repair| match repair {
+                Repair::Relink {
+                    target, problem, ..
+                } => (target, problem),
+                other => panic!("unexpected {other:?}"),
+            })
+            .collect();
+        assert_eq!(
+            problems,
+            [
+                (config_target, "missing"),
+                (receipt.binaries[0].target.clone(), "pointed elsewhere"),
+                (receipt.extras[0].target.clone(), "replaced by a file"),
+            ]
+        );
+    }
+
+    #[test]
+    fn restores_contents_from_cache_or_download() {
+        let temp = TempDir::new().unwrap();
+        let mut lockfile = Lockfile::new();
+        record(&mut lockfile, &temp, "cached", Some(b"cached"));
+        record(&mut lockfile, &temp, "gone", Some(b"gone"));
+        link_all(&lockfile);
+        for receipt in lockfile.tool_receipts() {
+            fs::remove_dir_all(&receipt.asset.as_ref().unwrap().extract_dir).unwrap();
+        }
+        fs::remove_file(temp.path().join("cache/gone/gone.tar.gz")).unwrap();
+
+        let restores: Vec<_> = diagnose(&lockfile, |_| true, &blobs(&temp))
+            .into_iter()
+            .map(|repair| match repair {
+                Repair::Restore { tool, download, .. } => (tool, download),
+                other => panic!("unexpected {other:?}"),
+            })
+            .collect();
+        assert_eq!(
+            restores,
+            [("cached".to_string(), false), ("gone".to_string(), true)]
+        );
+    }
+
+    #[test]
+    fn removes_dead_receipts() {
+        let temp = TempDir::new().unwrap();
+        let mut lockfile = Lockfile::new();
+        record(&mut lockfile, &temp, "orphan", Some(b"orphan"));
+        record(&mut lockfile, &temp, "legacy", None);
+        link_all(&lockfile);
+        fs::remove_dir_all(temp.path().join("cache/legacy/contents")).unwrap();
+
+        let repairs = diagnose(&lockfile, |name| name != "orphan", &blobs(&temp));
+        let plan = plan(&repairs, "default");
+        let removed: Vec<_> = plan
+            .actions_of(ActionKind::RemoveTool)
+            .map(|action| (action.target.as_str(), action.detail.as_deref()))
+            .collect();
+        assert_eq!(
+            removed,
+            [
+                ("orphan", Some("v1.0.0, no longer defined")),
+                (
+                    "legacy",
+                    Some("v1.0.0, files are gone and no download is recorded")
+                ),
+            ]
+        );
+        assert_eq!(plan.actions.len(), 2);
+    }
+}
@@ -0,0 +1,170 @@
+use age::secrecy::ExposeSecret;
+use age::x25519::{Identity, Recipient};
+use anyhow::{Context, Result};
+use std::fs;
+use std::io::{Read, Write};
+use std::os::unix::fs::{OpenOptionsExt, PermissionsExt};
+use std::path::Path;
+use std::str::FromStr;
+
+/// File extension marking a dotfile as age-encrypted.
+pub const SECRET_EXTENSION: &str = "age";
+
+/// Permissions for decrypted secrets and the identity file.
+const PRIVATE_MODE: u32 = 0o600;
+
+/// The x25519 identities from an age identity file (`age-keygen` format).
+pub struct Keyring {
+    identities: Vec<Identity>,
+}
+
+impl Keyring {
+    pub fn load(path: &Path) -> Result<Self> {
+        let contents = fs::read_to_string(path).with_context(|| {
+            format!(
+                "Failed to read age identity {:?} (copy your identity there or run `dws secret add`)",
+                path
+            )
+        })?;
+        let identities = contents
+            .lines()
+            .map(str::trim)
+            .filter(|line| !line.is_empty() && !line.starts_with('#'))
+            .map(|line| {
+                Identity::from_str(line)
+                    .map_err(|err| anyhow::anyhow!("Invalid age identity in {:?}: {err}", path))
+            })
+            .collect::<Result<Vec<_>>>()?;
+        if identities.is_empty() {
+            anyhow::bail!("No age identities found in {:?}", path);
+        }
+        Ok(Self { identities })
+    }
+
+    /// Load the identity file, creating one with a fresh key when it does not exist yet.
+    ///
+    /// Returns whether a new identity was generated.
+    pub fn load_or_generate(path: &Path) -> Result<(Self, bool)> {
+        if path.exists() {
+            return Ok((Self::load(path)?, false));
+        }
+
+        let identity = Identity::generate();
+        let contents = format!(
+            "# created: {}\n# public key: {}\n{}\n",
+            chrono::Utc::now().to_rfc3339(),
+            identity.to_public(),
+            identity.to_string().expose_secret()
+        );
+        write_private(path, contents.as_bytes())?;
+        Ok((
+            Self {
+                identities: vec![identity],
+            },
+            true,
+        ))
+    }
+
+    pub fn recipients(&self) -> Vec<Recipient> {
+        self.identities.iter().map(Identity::to_public).collect()
+    }
+
+    /// Encrypt `plaintext` to every identity in the keyring.
+    pub fn encrypt(&self, plaintext: &[u8]) -> Result<Vec<u8>> {
+        let recipients = self.recipients();
+        let encryptor = age::Encryptor::with_recipients(
+            recipients
+                .iter()
+                .map(|recipient| recipient as &dyn age::Recipient),
+        )
+        .context("Failed to set up age encryption")?;
+
+        let mut output = Vec::new();
+        let mut writer = encryptor
+            .wrap_output(&mut output)
+            .context("Failed to encrypt secret")?;
+        writer
+            .write_all(plaintext)
+            .context("Failed to encrypt secret")?;
+        writer.finish().context("Failed to encrypt secret")?;
+        Ok(output)
+    }
+
+    /// Decrypt the age file at `path`.
+    pub fn decrypt(&self, path: &Path) -> Result<Vec<u8>> {
+        let ciphertext = fs::read(path).with_context(|| format!("Failed to read {:?}", path))?;
+        let decryptor = age::Decryptor::new(&ciphertext[..])
+            .with_context(|| format!("{:?} is not an age file", path))?;
+        let mut reader = decryptor
+            .decrypt(
+                self.identities
+                    .iter()
+                    .map(|identity| identity as &dyn age::Identity),
+            )
+            .with_context(|| {
+                format!("Failed to decrypt {:?} with the configured identity", path)
+            })?;
+
+        let mut plaintext = Vec::new();
+        reader
+            .read_to_end(&mut plaintext)
+            .with_context(|| format!("Failed to decrypt {:?}", path))?;
+        Ok(plaintext)
+    }
+}
+
+/// Write `contents` to `path` readable only by the owner, creating parent directories.
+pub fn write_private(path: &Path, contents: &[u8]) -> Result<()> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)
+            .with_context(|| format!("Failed to create directory {:?}", parent))?;
+    }
+    let mut file = fs::OpenOptions::new()
+        .write(true)
+        .create(true)
+        .truncate(true)
+        .mode(PRIVATE_MODE)
+        .open(path)
+        .with_context(|| format!("Failed to write {:?}", path))?;
+    // `mode` only applies to new files.
+    file.set_permissions(fs::Permissions::from_mode(PRIVATE_MODE))
+        .with_context(|| format!("Failed to set permissions on {:?}", path))?;
+    file.write_all(contents)
+        .with_context(|| format!("Failed to write {:?}", path))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn generated_identity_round_trips() {
+        let temp = TempDir::new().unwrap();
+        let identity_path = temp.path().join("identity.txt");
+
+        let (keyring, generated) = Keyring::load_or_generate(&identity_path).unwrap();
+        assert!(generated);
+        let mode = fs::metadata(&identity_path).unwrap().permissions().mode();
+        assert_eq!(mode & 0o777, PRIVATE_MODE);
+
+        let secret = temp.path().join("netrc.age");
+        fs::write(&secret, keyring.encrypt(b"machine example.com").unwrap()).unwrap();
+
+        let (reloaded, generated) = Keyring::load_or_generate(&identity_path).unwrap();
+        assert!(!generated);
+        assert_eq!(reloaded.decrypt(&secret).unwrap(), b"machine example.com");
+    }
+
+    #[test]
+    fn decrypt_fails_with_another_identity() {
+        let temp = TempDir::new().unwrap();
+        let (keyring, _) = Keyring::load_or_generate(&temp.path().join("a.txt")).unwrap();
+        let (other, _) = Keyring::load_or_generate(&temp.path().join("b.txt")).unwrap();
+        let secret = temp.path().join("token.age");
+        fs::write(&secret, keyring.encrypt(b"token").unwrap()).unwrap();
+
+        let err = other.decrypt(&secret).unwrap_err();
+        assert!(err.to_string().contains("Failed to decrypt"));
+    }
+}
@@ -0,0 +1,104 @@
+use anyhow::{Context, Result};
+use minijinja::{Environment, UndefinedBehavior};
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::Path;
+
+/// File extension marking a dotfile as a template.
+pub const TEMPLATE_EXTENSION: &str = "tmpl";
+
+/// Variables available to `*.tmpl` dotfiles.
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct TemplateVars {
+    /// Same tags used by the `platform` tool filter (`linux`, `linux-x86_64`, `macos`, ...), sorted
+    pub platform_tags: Vec<String>,
+    /// Sanitized hostname, as matched by the `hosts` tool filter
+    pub host_slug: String,
+    pub username: String,
+    /// Active profile name
+    pub profile: String,
+    /// `[vars]` from the profile `dws.toml`, overridden per key by `config.toml`
+    pub vars: BTreeMap<String, toml::Value>,
+}
+
+impl TemplateVars {
+    /// Render the template at `path`.
+    pub fn render(&self, path: &Path) -> Result<String> {
+        let source = fs::read_to_string(path)
+            .with_context(|| format!("Failed to read template {:?}", path))?;
+
+        let mut env = Environment::new();
+        env.set_undefined_behavior(UndefinedBehavior::Strict);
+        env.set_keep_trailing_newline(true);
+        let name = path.to_string_lossy();
+        env.add_template(&name, &source)
+            .with_context(|| format!("Failed to parse template {:?}", path))?;
+        env.get_template(&name)
+            .and_then(|template| template.render(self))
+            .with_context(|| format!("Failed to render template {:?}", path))
+    }
+}
+
+/// Digest recorded for rendered files, in the same `sha256:<hex>` form as tool checksums.
+pub fn content_digest(contents: &[u8]) -> String {
+    format!("sha256:{}", hex::encode(Sha256::digest(contents)))
+}
+
+/// Digest of the file at `path`, or `None` when it cannot be read.
+pub fn file_digest(path: &Path) -> Option<String> {
+    fs::read(path)
+        .ok()
+        .map(|contents| content_digest(&contents))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    fn vars() -> TemplateVars {
+        TemplateVars {
+            platform_tags: vec!["linux".to_string(), "linux-x86_64".to_string()],
+            host_slug: "work-laptop".to_string(),
+            username: "sam".to_string(),
+            profile: "default".to_string(),
+            vars: BTreeMap::from([
+                (
+                    "email".to_string(),
+                    toml::Value::String("sam@example.com".to_string()),
+                ),
+                ("font_size".to_string(), toml::Value::Integer(14)),
+            ]),
+        }
+    }
+
+    #[test]
+    fn renders_variables_conditionals_and_loops() {
+        let temp = TempDir::new().unwrap();
+        let path = temp.path().join("config.tmpl");
+        fs::write(
+            &path,
+            "[user]\n\temail = {{ vars.email }}\n\
+             {% if host_slug == \"work-laptop\" %}size = {{ vars.font_size + 2 }}\n{% endif %}\
+             {% for tag in platform_tags %}# {{ tag }}\n{% endfor %}",
+        )
+        .unwrap();
+
+        assert_eq!(
+            vars().render(&path).unwrap(),
+            "[user]\n\temail = sam@example.com\nsize = 16\n# linux\n# linux-x86_64\n"
+        );
+    }
+
+    #[test]
+    fn undefined_variables_are_errors() {
+        let temp = TempDir::new().unwrap();
+        let path = temp.path().join("bad.tmpl");
+        fs::write(&path, "{{ vars.missing }}").unwrap();
+
+        let err = vars().render(&path).unwrap_err();
+        assert!(format!("{err:#}").contains("Failed to render template"));
+    }
+}
@@ -7,6 +7,10 @@ use std::fs;
 use std::path::{Path, PathBuf};
 use whoami::fallible;
 
+use crate::dotfiles::DotfilesSettings;
+use crate::environment::{interpolate, is_valid_env_name, EnvValue};
+use crate::process_lock::LockSettings;
+
 /// Supported installer backends defined in tool specifications.
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
 #[serde(rename_all = "lowercase")]
@@ -38,6 +42,36 @@ pub struct ToolBinary {
     pub source: String,
     #[serde(default, skip_serializing_if = "Option::is_none")]
     pub link: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub wrapper: Option<BinWrapper>,
+}
+
+/// Launcher script generated in `bin/` instead of a symlink, for binaries that only run with
+/// extra environment (e.g. `LD_LIBRARY_PATH`) or fixed leading arguments.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct BinWrapper {
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub env: BTreeMap<String, EnvValue>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub args: Vec<String>,
+}
+
+impl BinWrapper {
+    /// Expand `{install_dir}`, `$HOME` and `${XDG_*}` in every env value and argument.
+    pub fn interpolate(&self, install_dir: &Path) -> Result<Self> {
+        let env = self
+            .env
+            .iter()
+            .map(|(name, value)| Ok((name.clone(), value.interpolate(Some(install_dir))?)))
+            .collect::<Result<_>>()?;
+        let args = self
+            .args
+            .iter()
+            .map(|arg| interpolate(arg, Some(install_dir)))
+            .collect::<Result<_>>()?;
+        Ok(Self { env, args })
+    }
 }
 
 /// Types of ancillary files exposed alongside binaries.
@@ -70,6 +104,24 @@ pub struct ToolExtra {
     pub target: Option<String>,
 }
 
+/// Per-tool ceilings applied while unpacking release archives.
+///
+/// Unset values fall back to the installer defaults; they exist so that unusually large
+/// toolchains can raise the limits without weakening them for every other tool.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ExtractLimits {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_bytes: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_entries: Option<u64>,
+}
+
+impl ExtractLimits {
+    fn is_unset(&self) -> bool {
+        self.max_bytes.is_none() && self.max_entries.is_none()
+    }
+}
+
 /// Raw representation of a single tool defined in `dws.toml` (profile) or `config.toml` (workspace override).
 #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub struct ToolSpecToml {
@@ -96,6 +148,10 @@ pub struct ToolSpecToml {
     #[serde(default, skip_serializing_if = "Option::is_none")]
     pub checksum: Option<String>,
     #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub strip_components: Option<usize>,
+    #[serde(default, skip_serializing_if = "ExtractLimits::is_unset")]
+    pub extract: ExtractLimits,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
     pub app: Option<String>,
     #[serde(default, skip_serializing_if = "Option::is_none")]
     pub team_id: Option<String>,
@@ -105,6 +161,8 @@ pub struct ToolSpecToml {
     pub platforms: Vec<String>,
     #[serde(default, skip_serializing_if = "Vec::is_empty")]
     pub hosts: Vec<String>,
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub env: BTreeMap<String, EnvValue>,
 }
 
 impl ToolSpecToml {
@@ -144,11 +202,14 @@ impl ToolSpecToml {
             extras: self.extras,
             asset_filter: self.asset_filter,
             checksum: self.checksum,
+            strip_components: self.strip_components,
+            extract: self.extract,
             app: self.app,
             team_id: self.team_id,
             self_update: self.self_update,
             platforms: self.platforms,
             hosts: self.hosts,
+            env: self.env,
             name: name.to_string(),
         })
     }
@@ -214,6 +275,7 @@ impl ToolSpecToml {
             InstallerKind::Github | InstallerKind::Gitlab | InstallerKind::Script
         );
 
+        #[allow(clippy::collapsible_match)]
         match self.checksum.as_deref() {
             Some(value) if !value.trim().is_empty() => {
                 if !is_valid_checksum(value) {
@@ -251,6 +313,28 @@ impl ToolSpecToml {
             }
         }
 
+        if self.extract.max_bytes == Some(0) || self.extract.max_entries == Some(0) {
+            issues.push(ManifestIssue::tool_issue(
+                source,
+                name,
+                "`extract` limits must be greater than zero",
+            ));
+        }
+
+        for name_issue in env_name_issues(&self.env) {
+            issues.push(ManifestIssue::tool_issue(source, name, name_issue));
+        }
+
+        for (key, value) in &self.env {
+            if let Err(err) = value.interpolate(Some(Path::new("/"))) {
+                issues.push(ManifestIssue::tool_issue(
+                    source,
+                    name,
+                    format!("env `{key}`: {err}"),
+                ));
+            }
+        }
+
         if self.bin.is_empty() {
             issues.push(ManifestIssue::tool_issue(
                 source,
@@ -268,6 +352,32 @@ impl ToolSpecToml {
                 ));
             }
 
+            if let Some(message) = invalid_glob(&bin.source) {
+                issues.push(ManifestIssue::tool_issue(
+                    source,
+                    name,
+                    format!("bin entry #{idx} {message}"),
+                ));
+            }
+
+            if let Some(wrapper) = &bin.wrapper {
+                for message in env_name_issues(&wrapper.env) {
+                    issues.push(ManifestIssue::tool_issue(
+                        source,
+                        name,
+                        format!("bin entry #{idx} wrapper {message}"),
+                    ));
+                }
+
+                if let Err(err) = wrapper.interpolate(Path::new("/")) {
+                    issues.push(ManifestIssue::tool_issue(
+                        source,
+                        name,
+                        format!("bin entry #{idx} wrapper: {err}"),
+                    ));
+                }
+            }
+
             if let Some(link) = bin.link.as_deref() {
                 if link.trim().is_empty() {
                     issues.push(ManifestIssue::tool_issue(
@@ -288,6 +398,14 @@ impl ToolSpecToml {
                 ));
             }
 
+            if let Some(message) = invalid_glob(&extra.source) {
+                issues.push(ManifestIssue::tool_issue(
+                    source,
+                    name,
+                    format!("extras entry #{idx} {message}"),
+                ));
+            }
+
             if let Some(target) = extra.target.as_deref() {
                 if target.trim().is_empty() {
                     issues.push(ManifestIssue::tool_issue(
@@ -323,7 +441,16 @@ pub struct ToolConfigFile {
     #[serde(default)]
     pub active_profile: Option<String>,
     #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub env: BTreeMap<String, EnvValue>,
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
     pub tools: BTreeMap<String, ToolSpecToml>,
+    #[serde(default, skip_serializing_if = "DotfilesSettings::is_unset")]
+    pub dotfiles: DotfilesSettings,
+    #[serde(default, skip_serializing_if = "LockSettings::is_unset")]
+    pub lock: LockSettings,
+    /// Custom variables for `*.tmpl` dotfiles
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub vars: BTreeMap<String, toml::Value>,
     #[serde(flatten)]
     pub extras: BTreeMap<String, toml::Value>,
 }
@@ -358,8 +485,34 @@ impl ToolConfigFile {
     }
 }
 
+/// Describe why a glob-shaped `source` fails to compile, if it does.
+fn invalid_glob(source: &str) -> Option<String> {
+    if !source.contains(['*', '?', '[', '{']) {
+        return None;
+    }
+
+    globset::Glob::new(source)
+        .err()
+        .map(|err| format!("has an invalid glob `{source}`: {err}"))
+}
+
+fn env_name_issues(env: &BTreeMap<String, EnvValue>) -> Vec<String> {
+    env.keys()
+        .filter(|name| !is_valid_env_name(name))
+        .map(|name| format!("`{name}` is not a valid environment variable name"))
+        .collect()
+}
+
 pub fn validate_tool_config(path: &Path, config: &ToolConfigFile) -> Vec<ManifestIssue> {
     let mut issues = Vec::new();
+    for message in env_name_issues(&config.env) {
+        issues.push(ManifestIssue::general(path, format!("[env] {message}")));
+    }
+    for (key, value) in &config.env {
+        if let Err(err) = value.interpolate(None) {
+            issues.push(ManifestIssue::general(path, format!("[env] {key}: {err}")));
+        }
+    }
     for (name, spec) in &config.tools {
         issues.extend(spec.validate(name, path));
     }
@@ -379,11 +532,14 @@ pub struct ToolDefinition {
     pub extras: Vec<ToolExtra>,
     pub asset_filter: Vec<String>,
     pub checksum: Option<String>,
+    pub strip_components: Option<usize>,
+    pub extract: ExtractLimits,
     pub app: Option<String>,
     pub team_id: Option<String>,
     pub self_update: bool,
     pub platforms: Vec<String>,
     pub hosts: Vec<String>,
+    pub env: BTreeMap<String, EnvValue>,
 }
 
 /// Validation issue surfaced while checking manifest files.
@@ -423,6 +579,7 @@ pub struct ToolEntry {
 #[derive(Debug, Default)]
 pub struct ToolSet {
     entries: BTreeMap<String, ToolEntry>,
+    env: BTreeMap<String, EnvValue>,
 }
 
 impl ToolSet {
@@ -440,6 +597,12 @@ impl ToolSet {
         )
     }
 
+    /// Load a standalone manifest such as a project `.dws.toml`.
+    pub fn load_file(path: &Path) -> Result<Self> {
+        let config = ToolConfigFile::load(path)?;
+        Self::from_configs(path.to_path_buf(), config, path, ToolConfigFile::default())
+    }
+
     fn from_configs(
         profile_source: PathBuf,
         mut profile_config: ToolConfigFile,
@@ -455,6 +618,10 @@ impl ToolSet {
 
         let mut entries = BTreeMap::new();
 
+        // Workspace `[env]` keys replace profile keys of the same name, mirroring tool overrides.
+        let mut env = profile_config.env;
+        env.extend(workspace_config.env);
+
         for (name, spec) in profile_config.tools {
             if spec.applies_to(&platform_tags, host_slug.as_deref()) {
                 let definition = spec.into_definition(&name)?;
@@ -483,7 +650,12 @@ impl ToolSet {
             }
         }
 
-        Ok(Self { entries })
+        Ok(Self { entries, env })
+    }
+
+    /// Profile-level `[env]` variables (before interpolation).
+    pub fn env(&self) -> &BTreeMap<String, EnvValue> {
+        &self.env
     }
 
     /// Iterate over resolved tool entries.
@@ -535,7 +707,7 @@ fn is_valid_checksum(value: &str) -> bool {
     }
 }
 
-fn platform_tags() -> HashSet<String> {
+pub(crate) fn platform_tags() -> HashSet<String> {
     use std::env;
 
     let mut tags = HashSet::new();
@@ -586,7 +758,7 @@ fn linux_distribution_tags() -> Vec<String> {
     tags
 }
 
-fn host_slug() -> Option<String> {
+pub(crate) fn host_slug() -> Option<String> {
     let raw = fallible::hostname()
         .ok()
         .filter(|value| !value.trim().is_empty())
@@ -680,7 +852,8 @@ source = "rg"
             entry.definition.bin,
             vec![ToolBinary {
                 source: "rg".to_string(),
-                link: None
+                link: None,
+                wrapper: None,
             }]
         );
         assert!(entry.definition.self_update);
@@ -723,7 +896,8 @@ source = "rg"
             entry.definition.bin,
             vec![ToolBinary {
                 source: "rg".to_string(),
-                link: None
+                link: None,
+                wrapper: None,
             }]
         );
         assert_eq!(entry.source, workspace_config);
@@ -881,4 +1055,91 @@ kind = "completion"
             .iter()
             .any(|issue| issue.message.contains("requires a `shell` value")));
     }
+
+    #[test]
+    fn validate_rejects_zero_extract_limits() {
+        let manifest = r#"
+[tools.sample]
+installer = "github"
+project = "owner/sample"
+asset_filter = ["^sample$"]
+checksum = "sha256:aaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaa"
+extract = { max_bytes = 0, max_entries = 500 }
+
+[[tools.sample.bin]]
+source = "sample"
+"#;
+        let config: ToolConfigFile = toml::from_str(manifest).unwrap();
+        let spec = config.tools.get("sample").unwrap();
+        assert_eq!(spec.extract.max_entries, Some(500));
+        let issues = spec.validate("sample", Path::new("dws.toml"));
+        assert!(issues
+            .iter()
+            .any(|issue| issue.message.contains("`extract` limits")));
+    }
+
+    #[test]
+    fn env_tables_parse_and_merge() {
+        let temp = TempDir::new().unwrap();
+        let profile_root = setup_profile(
+            &temp,
+            r#"
+[env]
+EDITOR = "hx"
+PATH = { prepend = ["$HOME/.cargo/bin"] }
+
+[tools.go]
+installer = "github"
+project = "golang/go"
+asset_filter = ["^go"]
+env = { GOROOT = "{install_dir}/go", PATH = { append = ["{install_dir}/go/bin"] } }
+
+[[tools.go.bin]]
+source = "go/bin/go"
+"#,
+        );
+        let workspace_config = write_workspace_config(&temp, "[env]\nEDITOR = \"nvim\"\n");
+
+        let toolset = ToolSet::load(&profile_root, &workspace_config).unwrap();
+        assert_eq!(
+            toolset.env().get("EDITOR"),
+            Some(&EnvValue::Value("nvim".to_string()))
+        );
+        assert!(matches!(
+            toolset.env().get("PATH"),
+            Some(EnvValue::PathList(_))
+        ));
+
+        let go = &toolset.entries().get("go").unwrap().definition;
+        assert_eq!(
+            go.env.get("GOROOT"),
+            Some(&EnvValue::Value("{install_dir}/go".to_string()))
+        );
+    }
+
+    #[test]
+    fn validate_rejects_bad_env_names() {
+        let manifest = r#"
+[env]
+"BAD-NAME" = "x"
+
+[tools.sample]
+installer = "github"
+project = "owner/sample"
+asset_filter = ["^sample$"]
+checksum = "sha256:aaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaa"
+env = { SAMPLE_HOME = "$SHELL/sample" }
+
+[[tools.sample.bin]]
+source = "sample"
+"#;
+        let config: ToolConfigFile = toml::from_str(manifest).unwrap();
+        let issues = validate_tool_config(Path::new("dws.toml"), &config);
+        assert!(issues
+            .iter()
+            .any(|issue| issue.message.contains("`BAD-NAME` is not a valid")));
+        assert!(issues
+            .iter()
+            .any(|issue| issue.message.contains("env `SAMPLE_HOME`")));
+    }
 }
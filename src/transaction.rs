@@ -0,0 +1,161 @@
+use crate::dotfiles::move_path;
+use crate::ui;
+use anyhow::{Context, Result};
+use std::fs;
+use std::os::unix::fs::symlink;
+use std::path::{Path, PathBuf};
+
+/// A path moved out of the way while a new installation is swapped in.
+enum SetAside {
+    /// A symlink, remembered by its target so it can be recreated anywhere
+    Symlink { path: PathBuf, target: PathBuf },
+    /// A regular file or directory, moved into the transaction directory
+    Moved { path: PathBuf, stashed: PathBuf },
+}
+
+/// Previously installed links set aside until a new installation has fully succeeded.
+///
+/// Paths are moved out of place rather than deleted, so [`Transaction::roll_back`] can put the
+/// previous installation back exactly as it was if a later step fails.
+pub(crate) struct Transaction {
+    dir: PathBuf,
+    entries: Vec<SetAside>,
+}
+
+impl Transaction {
+    /// Start a transaction stashing regular files under `dir`.
+    pub(crate) fn begin(dir: PathBuf) -> Self {
+        Self {
+            dir,
+            entries: Vec::new(),
+        }
+    }
+
+    /// Move `path` out of the way. Missing paths are ignored.
+    pub(crate) fn set_aside(&mut self, path: &Path) -> Result<()> {
+        let Ok(metadata) = path.symlink_metadata() else {
+            return Ok(());
+        };
+
+        if metadata.is_symlink() {
+            let target = fs::read_link(path)
+                .with_context(|| format!("Failed to read symlink {:?}", path))?;
+            fs::remove_file(path).with_context(|| format!("Failed to remove {:?}", path))?;
+            self.entries.push(SetAside::Symlink {
+                path: path.to_path_buf(),
+                target,
+            });
+        } else {
+            let stashed = self.dir.join(self.entries.len().to_string());
+            move_path(path, &stashed)?;
+            self.entries.push(SetAside::Moved {
+                path: path.to_path_buf(),
+                stashed,
+            });
+        }
+        Ok(())
+    }
+
+    /// Put every set-aside path back, replacing whatever was installed there since.
+    ///
+    /// Rollback is best effort: a path that cannot be restored is reported and skipped, and its
+    /// stashed copy is kept in the transaction directory.
+    pub(crate) fn roll_back(mut self) {
+        let mut complete = true;
+        for entry in self.entries.drain(..).rev() {
+            let (path, restored) = match entry {
+                SetAside::Symlink { path, target } => {
+                    clear(&path);
+                    let restored = symlink(&target, &path)
+                        .with_context(|| format!("Failed to recreate symlink {:?}", path));
+                    (path, restored)
+                }
+                SetAside::Moved { path, stashed } => {
+                    clear(&path);
+                    let restored = move_path(&stashed, &path);
+                    (path, restored)
+                }
+            };
+            if let Err(err) = restored {
+                complete = false;
+                ui::warn(format!("Could not restore {}: {err:#}", path.display()));
+            }
+        }
+        if complete {
+            let _ = fs::remove_dir_all(&self.dir);
+        } else {
+            ui::warn(format!(
+                "Files that could not be restored are kept in {}",
+                self.dir.display()
+            ));
+        }
+    }
+
+    /// Discard the set-aside paths once the new installation is in place.
+    pub(crate) fn commit(self) -> Result<()> {
+        if self.dir.exists() {
+            fs::remove_dir_all(&self.dir).with_context(|| {
+                format!("Failed to remove transaction directory {:?}", self.dir)
+            })?;
+        }
+        Ok(())
+    }
+}
+
+/// Remove a file or symlink occupying `path` so a set-aside entry can move back.
+fn clear(path: &Path) {
+    if let Ok(metadata) = path.symlink_metadata() {
+        if metadata.is_dir() {
+            let _ = fs::remove_dir_all(path);
+        } else {
+            let _ = fs::remove_file(path);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn roll_back_restores_symlinks_and_files() {
+        let temp = TempDir::new().unwrap();
+        let link = temp.path().join("link");
+        let file = temp.path().join("rendered");
+        symlink("/profile/config/git", &link).unwrap();
+        fs::write(&file, "old").unwrap();
+
+        let mut transaction = Transaction::begin(temp.path().join("transaction"));
+        transaction.set_aside(&link).unwrap();
+        transaction.set_aside(&file).unwrap();
+        transaction.set_aside(&temp.path().join("missing")).unwrap();
+        assert!(link.symlink_metadata().is_err());
+        assert!(!file.exists());
+
+        // The failed attempt left something new in place.
+        fs::write(&file, "new").unwrap();
+        transaction.roll_back();
+
+        assert_eq!(
+            fs::read_link(&link).unwrap(),
+            PathBuf::from("/profile/config/git")
+        );
+        assert_eq!(fs::read_to_string(&file).unwrap(), "old");
+        assert!(!temp.path().join("transaction").exists());
+    }
+
+    #[test]
+    fn commit_discards_set_aside_files() {
+        let temp = TempDir::new().unwrap();
+        let file = temp.path().join("rendered");
+        fs::write(&file, "old").unwrap();
+
+        let mut transaction = Transaction::begin(temp.path().join("transaction"));
+        transaction.set_aside(&file).unwrap();
+        transaction.commit().unwrap();
+
+        assert!(!file.exists());
+        assert!(!temp.path().join("transaction").exists());
+    }
+}
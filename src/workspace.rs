@@ -1,24 +1,38 @@
 use anyhow::{Context, Result};
 use chrono::Utc;
 use git2::{
-    build::CheckoutBuilder, ErrorCode, Object, ObjectType, Repository, ResetType, Status,
-    StatusOptions,
+    build::CheckoutBuilder, ErrorCode, IndexAddOption, Object, ObjectType, Repository, ResetType,
+    Status, StatusOptions,
 };
-use std::collections::{HashMap, HashSet};
+use std::collections::{BTreeMap, HashMap, HashSet};
 use std::env;
+use std::fmt;
 use std::fs;
 use std::io::{self, Write};
-use std::path::PathBuf;
-use std::time::Instant;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
 
 use crate::config::{default_profile_name, Config};
-use crate::dotfiles::Dotfiles;
-use crate::environment::{Environment, Shell};
-use crate::installers::{self, InstallContext, ToolInstaller};
+use crate::dotfiles::{
+    move_path, ConflictPolicy, DotfileEntry, Dotfiles, EntryPlan, InstallOutcome, LINK_FILES_MARKER,
+};
+use crate::environment::{EnvValue, Environment, Shell};
+use crate::installers::{self, BlobStore, FetchedTool, GithubApi, InstallContext, ToolInstaller};
 // ToolEntry removed in schema v2; legacy alias dropped
-use crate::lockfile::{Lockfile, ToolReceipt};
+use crate::integrity::{self, check_symlink, check_wrapper, Finding, LinkState, Verification};
+use crate::lockfile::{BackupEntry, Lockfile, SymlinkEntry, ToolReceipt};
+use crate::plan::{ActionKind, Plan};
+use crate::process_lock::{LockMode, ProcessLock, DEFAULT_LOCK_TIMEOUT};
 use crate::profile::Profile;
-use crate::toolset::{ToolDefinition, ToolSet};
+use crate::project::{self, Project, Trust, TrustStore};
+use crate::repair::{self, Repair};
+use crate::secrets::Keyring;
+use crate::template::{file_digest, TemplateVars};
+use crate::toolset::{
+    host_slug, platform_tags, validate_tool_config, ToolConfigFile, ToolDefinition, ToolSet,
+};
+use crate::transaction::Transaction;
 use crate::ui::{self, Progress};
 use tokio::runtime::Runtime;
 
@@ -76,6 +90,12 @@ pub enum WorkspacePath {
     Profile,
     /// Config directory: active profile config
     Config,
+    /// Home directory: active profile `home/`, linked into $HOME
+    Home,
+    /// Data directory: active profile `data/`, linked into $XDG_DATA_HOME
+    Data,
+    /// State directory: active profile `state/`, linked into $XDG_STATE_HOME
+    State,
     /// Profile `dws.toml` file
     ProfileConfig,
     /// Bin directory: $XDG_STATE_HOME/dws/bin
@@ -84,10 +104,24 @@ pub enum WorkspacePath {
     Share,
     /// Lockfile path: $XDG_STATE_HOME/dws/dws.lock
     Lockfile,
+    /// Project environment state: $XDG_STATE_HOME/dws/projects/<key>
+    Projects,
+    /// Projects approved with `dws allow`: $XDG_STATE_HOME/dws/allowed.toml
+    TrustFile,
+    /// Files displaced by config symlinks: $XDG_STATE_HOME/dws/backups
+    Backups,
     /// Cache directory: $XDG_CACHE_HOME/dws
     Cache,
+    /// Content-addressed download store: $XDG_CACHE_HOME/dws/blobs/sha256
+    Blobs,
     /// Workspace config file path
     ConfigFile,
+    /// age identity for decrypting profile secrets: $XDG_CONFIG_HOME/dws/identity.txt
+    Identity,
+    /// Links set aside while an install is swapped in: $XDG_STATE_HOME/dws/transaction
+    Transaction,
+    /// Advisory lock serialising dws processes: $XDG_STATE_HOME/dws/process.lock
+    ProcessLock,
 }
 
 struct ToolInstallTask {
@@ -96,25 +130,46 @@ struct ToolInstallTask {
     installer: Box<dyn ToolInstaller>,
 }
 
+/// A tool downloaded and unpacked, waiting to be linked.
+struct StagedTool {
+    name: String,
+    resolved_version: Option<String>,
+    installer: Box<dyn ToolInstaller>,
+    fetched: FetchedTool,
+}
+
 struct UpdatedTool {
     name: String,
     version: Option<String>,
 }
 
+/// GitHub client used instead of the default one, so tests can serve releases locally.
+#[derive(Clone)]
+struct GithubApiOverride(Arc<dyn GithubApi>);
+
+impl fmt::Debug for GithubApiOverride {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("GithubApiOverride")
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct EnvironmentExport {
     pub shell: Shell,
     pub script: String,
-    pub defaulted: bool,
 }
 
 /// Workspace - represents the dws installation
 ///
 /// The workspace is rooted at $XDG_CONFIG_HOME/dws and represents your dotfiles.
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct Workspace {
+    /// User home directory ($HOME)
+    home_dir: PathBuf,
     /// XDG config home root
     config_home: PathBuf,
+    /// XDG data home root
+    data_home: PathBuf,
     /// Workspace root: $XDG_CONFIG_HOME/dws (version controlled)
     workspace_dir: PathBuf,
     /// Profiles directory: $XDG_CONFIG_HOME/dws/profiles
@@ -129,6 +184,12 @@ pub struct Workspace {
     workspace_config: Config,
     /// Currently active profile
     active_profile: Profile,
+    /// Conflict policy requested on the command line, overriding `[dotfiles] on_conflict`
+    conflict_policy: Option<ConflictPolicy>,
+    /// Lock wait requested on the command line, overriding `[lock] timeout`
+    lock_timeout: Option<Duration>,
+    /// GitHub client replacing the one built from the environment
+    github_api: Option<GithubApiOverride>,
 }
 
 impl Workspace {
@@ -138,7 +199,9 @@ impl Workspace {
     /// - Workspace: $XDG_CONFIG_HOME/dws (default: ~/.config/dws)
     /// - State: $XDG_STATE_HOME/dws (default: ~/.local/state/dws)
     pub fn new() -> Result<Self> {
+        let home_dir = Self::get_home_dir()?;
         let config_home = Self::get_config_home()?;
+        let data_home = Self::get_data_home()?;
         let workspace_dir = config_home.join("dws");
         let profiles_dir = workspace_dir.join("profiles");
         let state_dir = Self::get_state_dir()?;
@@ -150,7 +213,9 @@ impl Workspace {
         let active_profile = Profile::new(active_name.clone(), profiles_dir.join(&active_name));
 
         Ok(Self {
+            home_dir,
             config_home,
+            data_home,
             workspace_dir,
             profiles_dir,
             state_dir,
@@ -158,9 +223,64 @@ impl Workspace {
             config_path,
             workspace_config,
             active_profile,
+            conflict_policy: None,
+            lock_timeout: None,
+            github_api: None,
         })
     }
 
+    /// Override the dotfile conflict policy from configuration for this invocation.
+    pub fn set_conflict_policy(&mut self, policy: Option<ConflictPolicy>) {
+        self.conflict_policy = policy;
+    }
+
+    /// Override how long to wait for another dws process from configuration for this invocation.
+    pub fn set_lock_timeout(&mut self, timeout: Option<Duration>) {
+        self.lock_timeout = timeout;
+    }
+
+    /// Take the workspace process lock, waiting for other dws processes as configured.
+    pub fn lock(&self, mode: LockMode) -> Result<ProcessLock> {
+        let timeout = self
+            .lock_timeout
+            .or_else(|| {
+                self.workspace_config
+                    .lock()
+                    .timeout
+                    .map(Duration::from_secs)
+            })
+            .unwrap_or(DEFAULT_LOCK_TIMEOUT);
+        ProcessLock::acquire(&self.path(WorkspacePath::ProcessLock), mode, timeout)
+    }
+
+    /// Serve GitHub releases from `api` instead of the network.
+    #[cfg(test)]
+    fn set_github_api(&mut self, api: Arc<dyn GithubApi>) {
+        self.github_api = Some(GithubApiOverride(api));
+    }
+
+    /// Get the user's home directory
+    fn get_home_dir() -> Result<PathBuf> {
+        Ok(env::var("HOME").map(PathBuf::from).unwrap_or_else(|_| {
+            directories::BaseDirs::new()
+                .expect("Failed to get home directory")
+                .home_dir()
+                .to_path_buf()
+        }))
+    }
+
+    /// Get the XDG data home directory
+    fn get_data_home() -> Result<PathBuf> {
+        Ok(env::var("XDG_DATA_HOME")
+            .map(PathBuf::from)
+            .unwrap_or_else(|_| {
+                directories::BaseDirs::new()
+                    .expect("Failed to get home directory")
+                    .home_dir()
+                    .join(".local/share")
+            }))
+    }
+
     /// Get the XDG config home directory
     fn get_config_home() -> Result<PathBuf> {
         Ok(env::var("XDG_CONFIG_HOME")
@@ -208,12 +328,22 @@ impl Workspace {
             WorkspacePath::Profiles => self.profiles_dir.clone(),
             WorkspacePath::Profile => self.active_profile.root().to_path_buf(),
             WorkspacePath::Config => self.active_profile.config_dir(),
+            WorkspacePath::Home => self.active_profile.home_dir(),
+            WorkspacePath::Data => self.active_profile.data_dir(),
+            WorkspacePath::State => self.active_profile.state_dir(),
             WorkspacePath::ProfileConfig => self.active_profile.config_file(),
             WorkspacePath::Bin => self.state_dir.join("bin"),
             WorkspacePath::Share => self.state_dir.join("share"),
             WorkspacePath::Lockfile => self.state_dir.join("dws.lock"),
+            WorkspacePath::Projects => self.state_dir.join("projects"),
+            WorkspacePath::TrustFile => self.state_dir.join("allowed.toml"),
+            WorkspacePath::Backups => self.state_dir.join("backups"),
             WorkspacePath::Cache => self.cache_dir.clone(),
+            WorkspacePath::Blobs => self.cache_dir.join("blobs").join("sha256"),
             WorkspacePath::ConfigFile => self.config_path.clone(),
+            WorkspacePath::Identity => self.workspace_dir.join("identity.txt"),
+            WorkspacePath::Transaction => self.state_dir.join("transaction"),
+            WorkspacePath::ProcessLock => self.state_dir.join("process.lock"),
         }
     }
 
@@ -293,40 +423,17 @@ impl Workspace {
             return Ok(());
         }
 
-        let profile = Profile::new(profile_name.to_string(), self.profile_path(profile_name));
-        if !profile.root().exists() {
-            anyhow::bail!("Profile '{}' does not exist", profile_name);
-        }
-
-        let lockfile_path = self.path(WorkspacePath::Lockfile);
-        if lockfile_path.exists() {
-            let lockfile = Lockfile::load(&lockfile_path)?;
-            // Inline removal of previous symlinks (config + tool binaries)
-            for entry in lockfile.config_symlinks() {
-                if entry.target.exists() || entry.target.symlink_metadata().is_ok() {
-                    let _ = fs::remove_file(&entry.target);
-                }
-            }
-            for receipt in lockfile.tool_receipts() {
-                for bin in &receipt.binaries {
-                    if bin.target.exists() || bin.target.symlink_metadata().is_ok() {
-                        let _ = fs::remove_file(&bin.target);
-                    }
-                }
-                for extra in &receipt.extras {
-                    if extra.target.exists() || extra.target.symlink_metadata().is_ok() {
-                        let _ = fs::remove_file(&extra.target);
-                    }
-                }
-            }
-        }
+        self.with_active_profile(profile_name)?;
 
+        // Installing swaps the previous profile's links out, and back in if anything fails.
+        let previous = self.active_profile_name().to_string();
         self.set_active_profile(profile_name.to_string())?;
         let progress = ui::Progress::new("Switching", format!("profile '{profile_name}'"));
         match self.install() {
             Ok(()) => progress.success("Finished", Some("active now".to_string())),
             Err(err) => {
                 progress.fail("Failed", &err);
+                self.set_active_profile(previous)?;
                 return Err(err);
             }
         }
@@ -358,14 +465,7 @@ impl Workspace {
         shell: &str,
         profile: Option<&str>,
     ) -> Result<()> {
-        let target_name = if let Some(name) = profile {
-            name.to_string()
-        } else if let Some(repo) = repository {
-            Self::profile_name_from_repository(repo)
-        } else {
-            self.workspace_config.active_profile().to_string()
-        };
-
+        let target_name = self.init_profile_name(repository, profile);
         let target_profile = Profile::new(target_name.clone(), self.profile_path(&target_name));
 
         if let Some(repo) = repository {
@@ -410,6 +510,17 @@ impl Workspace {
         Ok(())
     }
 
+    /// The profile `dws init` targets: `--profile`, else the repository slug, else the active one.
+    fn init_profile_name(&self, repository: Option<&str>, profile: Option<&str>) -> String {
+        if let Some(name) = profile {
+            name.to_string()
+        } else if let Some(repo) = repository {
+            Self::profile_name_from_repository(repo)
+        } else {
+            self.workspace_config.active_profile().to_string()
+        }
+    }
+
     /// Verify that the workspace's git remote URL matches the provided repository
     fn verify_profile_repo(profile: &Profile, expected_repo: &str) -> Result<()> {
         let expected_url = Self::canonical_url(expected_repo);
@@ -566,31 +677,8 @@ impl Workspace {
 
     /// Setup shell integration by adding dws env to shell rc files
     pub fn setup(&self, shell: &str) -> Result<()> {
-        let configure_result = (|| -> Result<bool> {
-            let home = directories::BaseDirs::new()
-                .ok_or_else(|| anyhow::anyhow!("Could not determine home directory"))?
-                .home_dir()
-                .to_path_buf();
-
-            match shell {
-                "zsh" => {
-                    let zshenv = home.join(".zshenv");
-                    Self::add_shell_integration(&zshenv, "eval \"$(dws env --shell zsh)\"")
-                }
-                "bash" => {
-                    let bashrc = home.join(".bashrc");
-                    Self::add_shell_integration(&bashrc, "eval \"$(dws env --shell bash)\"")
-                }
-                "fish" => {
-                    let config_fish = home.join(".config/fish/config.fish");
-                    if let Some(parent) = config_fish.parent() {
-                        fs::create_dir_all(parent)?;
-                    }
-                    Self::add_shell_integration(&config_fish, "dws env --shell fish | source")
-                }
-                _ => Err(anyhow::anyhow!("Unsupported shell: {shell}")),
-            }
-        })();
+        let configure_result = Self::shell_integration(shell)
+            .and_then(|(rc_file, line)| Self::add_shell_integration(&rc_file, line));
 
         match configure_result {
             Ok(changed) => {
@@ -612,6 +700,22 @@ impl Workspace {
         }
     }
 
+    /// The rc file for `shell` and the line that loads dws from it.
+    fn shell_integration(shell: &str) -> Result<(PathBuf, &'static str)> {
+        let home = directories::BaseDirs::new()
+            .ok_or_else(|| anyhow::anyhow!("Could not determine home directory"))?
+            .home_dir()
+            .to_path_buf();
+
+        let shell = Shell::from_name(shell).with_context(|| {
+            format!(
+                "Unsupported shell: {shell} (supported: {})",
+                Shell::supported_names()
+            )
+        })?;
+        Ok((home.join(shell.rc_file()), shell.integration_line()))
+    }
+
     /// Add integration line to shell rc file (idempotent)
     fn add_shell_integration(rc_file: &PathBuf, integration_line: &str) -> Result<bool> {
         let existing_content = if rc_file.exists() {
@@ -652,14 +756,254 @@ impl Workspace {
         Ok(true)
     }
 
-    /// Get the dotfile manager for the active profile
-    pub fn dotfiles(&self) -> Result<Dotfiles> {
-        let config_dir = self.path(WorkspacePath::Config);
+    /// Get the dotfile managers for the active profile
+    ///
+    /// One per profile directory: `config/` → $XDG_CONFIG_HOME, `home/` → $HOME,
+    /// `data/` → $XDG_DATA_HOME and `state/` → $XDG_STATE_HOME.
+    pub fn dotfiles(&self) -> Result<Vec<Dotfiles>> {
+        // Workspace `[dotfiles]` settings take precedence over the profile's.
+        let workspace_settings = self.workspace_config.dotfiles();
+        let profile_config = ToolConfigFile::load(&self.path(WorkspacePath::ProfileConfig))?;
+        let profile_settings = &profile_config.dotfiles;
+        let link_mode = workspace_settings
+            .link_mode
+            .or(profile_settings.link_mode)
+            .unwrap_or_default();
+        let conflict_policy = self
+            .conflict_policy
+            .or(workspace_settings.on_conflict)
+            .or(profile_settings.on_conflict)
+            .unwrap_or_default();
+
+        // Template variables merge per key, with `config.toml` winning.
+        let mut vars = profile_config.vars.clone();
+        vars.extend(self.workspace_config.vars().clone());
+        let tags = platform_tags();
+        let host = host_slug();
+        let mut sorted_tags: Vec<String> = tags.iter().cloned().collect();
+        sorted_tags.sort();
+        let template_vars = TemplateVars {
+            platform_tags: sorted_tags,
+            host_slug: host.clone().unwrap_or_default(),
+            username: whoami::username(),
+            profile: self.active_profile_name().to_string(),
+            vars,
+        };
+
+        Ok(self
+            .dotfile_roots()
+            .into_iter()
+            .map(|(source, target)| {
+                Dotfiles::new(self.path(source), target)
+                    .with_link_mode(link_mode)
+                    .with_conflict_policy(conflict_policy)
+                    .with_template_vars(template_vars.clone())
+                    .with_overlays(&tags, host.as_deref())
+                    .with_identity(self.path(WorkspacePath::Identity))
+            })
+            .collect())
+    }
+
+    /// Profile directories paired with the directory their contents are linked into.
+    fn dotfile_roots(&self) -> [(WorkspacePath, PathBuf); 4] {
+        let state_home = self
+            .state_dir
+            .parent()
+            .map(Path::to_path_buf)
+            .unwrap_or_else(|| self.state_dir.clone());
+        [
+            (WorkspacePath::Config, self.config_home.clone()),
+            (WorkspacePath::Home, self.home_dir.clone()),
+            (WorkspacePath::Data, self.data_home.clone()),
+            (WorkspacePath::State, state_home),
+        ]
+    }
+
+    /// Where `path` belongs in the profile, relative to the profile root (e.g. `config/git`).
+    ///
+    /// The most specific root wins, so `~/.config/git` maps to `config/git` rather than
+    /// `home/.config/git`. `path` must already be absolute with its parent resolved.
+    fn profile_relative(&self, path: &Path) -> Option<PathBuf> {
+        let profile = self.path(WorkspacePath::Profile);
+        let mut roots: Vec<(PathBuf, PathBuf)> = self
+            .dotfile_roots()
+            .into_iter()
+            .filter_map(|(source, target)| {
+                let dir = self.path(source).strip_prefix(&profile).ok()?.to_path_buf();
+                Some((dir, target.canonicalize().unwrap_or(target)))
+            })
+            .collect();
+        roots.sort_by_key(|(_, target)| std::cmp::Reverse(target.components().count()));
+        roots.into_iter().find_map(|(dir, target)| {
+            let rest = path.strip_prefix(&target).ok()?;
+            (!rest.as_os_str().is_empty()).then(|| dir.join(rest))
+        })
+    }
+
+    /// The link target for a path relative to the profile root, if it is under a linked root.
+    fn profile_target(&self, relative: &Path) -> Option<PathBuf> {
+        let profile = self.path(WorkspacePath::Profile);
+        self.dotfile_roots()
+            .into_iter()
+            .find_map(|(source, target)| {
+                let rest = profile
+                    .join(relative)
+                    .strip_prefix(self.path(source))
+                    .ok()?
+                    .to_path_buf();
+                let target = target.canonicalize().unwrap_or(target);
+                (!rest.as_os_str().is_empty()).then(|| target.join(rest))
+            })
+    }
+
+    /// Move `path` into the active profile and link it back in place.
+    ///
+    /// `dest` is relative to the profile root and defaults to the location the profile would
+    /// link to `path` (`~/.config/foo` → `config/foo`). With `git_add`, the adopted files are
+    /// staged in the profile repository. Returns the new location inside the profile.
+    pub fn adopt(&self, path: &Path, dest: Option<&Path>, git_add: bool) -> Result<PathBuf> {
+        if !self.exists() {
+            anyhow::bail!("Workspace not initialized. Run: dws init [repo]");
+        }
+        let target = resolve_parent(path)?;
+        let metadata = fs::symlink_metadata(&target)
+            .with_context(|| format!("Failed to read {:?}", target))?;
+        let profile = self.path(WorkspacePath::Profile);
+        if metadata.is_symlink()
+            && fs::canonicalize(&target).is_ok_and(|resolved| resolved.starts_with(&profile))
+        {
+            anyhow::bail!("{:?} is already linked from the profile", target);
+        }
+        if self.is_reserved_target(&target) {
+            anyhow::bail!("{:?} is a dws directory and cannot be adopted", target);
+        }
+
+        let relative = match dest {
+            Some(dest) => dest.to_path_buf(),
+            None => self.profile_relative(&target).with_context(|| {
+                format!(
+                    "{:?} is not under $XDG_CONFIG_HOME, $HOME, $XDG_DATA_HOME or $XDG_STATE_HOME",
+                    target
+                )
+            })?,
+        };
+        check_profile_relative(&relative)?;
+        let entry = DotfileEntry::new(profile.join(&relative), target.clone());
+        if entry.is_rendered() {
+            anyhow::bail!(
+                "{:?} would be rendered rather than linked; use `dws secret add` for secrets",
+                relative
+            );
+        }
+        if self.profile_target(&relative).as_deref() != Some(target.as_path()) {
+            anyhow::bail!(
+                "{:?} is not linked to {:?}; choose a path under the matching profile directory",
+                relative,
+                target
+            );
+        }
+        if fs::symlink_metadata(&entry.source).is_ok() {
+            anyhow::bail!("{:?} already exists in the profile", entry.source);
+        }
+
+        // Adopting `~/.config/foo/bar` must not make the next install link `~/.config/foo` as a
+        // whole, so a directory created here links its files one by one.
+        let top = relative.components().take(2).collect::<PathBuf>();
+        let new_parent =
+            (top != relative && !profile.join(&top).exists()).then(|| profile.join(&top));
+
+        move_path(&target, &entry.source)?;
+        if let Some(dir) = new_parent {
+            fs::write(dir.join(LINK_FILES_MARKER), "")
+                .with_context(|| format!("Failed to write {:?}", dir.join(LINK_FILES_MARKER)))?;
+        }
+        if let Err(err) = std::os::unix::fs::symlink(&entry.source, &target) {
+            // Put the original back rather than leave nothing at the target.
+            move_path(&entry.source, &target)?;
+            return Err(err).with_context(|| format!("Failed to link {:?}", target));
+        }
+
+        let lockfile_path = self.path(WorkspacePath::Lockfile);
+        let mut lockfile = if lockfile_path.exists() {
+            Lockfile::load(&lockfile_path)?
+        } else {
+            Lockfile::new()
+        };
+        lockfile.add_config_symlink(entry.source.clone(), target);
+        lockfile.save(&lockfile_path)?;
+
+        if git_add {
+            let repo = Repository::open(&profile).with_context(|| {
+                format!(
+                    "Profile {:?} is not a git repository; nothing staged",
+                    profile
+                )
+            })?;
+            let mut index = repo
+                .index()
+                .context("Failed to read the profile git index")?;
+            index
+                .add_all([&relative], IndexAddOption::DEFAULT, None)
+                .with_context(|| format!("Failed to stage {:?}", relative))?;
+            index
+                .write()
+                .context("Failed to write the profile git index")?;
+        }
+        Ok(entry.source)
+    }
+
+    /// Encrypt `file` into the active profile as `<dest>.age`.
+    ///
+    /// `dest` is relative to the profile root (e.g. `home/.netrc`). Without it, files under
+    /// $XDG_CONFIG_HOME go to `config/` and files under $HOME to `home/`. An identity is
+    /// generated on first use. Returns the written path and whether the identity is new.
+    pub fn add_secret(&self, file: &Path, dest: Option<&Path>) -> Result<(PathBuf, bool)> {
+        let file = file
+            .canonicalize()
+            .with_context(|| format!("Failed to resolve {:?}", file))?;
+        if !file.is_file() {
+            anyhow::bail!("{:?} is not a regular file", file);
+        }
+
+        let relative = match dest {
+            Some(dest) => dest.to_path_buf(),
+            None => self.profile_relative(&file).with_context(|| {
+                format!(
+                    "{:?} is outside $HOME; choose a profile path with --as (e.g. home/.netrc)",
+                    file
+                )
+            })?,
+        };
+        check_profile_relative(&relative)?;
+
+        let mut name = relative.into_os_string();
+        if Path::new(&name).extension().and_then(|ext| ext.to_str()) != Some("age") {
+            name.push(".age");
+        }
+        let secret = self.path(WorkspacePath::Profile).join(name);
+        if secret.exists() {
+            anyhow::bail!(
+                "{:?} already exists; use `dws secret edit` to change it",
+                secret
+            );
+        }
 
-        // Target is $XDG_CONFIG_HOME (default: ~/.config)
-        let target_dir = self.config_home.clone();
+        let (keyring, generated) = Keyring::load_or_generate(&self.path(WorkspacePath::Identity))?;
+        let plaintext = fs::read(&file).with_context(|| format!("Failed to read {:?}", file))?;
+        let ciphertext = keyring.encrypt(&plaintext)?;
+        if let Some(parent) = secret.parent() {
+            fs::create_dir_all(parent)
+                .with_context(|| format!("Failed to create directory {:?}", parent))?;
+        }
+        fs::write(&secret, ciphertext).with_context(|| format!("Failed to write {:?}", secret))?;
+        Ok((secret, generated))
+    }
 
-        Ok(Dotfiles::new(config_dir, target_dir))
+    /// Whether linking `target` would replace or shadow one of dws's own directories.
+    fn is_reserved_target(&self, target: &Path) -> bool {
+        [&self.workspace_dir, &self.state_dir, &self.cache_dir]
+            .iter()
+            .any(|dir| dir.starts_with(target) || target.starts_with(dir))
     }
 
     /// Get the Environment for shell integration
@@ -672,10 +1016,12 @@ impl Workspace {
             anyhow::bail!("Workspace not initialized. Run: dws init [repo]");
         }
 
-        let (resolved, defaulted) = match Shell::from_name(shell) {
-            Some(shell) => (shell, false),
-            None => (Shell::Zsh, true),
-        };
+        let resolved = Shell::from_name(shell).with_context(|| {
+            format!(
+                "Unsupported shell: {shell} (supported: {})",
+                Shell::supported_names()
+            )
+        })?;
 
         let env = self.environment(resolved)?;
         let script = env.format_for_shell(resolved);
@@ -683,10 +1029,160 @@ impl Workspace {
         Ok(EnvironmentExport {
             shell: resolved,
             script,
-            defaulted,
         })
     }
 
+    /// Environment variables to apply to a process started by `dws exec` or `dws shell`.
+    pub fn process_environment(&self) -> Result<BTreeMap<String, String>> {
+        if !self.exists() {
+            anyhow::bail!("Workspace not initialized. Run: dws init [repo]");
+        }
+
+        // The shell only selects completion directories, which a plain process never reads.
+        let env = self.environment(Shell::Bash)?;
+        Ok(env.process_variables(|name| env::var(name).ok()))
+    }
+
+    /// State directory holding a project's bin links and lockfile.
+    fn project_state_dir(&self, project: &Project) -> PathBuf {
+        self.path(WorkspacePath::Projects).join(project.key())
+    }
+
+    /// Lockfiles of every installed project environment.
+    fn project_lockfiles(&self) -> Result<Vec<Lockfile>> {
+        let projects_dir = self.path(WorkspacePath::Projects);
+        if !projects_dir.exists() {
+            return Ok(Vec::new());
+        }
+
+        let mut lockfiles = Vec::new();
+        for entry in fs::read_dir(&projects_dir)
+            .with_context(|| format!("Failed to read projects directory {:?}", projects_dir))?
+        {
+            let lockfile_path = entry?.path().join("dws.lock");
+            if lockfile_path.exists() {
+                lockfiles.push(Lockfile::load(&lockfile_path)?);
+            }
+        }
+        Ok(lockfiles)
+    }
+
+    /// Trust the `.dws.toml` enclosing `path` and install its tools.
+    pub fn allow_project(&self, path: &Path) -> Result<()> {
+        let project = Project::locate(path)?;
+        let issues = validate_tool_config(
+            &project.manifest(),
+            &ToolConfigFile::load(&project.manifest())?,
+        );
+        if !issues.is_empty() {
+            for issue in &issues {
+                match &issue.tool {
+                    Some(tool) => ui::error(format!("{tool}: {}", issue.message)),
+                    None => ui::error(&issue.message),
+                }
+            }
+            anyhow::bail!(
+                "{} failed validation ({} issue(s)).",
+                project.manifest().display(),
+                issues.len()
+            );
+        }
+
+        self.install_project(&project)?;
+
+        let trust_path = self.path(WorkspacePath::TrustFile);
+        let mut store = TrustStore::load(&trust_path)?;
+        store.allow(&project)?;
+        store.save(&trust_path)?;
+
+        ui::success("Allowed", project.root.display());
+        ui::info("Re-enter the directory to activate it.");
+        Ok(())
+    }
+
+    /// Stop activating the `.dws.toml` enclosing `path`.
+    pub fn revoke_project(&self, path: &Path) -> Result<()> {
+        let project = Project::locate(path)?;
+        let trust_path = self.path(WorkspacePath::TrustFile);
+        let mut store = TrustStore::load(&trust_path)?;
+        if store.revoke(&project) {
+            store.save(&trust_path)?;
+            ui::success("Revoked", project.root.display());
+        } else {
+            ui::info(format!("{} was not allowed.", project.root.display()));
+        }
+        Ok(())
+    }
+
+    /// Install a project's tools into the shared cache, linking them into its own bin dir.
+    fn install_project(&self, project: &Project) -> Result<()> {
+        let tools = project.tools()?;
+        let state_dir = self.project_state_dir(project);
+        let lockfile_path = state_dir.join("dws.lock");
+        let context = self.tool_install_context(state_dir.join("bin"), state_dir.join("share"))?;
+
+        let definitions: Vec<(String, ToolDefinition)> = tools
+            .iter()
+            .map(|(name, entry)| (name.clone(), entry.definition.clone()))
+            .collect();
+        let tasks = self.build_tool_tasks(definitions, &context)?;
+        let mut lockfile = Lockfile::new();
+        let installed = self.execute_tool_tasks(tasks, &mut lockfile, "Installing")?;
+
+        prune_bin_dir(&context.bin_dir, &lockfile)?;
+        lockfile.metadata.installed_at = Utc::now().to_rfc3339();
+        lockfile.save(&lockfile_path)?;
+
+        if !installed.is_empty() {
+            ui::success(
+                "Installed",
+                format!(
+                    "{} project tool(s) for {}",
+                    installed.len(),
+                    project.root.display()
+                ),
+            );
+        }
+        Ok(())
+    }
+
+    /// Shell code switching PATH to the project environment enclosing `cwd`, if it is allowed.
+    pub fn hook_env(&self, shell: &str, cwd: &Path) -> Result<String> {
+        let shell = Shell::from_name(shell)
+            .filter(|shell| project::hook_script(*shell).is_some())
+            .with_context(|| format!("hook-env does not support shell: {shell}"))?;
+
+        let mut next = None;
+        if let Some(project) = Project::discover(cwd) {
+            let store = TrustStore::load(&self.path(WorkspacePath::TrustFile))?;
+            match store.trust(&project)? {
+                Trust::Allowed => {
+                    next = Some((
+                        project.root.clone(),
+                        self.project_state_dir(&project).join("bin"),
+                    ))
+                }
+                Trust::Changed => ui::warn(format!(
+                    "{} changed since it was allowed; run `dws allow` to activate it",
+                    project.manifest().display()
+                )),
+                Trust::Unknown => ui::warn(format!(
+                    "{} is not allowed; run `dws allow` to activate it",
+                    project.manifest().display()
+                )),
+            }
+        }
+
+        let current_path = env::var("PATH").unwrap_or_default();
+        Ok(project::render_hook_env(
+            shell,
+            &current_path,
+            project::previous_bin().as_deref(),
+            next.as_ref()
+                .map(|(root, bin)| (root.as_path(), bin.as_path())),
+        ))
+    }
+
     /// Load tool definitions defined for this workspace.
     pub fn tools(&self) -> Result<ToolSet> {
         let profile_root = self.path(WorkspacePath::Profile);
@@ -695,6 +1191,14 @@ impl Workspace {
     }
 
     fn prepare_tool_install_context(&self) -> Result<InstallContext> {
+        self.tool_install_context(
+            self.path(WorkspacePath::Bin),
+            self.path(WorkspacePath::Share),
+        )
+    }
+
+    /// Install context linking into `bin_dir`/`share_dir` while sharing the download cache.
+    fn tool_install_context(&self, bin_dir: PathBuf, share_dir: PathBuf) -> Result<InstallContext> {
         let cache_dir = self.path(WorkspacePath::Cache);
         fs::create_dir_all(&cache_dir)
             .with_context(|| format!("Failed to create cache directory {:?}", cache_dir))?;
@@ -706,11 +1210,9 @@ impl Workspace {
             )
         })?;
 
-        let bin_dir = self.path(WorkspacePath::Bin);
         fs::create_dir_all(&bin_dir)
             .with_context(|| format!("Failed to create bin directory {:?}", bin_dir))?;
 
-        let share_dir = self.path(WorkspacePath::Share);
         fs::create_dir_all(&share_dir)
             .with_context(|| format!("Failed to create share directory {:?}", share_dir))?;
 
@@ -726,12 +1228,20 @@ impl Workspace {
             )
         })?;
 
-        let github_api = installers::default_github_api()?;
+        let blobs_dir = self.path(WorkspacePath::Blobs);
+        fs::create_dir_all(&blobs_dir)
+            .with_context(|| format!("Failed to create blob store directory {:?}", blobs_dir))?;
+
+        let github_api = match &self.github_api {
+            Some(GithubApiOverride(api)) => api.clone(),
+            None => installers::default_github_api()?,
+        };
 
         Ok(InstallContext {
             cache_tools_dir,
             bin_dir,
             share_dir,
+            blob_store: BlobStore::new(blobs_dir),
             github_api,
         })
     }
@@ -766,6 +1276,16 @@ impl Workspace {
         lockfile: &mut Lockfile,
         pending_label: &str,
     ) -> Result<Vec<UpdatedTool>> {
+        let staged = self.fetch_tool_tasks(tasks, pending_label)?;
+        self.link_staged_tools(staged, lockfile, pending_label)
+    }
+
+    /// Download and unpack every tool before any of them is linked.
+    fn fetch_tool_tasks(
+        &self,
+        tasks: Vec<ToolInstallTask>,
+        pending_label: &str,
+    ) -> Result<Vec<StagedTool>> {
         if tasks.is_empty() {
             return Ok(Vec::new());
         }
@@ -779,7 +1299,7 @@ impl Workspace {
             None
         };
 
-        let mut updated = Vec::new();
+        let mut staged = Vec::new();
 
         for (index, task) in tasks.into_iter().enumerate() {
             let ToolInstallTask {
@@ -795,19 +1315,52 @@ impl Workspace {
                 .unwrap_or_else(|| name.clone());
             ui::status(pending_label, format!("{display} ({position}/{total})"));
 
-            if let Err(err) = installer.install(runtime.as_mut(), lockfile) {
-                ui::error(format!(
-                    "Failed to {} tool '{}': {}",
-                    pending_label.to_lowercase(),
+            match installer.fetch(runtime.as_mut()) {
+                Ok(fetched) => staged.push(StagedTool {
                     name,
-                    err
-                ));
-                return Err(err).context(format!(
-                    "Failed to {} tool '{}'",
-                    pending_label.to_lowercase(),
-                    name
-                ));
+                    resolved_version,
+                    installer,
+                    fetched,
+                }),
+                Err(err) => {
+                    ui::error(format!(
+                        "Failed to {} tool '{}': {}",
+                        pending_label.to_lowercase(),
+                        name,
+                        err
+                    ));
+                    return Err(err).context(format!(
+                        "Failed to {} tool '{}'",
+                        pending_label.to_lowercase(),
+                        name
+                    ));
+                }
             }
+        }
+
+        Ok(staged)
+    }
+
+    /// Link fetched tools into the bin and share directories, recording them in `lockfile`.
+    fn link_staged_tools(
+        &self,
+        staged: Vec<StagedTool>,
+        lockfile: &mut Lockfile,
+        pending_label: &str,
+    ) -> Result<Vec<UpdatedTool>> {
+        let mut updated = Vec::new();
+
+        for tool in staged {
+            let StagedTool {
+                name,
+                resolved_version,
+                installer,
+                fetched,
+            } = tool;
+
+            installer.link(fetched, lockfile).with_context(|| {
+                format!("Failed to {} tool '{}'", pending_label.to_lowercase(), name)
+            })?;
 
             updated.push(UpdatedTool {
                 name,
@@ -819,39 +1372,20 @@ impl Workspace {
     }
 
     /// Install the workspace (symlink configs, install tools)
+    ///
+    /// Installation is transactional. Templates and secrets are rendered and every tool is
+    /// downloaded before anything on disk changes; the previous links are then set aside, the new
+    /// ones created and the lockfile replaced atomically. If any step fails, the partial
+    /// installation is removed and the previous links are put back.
     pub fn install(&self) -> Result<()> {
         let tools = self.tools()?;
-
-        // Load or create lockfile
         let lockfile_path = self.path(WorkspacePath::Lockfile);
-        let mut lockfile = if lockfile_path.exists() {
-            // Cleanup existing installation first (remove previous symlinks)
-            let old_lockfile = Lockfile::load(&lockfile_path)?;
-            for entry in old_lockfile.config_symlinks() {
-                if entry.target.exists() || entry.target.symlink_metadata().is_ok() {
-                    let _ = fs::remove_file(&entry.target);
-                }
-            }
-            for receipt in old_lockfile.tool_receipts() {
-                for bin in &receipt.binaries {
-                    if bin.target.exists() || bin.target.symlink_metadata().is_ok() {
-                        let _ = fs::remove_file(&bin.target);
-                    }
-                }
-            }
-            Lockfile::new()
-        } else {
-            Lockfile::new()
-        };
+        let previous = self.current_lockfile()?;
+        let env = resolve_profile_env(&tools)?;
 
-        // Install config entries and record in lockfile
-        let dotfiles = self.dotfiles()?;
-        let config_entries = dotfiles.discover_entries()?;
-
-        for entry in &config_entries {
-            entry.install()?;
-            lockfile.add_config_symlink(entry.source.clone(), entry.target.clone());
-        }
+        // Stage: fail on broken templates, secrets or downloads while the old links still work.
+        let mut preflight = Plan::new("install", self.active_profile_name());
+        self.plan_install_into(&mut preflight, previous.as_ref())?;
 
         let install_context = self.prepare_tool_install_context()?;
 
@@ -865,14 +1399,37 @@ impl Workspace {
             ui::info("No tools defined for the active profile.");
         }
         let install_start = Instant::now();
-        let installed = self.execute_tool_tasks(tasks, &mut lockfile, "Installing")?;
+        let staged = self.fetch_tool_tasks(tasks, "Installing")?;
+
+        // Swap: backups outlive the links that displaced them until they are restored.
+        let mut lockfile = Lockfile::new();
+        if let Some(previous) = &previous {
+            lockfile.backups = previous.backups.clone();
+        }
+        let kept_backups = lockfile.backups.len();
+        lockfile.env = env;
+
+        let mut transaction = Transaction::begin(self.transaction_dir());
+        let swapped = self.swap_in_install(
+            &mut transaction,
+            previous.as_ref(),
+            staged,
+            &mut lockfile,
+            &lockfile_path,
+        );
+        let installed = match swapped {
+            Ok(installed) => installed,
+            Err(err) => {
+                lockfile.backups.drain(..kept_backups);
+                self.roll_back_install(&lockfile, transaction, previous.as_ref());
+                return Err(err);
+            }
+        };
+        transaction.commit()?;
 
         self.prune_unused_bin(&lockfile)?;
         self.prune_unused_cache(&lockfile)?;
-
-        // Save lockfile
-        lockfile.metadata.installed_at = Utc::now().to_rfc3339();
-        lockfile.save(&lockfile_path)?;
+        self.prune_unused_blobs(&lockfile)?;
 
         if !installed.is_empty() {
             let elapsed = install_start.elapsed();
@@ -899,14 +1456,439 @@ impl Workspace {
         Ok(())
     }
 
-    /// Update installed tools, respecting version pins and the `self_update` flag.
-    pub fn update_tools(&self, requested: Option<&str>) -> Result<()> {
+    /// Directory for the links set aside by one install attempt.
+    fn transaction_dir(&self) -> PathBuf {
+        self.path(WorkspacePath::Transaction)
+            .join(Utc::now().format("%Y%m%dT%H%M%S%.f").to_string())
+    }
+
+    /// Replace the links recorded in `previous` with the staged installation.
+    fn swap_in_install(
+        &self,
+        transaction: &mut Transaction,
+        previous: Option<&Lockfile>,
+        staged: Vec<StagedTool>,
+        lockfile: &mut Lockfile,
+        lockfile_path: &Path,
+    ) -> Result<Vec<UpdatedTool>> {
+        if let Some(previous) = previous {
+            for entry in previous.config_symlinks() {
+                if entry.target.symlink_metadata().is_err() {
+                    continue;
+                }
+                if edited_since_install(entry) {
+                    ui::warn(format!(
+                        "Keeping {}: it was edited after dws rendered it",
+                        entry.target.display()
+                    ));
+                    continue;
+                }
+                transaction.set_aside(&entry.target)?;
+            }
+            for receipt in previous.tool_receipts() {
+                set_aside_tool_links(transaction, receipt)?;
+            }
+        }
+
+        let backup_dir = self
+            .path(WorkspacePath::Backups)
+            .join(Utc::now().format("%Y%m%dT%H%M%S").to_string());
+
+        for dotfiles in self.dotfiles()? {
+            self.install_dotfiles(&dotfiles, &backup_dir, lockfile)?;
+        }
+
+        let installed = self.link_staged_tools(staged, lockfile, "Installing")?;
+
+        lockfile.metadata.installed_at = Utc::now().to_rfc3339();
+        lockfile.save(lockfile_path)?;
+        Ok(installed)
+    }
+
+    /// Undo a failed swap: remove what the attempt installed and put the previous links back.
+    ///
+    /// `attempt` holds the entries installed before the failure, with only the backups this
+    /// attempt created.
+    fn roll_back_install(
+        &self,
+        attempt: &Lockfile,
+        transaction: Transaction,
+        previous: Option<&Lockfile>,
+    ) {
+        let mut attempt = attempt.clone();
+        let restored = restore_recorded_backups(&mut attempt);
+        for entry in attempt.config_symlinks() {
+            if !restored.contains(&entry.target) {
+                let _ = remove_config_entry(entry);
+            }
+        }
+        for receipt in attempt.tool_receipts() {
+            remove_tool_links(receipt);
+        }
+
+        transaction.roll_back();
+
+        // Links of a tool that failed half way are not on any receipt yet.
+        let fallback = Lockfile::new();
+        let _ = prune_bin_dir(
+            &self.path(WorkspacePath::Bin),
+            previous.unwrap_or(&fallback),
+        );
+        ui::warn("Install failed; the previous installation was restored");
+    }
+
+    /// The lockfile on disk, if the workspace has been installed.
+    fn current_lockfile(&self) -> Result<Option<Lockfile>> {
+        let lockfile_path = self.path(WorkspacePath::Lockfile);
+        if lockfile_path.exists() {
+            Ok(Some(Lockfile::load(&lockfile_path)?))
+        } else {
+            Ok(None)
+        }
+    }
+
+    /// A copy of this workspace with `profile_name` active, without saving `config.toml`.
+    fn with_active_profile(&self, profile_name: &str) -> Result<Workspace> {
+        let profile = Profile::new(profile_name.to_string(), self.profile_path(profile_name));
+        if !profile.root().exists() {
+            anyhow::bail!("Profile '{}' does not exist", profile_name);
+        }
+        let mut view = self.clone();
+        view.workspace_config
+            .set_active_profile(profile_name.to_string());
+        view.active_profile = profile;
+        Ok(view)
+    }
+
+    /// Plan [`Workspace::install`] for the active profile without touching disk.
+    pub fn plan_install(&self) -> Result<Plan> {
+        let mut plan = Plan::new("install", self.active_profile_name());
+        self.plan_install_into(&mut plan, self.current_lockfile()?.as_ref())?;
+        Ok(plan)
+    }
+
+    /// Plan [`Workspace::use_profile`].
+    pub fn plan_use(&self, profile_name: &str) -> Result<Plan> {
+        let mut plan = Plan::new(format!("use {profile_name}"), profile_name);
+        if profile_name == self.active_profile_name() {
+            return Ok(plan);
+        }
+        let target = self.with_active_profile(profile_name)?;
+        plan.push(
+            ActionKind::Activate,
+            profile_name,
+            Some(format!("replacing '{}'", self.active_profile_name())),
+        );
+        target.plan_install_into(&mut plan, self.current_lockfile()?.as_ref())?;
+        Ok(plan)
+    }
+
+    /// Plan [`Workspace::update_tools`]. Latest releases are not looked up, so every eligible
+    /// tool is listed.
+    pub fn plan_update(&self, requested: Option<&str>) -> Result<Plan> {
+        let command = match requested {
+            Some(name) => format!("update {name}"),
+            None => "update".to_string(),
+        };
+        let mut plan = Plan::new(command, self.active_profile_name());
         let tools = self.tools()?;
-        if tools.is_empty() {
-            ui::info("No tools defined for the active profile.");
-            return Ok(());
+        let lockfile = self.current_lockfile()?.unwrap_or_else(Lockfile::new);
+        for (name, definition) in Self::update_candidates(&tools, requested)? {
+            if !installers::is_supported(definition.installer) {
+                continue;
+            }
+            match lockfile
+                .tool_receipts()
+                .find(|receipt| receipt.name == name)
+            {
+                Some(receipt) => plan.push(
+                    ActionKind::UpgradeTool,
+                    name,
+                    Some(format!("{} → latest release", receipt.resolved_version)),
+                ),
+                None => plan.push(ActionKind::InstallTool, name, Some("latest".to_string())),
+            }
+        }
+        Ok(plan)
+    }
+
+    /// Plan [`Workspace::reset`], based on the profile as it is now.
+    pub fn plan_reset(&self) -> Result<Plan> {
+        let mut plan = Plan::new("reset", self.active_profile_name());
+        let profile_path = self.path(WorkspacePath::Profile);
+        if !profile_path.exists() {
+            anyhow::bail!(
+                "Active profile at {:?} does not exist. Run 'dws init' first.",
+                profile_path
+            );
+        }
+        if Repository::open(&profile_path).is_ok() {
+            plan.push(
+                ActionKind::ResetRepository,
+                profile_path.display(),
+                Some("discards uncommitted changes".to_string()),
+            );
+        }
+
+        // Reset uninstalls first, so everything is planned as a fresh install. Links the profile
+        // still provides come back unchanged; the rest of what uninstall removes is listed here.
+        if let Some(current) = self.current_lockfile()? {
+            let mut targets = HashSet::new();
+            for dotfiles in self.dotfiles()? {
+                for entry in dotfiles.discover_entries()? {
+                    targets.insert(entry.target);
+                }
+            }
+            for entry in current.config_symlinks() {
+                let present = entry.target.symlink_metadata().is_ok();
+                if present && !targets.contains(&entry.target) && !edited_since_install(entry) {
+                    plan.push(ActionKind::Unlink, entry.target.display(), None);
+                }
+            }
+            let tools = self.tools()?;
+            for receipt in current.tool_receipts() {
+                if !tools.entries().contains_key(&receipt.name) {
+                    plan.push(
+                        ActionKind::RemoveTool,
+                        &receipt.name,
+                        Some(receipt.resolved_version.clone()),
+                    );
+                }
+            }
+        }
+        for (dir, detail) in [
+            (&self.cache_dir, "downloads and unpacked tools"),
+            (&self.state_dir, "lockfile, backups and stored archives"),
+        ] {
+            if dir.exists() {
+                plan.push(
+                    ActionKind::PruneCache,
+                    dir.display(),
+                    Some(detail.to_string()),
+                );
+            }
+        }
+        // Cache entries an empty lockfile leaves unreferenced go with the whole directory.
+        let mut install = Plan::default();
+        self.plan_install_into(&mut install, None)?;
+        plan.actions.extend(
+            install
+                .actions
+                .into_iter()
+                .filter(|action| action.action != ActionKind::PruneCache),
+        );
+        Ok(plan)
+    }
+
+    /// Plan [`Workspace::repair`].
+    pub fn plan_repair(&self) -> Result<Plan> {
+        let (_, _, repairs) = self.diagnose_drift()?;
+        Ok(repair::plan(&repairs, self.active_profile_name()))
+    }
+
+    /// Plan [`Workspace::init_with_shell`].
+    pub fn plan_init(
+        &self,
+        repository: Option<&str>,
+        shell: Option<&str>,
+        profile: Option<&str>,
+    ) -> Result<Plan> {
+        let shell = match shell {
+            Some(name) => name.to_string(),
+            None => Self::detect_shell_from_env()?,
+        };
+        let target_name = self.init_profile_name(repository, profile);
+        let mut plan = Plan::new("init", &target_name);
+
+        let root = self.profile_path(&target_name);
+        if !root.exists() {
+            // Links and tools depend on the profile contents, which don't exist yet.
+            let (kind, detail) = match repository {
+                Some(repo) => (ActionKind::Clone, Self::canonical_url(repo)),
+                None => (
+                    ActionKind::CreateProfile,
+                    "from the built-in template".to_string(),
+                ),
+            };
+            plan.push(kind, root.display(), Some(detail));
+        } else {
+            if target_name != self.active_profile_name() {
+                plan.push(ActionKind::Activate, &target_name, None);
+            }
+            self.with_active_profile(&target_name)?
+                .plan_install_into(&mut plan, self.current_lockfile()?.as_ref())?;
+        }
+
+        let (rc_file, line) = Self::shell_integration(&shell)?;
+        let configured = fs::read_to_string(&rc_file)
+            .map(|content| content.contains(line))
+            .unwrap_or(false);
+        if !configured {
+            plan.push(
+                ActionKind::ModifyRc,
+                rc_file.display(),
+                Some(format!("add {shell} shell integration")),
+            );
+        }
+        Ok(plan)
+    }
+
+    /// Add the changes [`Workspace::install`] would make, given the lockfile currently on disk.
+    fn plan_install_into(&self, plan: &mut Plan, current: Option<&Lockfile>) -> Result<()> {
+        let empty = Lockfile::new();
+        let current = current.unwrap_or(&empty);
+        let recorded: HashMap<&Path, &SymlinkEntry> = current
+            .config_symlinks()
+            .map(|entry| (entry.target.as_path(), entry))
+            .collect();
+
+        let identity = self.path(WorkspacePath::Identity);
+        let mut targets = HashSet::new();
+        for dotfiles in self.dotfiles()? {
+            for entry in dotfiles.discover_entries()? {
+                if self.is_reserved_target(&entry.target) {
+                    plan.push(
+                        ActionKind::Skip,
+                        entry.target.display(),
+                        Some("it would replace a dws directory".to_string()),
+                    );
+                    continue;
+                }
+                if entry.is_secret() && !identity.exists() {
+                    plan.push(
+                        ActionKind::Skip,
+                        entry.target.display(),
+                        Some(format!("no age identity at {}", identity.display())),
+                    );
+                    continue;
+                }
+
+                // Install first removes what the last install left, unless it was edited since.
+                let cleared = recorded
+                    .get(entry.target.as_path())
+                    .is_some_and(|previous| !edited_since_install(previous));
+                targets.insert(entry.target.clone());
+                let (kind, detail) = match dotfiles.plan_entry(&entry, cleared)? {
+                    EntryPlan::Unchanged => continue,
+                    EntryPlan::Install => {
+                        let kind = if entry.is_rendered() {
+                            ActionKind::Render
+                        } else {
+                            ActionKind::Link
+                        };
+                        (
+                            kind,
+                            entry.overlay.as_ref().map(|tag| format!("from {tag}")),
+                        )
+                    }
+                    EntryPlan::Conflict(policy) => {
+                        let kind = match policy {
+                            ConflictPolicy::Backup => ActionKind::Backup,
+                            ConflictPolicy::Skip => ActionKind::Skip,
+                            ConflictPolicy::Adopt => ActionKind::Adopt,
+                            ConflictPolicy::Overwrite => ActionKind::Overwrite,
+                        };
+                        (kind, Some("exists and is not managed by dws".to_string()))
+                    }
+                };
+                plan.push(kind, entry.target.display(), detail);
+            }
+        }
+        for entry in current.config_symlinks() {
+            let present = entry.target.symlink_metadata().is_ok();
+            if present && !targets.contains(&entry.target) && !edited_since_install(entry) {
+                plan.push(ActionKind::Unlink, entry.target.display(), None);
+            }
+        }
+
+        let tools = self.tools()?;
+        let mut replaced = HashSet::new();
+        for (name, entry) in tools.iter() {
+            let definition = &entry.definition;
+            if !installers::is_supported(definition.installer) {
+                plan.push(
+                    ActionKind::Skip,
+                    name,
+                    Some(format!(
+                        "installer '{}' is not yet supported",
+                        definition.installer
+                    )),
+                );
+                continue;
+            }
+
+            let receipts: Vec<&ToolReceipt> = current
+                .tool_receipts()
+                .filter(|receipt| &receipt.name == name)
+                .collect();
+            let pinned = definition.version.as_ref().filter(|version| {
+                receipts
+                    .iter()
+                    .any(|receipt| &receipt.resolved_version != *version)
+            });
+            match (receipts.first(), pinned) {
+                (None, _) => plan.push(
+                    ActionKind::InstallTool,
+                    name,
+                    Some(
+                        definition
+                            .version
+                            .clone()
+                            .unwrap_or_else(|| "latest".to_string()),
+                    ),
+                ),
+                (Some(receipt), Some(version)) => {
+                    plan.push(
+                        ActionKind::UpgradeTool,
+                        name,
+                        Some(format!("{} → {}", receipt.resolved_version, version)),
+                    );
+                    replaced.insert(name.clone());
+                }
+                (Some(receipt), None) if receipts.iter().any(|r| receipt_missing_artifacts(r)) => {
+                    plan.push(
+                        ActionKind::InstallTool,
+                        name,
+                        Some(format!(
+                            "{}, files missing or damaged",
+                            receipt.resolved_version
+                        )),
+                    )
+                }
+                (Some(_), None) => {}
+            }
+        }
+        for receipt in current.tool_receipts() {
+            if !tools.entries().contains_key(&receipt.name) {
+                plan.push(
+                    ActionKind::RemoveTool,
+                    &receipt.name,
+                    Some(receipt.resolved_version.clone()),
+                );
+            }
+        }
+
+        // What stays referenced once removed and upgraded tools are gone.
+        let mut kept = current.clone();
+        kept.retain_tool_receipts(|receipt| {
+            tools.entries().contains_key(&receipt.name) && !replaced.contains(&receipt.name)
+        });
+        for dir in self.unused_cache_dirs(&kept)? {
+            plan.push(ActionKind::PruneCache, dir.display(), None);
         }
+        let store = BlobStore::new(self.path(WorkspacePath::Blobs));
+        for blob in store.unreferenced(&self.blob_references(&kept)?)? {
+            plan.push(ActionKind::PruneCache, blob.display(), None);
+        }
+        Ok(())
+    }
 
+    /// Tools `dws update` considers: the requested one (or all), minus pinned and self-updating
+    /// tools.
+    fn update_candidates(
+        tools: &ToolSet,
+        requested: Option<&str>,
+    ) -> Result<Vec<(String, ToolDefinition)>> {
         let mut selected: Vec<(&str, &crate::toolset::ToolEntry)> = Vec::new();
         if let Some(tool_name) = requested {
             if let Some(entry) = tools.entries().get(tool_name) {
@@ -940,6 +1922,18 @@ impl Workspace {
             candidates.push((name.to_string(), entry.definition.clone()));
         }
 
+        Ok(candidates)
+    }
+
+    /// Update installed tools, respecting version pins and the `self_update` flag.
+    pub fn update_tools(&self, requested: Option<&str>) -> Result<()> {
+        let tools = self.tools()?;
+        if tools.is_empty() {
+            ui::info("No tools defined for the active profile.");
+            return Ok(());
+        }
+
+        let candidates = Self::update_candidates(&tools, requested)?;
         if candidates.is_empty() {
             ui::info("No tools eligible for update.");
             return Ok(());
@@ -971,7 +1965,12 @@ impl Workspace {
         }
 
         let mut filtered_tasks = Vec::new();
-        for task in tasks {
+        for mut task in tasks {
+            // Unpinned tools follow the latest release; a failed lookup is left to the download
+            // to report.
+            if task.resolved_version.is_none() {
+                task.resolved_version = task.installer.resolve_version().ok();
+            }
             if let Some(resolved) = &task.resolved_version {
                 if let Some(versions) = existing_versions.get(&task.name) {
                     let all_match = versions.iter().all(|v| v == resolved);
@@ -989,7 +1988,7 @@ impl Workspace {
                         continue;
                     } else if all_match && missing_paths {
                         ui::warn(format!(
-                            "'{}' is already at version '{}' but required files are missing; reinstalling.",
+                            "'{}' is already at version '{}' but required files are missing or damaged; reinstalling.",
                             task.name, resolved
                         ));
                     }
@@ -1003,39 +2002,54 @@ impl Workspace {
             return Ok(());
         }
 
-        let names_to_update: Vec<String> = filtered_tasks
+        let names_to_update: HashSet<String> = filtered_tasks
             .iter()
             .map(|task| task.name.clone())
             .collect();
 
-        for name in &names_to_update {
-            // Remove binary symlinks for this tool before re-installing
-            let receipts_for_tool: Vec<_> = lockfile
-                .tool_receipts()
-                .filter(|r| r.name == *name)
-                .cloned()
-                .collect();
-            for receipt in receipts_for_tool {
-                for bin in receipt.binaries {
-                    if bin.target.exists() || bin.target.symlink_metadata().is_ok() {
-                        let _ = fs::remove_file(&bin.target);
-                    }
-                }
-            }
-            // Drop prior receipts
-            lockfile.retain_tool_receipts(|entry| entry.name != *name);
-        }
+        let env = resolve_profile_env(&tools)?;
 
+        // Download everything first so a failed fetch leaves the current versions linked.
         let update_start = Instant::now();
-        let updated = self
-            .execute_tool_tasks(filtered_tasks, &mut lockfile, "Updating")
+        let staged = self
+            .fetch_tool_tasks(filtered_tasks, "Updating")
             .context("Failed to update selected tools")?;
 
+        let mut transaction = Transaction::begin(self.transaction_dir());
+        let previous = lockfile.clone();
+        let swapped = (|| -> Result<Vec<UpdatedTool>> {
+            for receipt in previous.tool_receipts() {
+                if names_to_update.contains(&receipt.name) {
+                    set_aside_tool_links(&mut transaction, receipt)?;
+                }
+            }
+            lockfile.retain_tool_receipts(|entry| !names_to_update.contains(&entry.name));
+            lockfile.env = env;
+
+            let updated = self.link_staged_tools(staged, &mut lockfile, "Updating")?;
+            lockfile.metadata.installed_at = Utc::now().to_rfc3339();
+            lockfile.save(&lockfile_path)?;
+            Ok(updated)
+        })();
+        let updated = match swapped {
+            Ok(updated) => updated,
+            Err(err) => {
+                for receipt in lockfile.tool_receipts() {
+                    if names_to_update.contains(&receipt.name) {
+                        remove_tool_links(receipt);
+                    }
+                }
+                transaction.roll_back();
+                let _ = prune_bin_dir(&self.path(WorkspacePath::Bin), &previous);
+                ui::warn("Update failed; the previous tool versions were restored");
+                return Err(err).context("Failed to update selected tools");
+            }
+        };
+        transaction.commit()?;
+
         self.prune_unused_bin(&lockfile)?;
         self.prune_unused_cache(&lockfile)?;
-
-        lockfile.metadata.installed_at = Utc::now().to_rfc3339();
-        lockfile.save(&lockfile_path)?;
+        self.prune_unused_blobs(&lockfile)?;
 
         let summary = updated
             .iter()
@@ -1062,6 +2076,128 @@ impl Workspace {
         Ok(())
     }
 
+    /// Re-hash the files of installed tools (all, or just `requested`) and record the outcome
+    /// on their receipts.
+    ///
+    /// A cached archive that no longer matches its checksum is discarded along with its blob, so
+    /// the reinstall that fixes the tool downloads a fresh copy.
+    pub fn verify_tools(&self, requested: Option<&str>) -> Result<Vec<Verification>> {
+        let lockfile_path = self.path(WorkspacePath::Lockfile);
+        if !lockfile_path.exists() {
+            anyhow::bail!("No lockfile found; run `dws install` first.");
+        }
+        let mut lockfile = Lockfile::load(&lockfile_path)?;
+        if let Some(name) = requested {
+            if !lockfile.tool_receipts().any(|receipt| receipt.name == name) {
+                anyhow::bail!("Tool '{}' is not installed.", name);
+            }
+        }
+
+        let store = BlobStore::new(self.path(WorkspacePath::Blobs));
+        let mut verifications = Vec::new();
+        for receipt in lockfile.tool_receipts_mut() {
+            if requested.is_some_and(|name| receipt.name != name) {
+                continue;
+            }
+            let verification = integrity::verify_receipt(receipt);
+            for finding in &verification.findings {
+                if let Finding::ArchiveModified(archive) = finding {
+                    discard_tampered_archive(&store, receipt, archive)?;
+                }
+            }
+            receipt.status = verification.status;
+            verifications.push(verification);
+        }
+        lockfile.save(&lockfile_path)?;
+        Ok(verifications)
+    }
+
+    /// The lockfile, the active tool set and the repairs that would bring disk back in line with
+    /// the lockfile.
+    fn diagnose_drift(&self) -> Result<(Lockfile, ToolSet, Vec<Repair>)> {
+        let Some(lockfile) = self.current_lockfile()? else {
+            anyhow::bail!("No lockfile found; run `dws install` first.");
+        };
+        let tools = self.tools()?;
+        let store = BlobStore::new(self.path(WorkspacePath::Blobs));
+        let repairs =
+            repair::diagnose(&lockfile, |name| tools.entries().contains_key(name), &store);
+        Ok((lockfile, tools, repairs))
+    }
+
+    /// Fix links and tool contents that drifted from the lockfile, without reinstalling anything
+    /// that is intact. Returns what was fixed.
+    ///
+    /// Missing or misdirected links are recreated from the receipts, missing `contents/`
+    /// directories are unpacked again from the cached archive (downloading it only when it is
+    /// gone too), and receipts that cannot be restored are dropped.
+    pub fn repair(&self) -> Result<Plan> {
+        let (mut lockfile, tools, repairs) = self.diagnose_drift()?;
+        if repairs.is_empty() {
+            return Ok(repair::plan(&repairs, self.active_profile_name()));
+        }
+
+        let context = if repairs
+            .iter()
+            .any(|repair| matches!(repair, Repair::Restore { .. }))
+        {
+            Some(self.prepare_tool_install_context()?)
+        } else {
+            None
+        };
+
+        let mut restored = HashSet::new();
+        for fix in &repairs {
+            match fix {
+                Repair::RemoveReceipt { tool, version, .. } => {
+                    for receipt in lockfile.tool_receipts() {
+                        if &receipt.name == tool && &receipt.resolved_version == version {
+                            remove_tool_links(receipt);
+                        }
+                    }
+                    lockfile.retain_tool_receipts(|receipt| {
+                        !(&receipt.name == tool && &receipt.resolved_version == version)
+                    });
+                }
+                Repair::Restore { tool, version, .. } => {
+                    let asset = lockfile
+                        .tool_receipts()
+                        .find(|receipt| {
+                            &receipt.name == tool && &receipt.resolved_version == version
+                        })
+                        .and_then(|receipt| receipt.asset.as_ref())
+                        .context("Receipt to restore is missing its asset record")?;
+                    let definition = &tools.entries()[tool].definition;
+                    installers::restore_contents(
+                        definition,
+                        asset,
+                        context.as_ref().expect("context is prepared for restores"),
+                    )
+                    .with_context(|| format!("Failed to restore '{}' {}", tool, version))?;
+                    restored.insert((tool.clone(), version.clone()));
+                }
+                Repair::Relink {
+                    tool,
+                    source,
+                    target,
+                    wrapper,
+                    ..
+                } => {
+                    installers::relink(source, target, tool.as_deref().zip(wrapper.as_ref()))?;
+                }
+            }
+        }
+
+        for receipt in lockfile.tool_receipts_mut() {
+            if restored.contains(&(receipt.name.clone(), receipt.resolved_version.clone())) {
+                receipt.status = integrity::verify_receipt(receipt).status;
+            }
+        }
+        lockfile.save(&self.path(WorkspacePath::Lockfile))?;
+
+        Ok(repair::plan(&repairs, self.active_profile_name()))
+    }
+
     /// Reset workspace state and active profile repository.
     pub fn reset(&self, force: bool) -> Result<()> {
         let profile_path = self.path(WorkspacePath::Profile);
@@ -1136,17 +2272,42 @@ impl Workspace {
             return Ok(());
         }
 
-        let lockfile = Lockfile::load(&lockfile_path)?;
+        let mut lockfile = Lockfile::load(&lockfile_path)?;
         self.remove_tracked_symlinks(&lockfile)?;
 
+        // Backups live in the state directory, so they must be back in place before it goes.
+        restore_recorded_backups(&mut lockfile);
+        if !lockfile.backups.is_empty() {
+            lockfile.save(&lockfile_path)?;
+            anyhow::bail!(
+                "{} backup(s) could not be restored; move them back manually from {:?} and retry",
+                lockfile.backups.len(),
+                self.path(WorkspacePath::Backups)
+            );
+        }
+
         // Remove lockfile
         fs::remove_file(&lockfile_path)
             .with_context(|| format!("Failed to remove lockfile {:?}", lockfile_path))?;
 
+        // The process lock stays: this process holds it, and a fresh file would let another dws
+        // lock it while the reset that called us is still reinstalling.
         if self.state_dir.exists() {
-            fs::remove_dir_all(&self.state_dir).with_context(|| {
-                format!("Failed to remove state directory {:?}", self.state_dir)
-            })?;
+            let process_lock = self.path(WorkspacePath::ProcessLock);
+            for entry in fs::read_dir(&self.state_dir)
+                .with_context(|| format!("Failed to read state directory {:?}", self.state_dir))?
+            {
+                let path = entry?.path();
+                if path == process_lock {
+                    continue;
+                }
+                let removed = if path.is_dir() && !path.is_symlink() {
+                    fs::remove_dir_all(&path)
+                } else {
+                    fs::remove_file(&path)
+                };
+                removed.with_context(|| format!("Failed to remove {:?}", path))?;
+            }
         }
 
         if self.cache_dir.exists() {
@@ -1158,19 +2319,104 @@ impl Workspace {
         Ok(())
     }
 
-    /// Remove all symlinks tracked in the lockfile
-    fn remove_tracked_symlinks(&self, lockfile: &Lockfile) -> Result<()> {
-        // Remove config symlinks
-        for entry in lockfile.config_symlinks() {
-            if entry.target.exists() || entry.target.symlink_metadata().is_ok() {
-                fs::remove_file(&entry.target).with_context(|| {
-                    format!("Failed to remove config symlink {:?}", entry.target)
-                })?;
+    /// Link every entry of one profile dotfile directory and record it in `lockfile`.
+    fn install_dotfiles(
+        &self,
+        dotfiles: &Dotfiles,
+        backup_dir: &Path,
+        lockfile: &mut Lockfile,
+    ) -> Result<()> {
+        for entry in dotfiles.discover_entries()? {
+            if self.is_reserved_target(&entry.target) {
+                ui::warn(format!(
+                    "Skipping {}: it would replace a dws directory",
+                    entry.target.display()
+                ));
+                continue;
             }
-        }
-
-        // Remove tool binary symlinks
-        for receipt in lockfile.tool_receipts() {
+            if entry.is_secret() && !self.path(WorkspacePath::Identity).exists() {
+                ui::warn(format!(
+                    "Skipping secret {}: no age identity at {}",
+                    entry.target.display(),
+                    self.path(WorkspacePath::Identity).display()
+                ));
+                continue;
+            }
+            match dotfiles.install_entry(&entry, backup_dir)? {
+                InstallOutcome::Skipped => {
+                    ui::warn(format!(
+                        "Skipping {}: it already exists and is not managed by dws",
+                        entry.target.display()
+                    ));
+                    continue;
+                }
+                InstallOutcome::BackedUp(backup) => {
+                    ui::info(format!(
+                        "Backed up {} to {}",
+                        entry.target.display(),
+                        backup.display()
+                    ));
+                    lockfile.add_backup(entry.target.clone(), backup);
+                }
+                InstallOutcome::Adopted => ui::info(format!(
+                    "Adopted {} into the profile (review with git diff)",
+                    entry.target.display()
+                )),
+                InstallOutcome::Overwritten => {
+                    ui::warn(format!("Replaced existing {}", entry.target.display()))
+                }
+                InstallOutcome::Linked => {}
+            }
+            let recorded = if entry.is_rendered() {
+                let digest = file_digest(&entry.target)
+                    .with_context(|| format!("Failed to read rendered file {:?}", entry.target))?;
+                lockfile.add_rendered_config(entry.source.clone(), entry.target.clone(), digest)
+            } else {
+                lockfile.add_config_symlink(entry.source.clone(), entry.target.clone())
+            };
+            recorded.overlay = entry.overlay.clone();
+        }
+        Ok(())
+    }
+
+    /// Move files displaced by the `backup` conflict policy back to their original location.
+    ///
+    /// Returns the restored paths. Backups whose original location is occupied by something
+    /// other than a dws link stay recorded so they can be restored once it is cleared.
+    pub fn restore_backups(&self) -> Result<Vec<PathBuf>> {
+        let lockfile_path = self.path(WorkspacePath::Lockfile);
+        if !lockfile_path.exists() {
+            return Ok(Vec::new());
+        }
+
+        let mut lockfile = Lockfile::load(&lockfile_path)?;
+        let restored = restore_recorded_backups(&mut lockfile);
+        lockfile
+            .config_symlinks
+            .retain(|entry| !restored.contains(&entry.target));
+        lockfile.save(&lockfile_path)?;
+
+        // Drop the timestamped directories that are now empty.
+        let backups_dir = self.path(WorkspacePath::Backups);
+        if let Ok(entries) = fs::read_dir(&backups_dir) {
+            for entry in entries.flatten() {
+                remove_empty_dirs(&entry.path());
+            }
+        }
+
+        Ok(restored)
+    }
+
+    /// Remove all symlinks tracked in the lockfile
+    fn remove_tracked_symlinks(&self, lockfile: &Lockfile) -> Result<()> {
+        // Remove config symlinks
+        for entry in lockfile.config_symlinks() {
+            remove_config_entry(entry)
+                .with_context(|| format!("Failed to remove config symlink {:?}", entry.target))?;
+        }
+
+        // Remove tool binary symlinks
+        for receipt in lockfile.tool_receipts() {
             for bin in &receipt.binaries {
                 if bin.target.exists() || bin.target.symlink_metadata().is_ok() {
                     fs::remove_file(&bin.target).with_context(|| {
@@ -1191,160 +2437,350 @@ impl Workspace {
     }
 
     /// Remove cached tool versions that no longer have symlinks tracked in the lockfile.
+    fn prune_unused_cache(&self, lockfile: &Lockfile) -> Result<()> {
+        for version_path in self.unused_cache_dirs(lockfile)? {
+            fs::remove_dir_all(&version_path)
+                .with_context(|| format!("Failed to remove cached tool at {:?}", version_path))?;
+            let Some(tool_path) = version_path.parent() else {
+                continue;
+            };
+            if tool_path.read_dir()?.next().is_none() {
+                fs::remove_dir(tool_path).with_context(|| {
+                    format!("Failed to remove empty cache directory {:?}", tool_path)
+                })?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Cached tool versions that no receipt references.
     ///
     /// The cache is organised as $XDG_CACHE_HOME/dws/tools/<tool>/<version>. The lockfile stores the
-    /// fully qualified path to the version directory. Anything not referenced gets pruned.
-    fn prune_unused_cache(&self, lockfile: &Lockfile) -> Result<()> {
+    /// fully qualified path to the version directory. Anything not referenced is unused.
+    fn unused_cache_dirs(&self, lockfile: &Lockfile) -> Result<Vec<PathBuf>> {
         let cache_dir = self.path(WorkspacePath::Cache);
         let tools_dir = cache_dir.join("tools");
         if !tools_dir.exists() {
-            return Ok(());
+            return Ok(Vec::new());
         }
 
-        let mut in_use: HashSet<PathBuf> = HashSet::new();
-        for receipt in lockfile.tool_receipts() {
-            for bin in &receipt.binaries {
-                let mut current = bin.source.parent();
-                while let Some(dir) = current {
-                    if !dir.starts_with(&tools_dir) {
-                        break;
-                    }
-
-                    in_use.insert(dir.to_path_buf());
-
-                    if dir == tools_dir {
-                        break;
-                    }
+        // Project environments share the cache, so their receipts keep versions alive too.
+        let project_lockfiles = self.project_lockfiles()?;
+        let receipts = lockfile
+            .tool_receipts()
+            .chain(project_lockfiles.iter().flat_map(Lockfile::tool_receipts));
 
-                    current = dir.parent();
+        let mut in_use: HashSet<PathBuf> = HashSet::new();
+        let mut mark = |path: &Path| {
+            let mut current = Some(path);
+            while let Some(dir) = current {
+                if !dir.starts_with(&tools_dir) || dir == tools_dir {
+                    break;
                 }
+                in_use.insert(dir.to_path_buf());
+                current = dir.parent();
             }
-
-            for tool_entry in fs::read_dir(&tools_dir)
-                .with_context(|| format!("Failed to read cache directory {:?}", tools_dir))?
-            {
-                let tool_entry = tool_entry?;
-                let tool_path = tool_entry.path();
-                if !tool_path.is_dir() {
-                    continue;
-                }
-
-                for version_entry in fs::read_dir(&tool_path)? {
-                    let version_entry = version_entry?;
-                    let version_path = version_entry.path();
-                    if !version_path.is_dir() {
-                        continue;
-                    }
-
-                    if !in_use.contains(&version_path) {
-                        fs::remove_dir_all(&version_path).with_context(|| {
-                            format!("Failed to remove cached tool at {:?}", version_path)
-                        })?;
-                    }
-                }
-
-                if !tool_path.exists() {
-                    continue;
-                }
-
-                if tool_path.read_dir()?.next().is_none() {
-                    fs::remove_dir(&tool_path).with_context(|| {
-                        format!("Failed to remove empty cache directory {:?}", tool_path)
-                    })?;
+        };
+        for receipt in receipts {
+            for bin in &receipt.binaries {
+                if let Some(parent) = bin.source.parent() {
+                    mark(parent);
                 }
             }
-
             if let Some(asset) = &receipt.asset {
                 if let Some(parent) = asset.archive_path.parent() {
-                    let mut current = Some(parent);
-                    while let Some(dir) = current {
-                        if !dir.starts_with(&tools_dir) {
-                            break;
-                        }
-                        in_use.insert(dir.to_path_buf());
-                        if dir == tools_dir {
-                            break;
-                        }
-                        current = dir.parent();
-                    }
+                    mark(parent);
                 }
+                mark(&asset.extract_dir);
+            }
+        }
 
-                let mut current = Some(asset.extract_dir.as_path());
-                while let Some(dir) = current {
-                    if !dir.starts_with(&tools_dir) {
-                        break;
-                    }
-                    in_use.insert(dir.to_path_buf());
-                    if dir == tools_dir {
-                        break;
-                    }
-                    current = dir.parent();
+        let mut unused = Vec::new();
+        for tool_entry in fs::read_dir(&tools_dir)
+            .with_context(|| format!("Failed to read cache directory {:?}", tools_dir))?
+        {
+            let tool_path = tool_entry?.path();
+            if !tool_path.is_dir() {
+                continue;
+            }
+
+            for version_entry in fs::read_dir(&tool_path)? {
+                let version_path = version_entry?.path();
+                if version_path.is_dir() && !in_use.contains(&version_path) {
+                    unused.push(version_path);
                 }
             }
         }
+        unused.sort();
+        Ok(unused)
+    }
+
+    /// Garbage-collect downloads in the blob store that no receipt references.
+    fn prune_unused_blobs(&self, lockfile: &Lockfile) -> Result<()> {
+        let store = BlobStore::new(self.path(WorkspacePath::Blobs));
+        store.collect_garbage(&self.blob_references(lockfile)?)?;
         Ok(())
     }
 
-    /// Remove stale symlinks from $XDG_STATE_HOME/dws/bin when they are no longer listed in the
-    /// lockfile. Only symlinks are touched; any user-managed files remain untouched.
+    /// Reference counts for the blob store.
+    ///
+    /// Blobs are keyed by the SHA256 recorded on each receipt's asset, so the reference count is
+    /// the number of receipts pointing at a digest.
+    fn blob_references(&self, lockfile: &Lockfile) -> Result<HashMap<String, usize>> {
+        let project_lockfiles = self.project_lockfiles()?;
+        let receipts = lockfile
+            .tool_receipts()
+            .chain(project_lockfiles.iter().flat_map(Lockfile::tool_receipts));
+
+        let mut references: HashMap<String, usize> = HashMap::new();
+        for receipt in receipts {
+            if let Some(asset) = &receipt.asset {
+                *references
+                    .entry(asset.checksum.to_ascii_lowercase())
+                    .or_default() += 1;
+            }
+        }
+        Ok(references)
+    }
+
+    /// Remove stale symlinks and wrappers from $XDG_STATE_HOME/dws/bin when they are no longer
+    /// listed in the lockfile. Other files are left untouched.
     fn prune_unused_bin(&self, lockfile: &Lockfile) -> Result<()> {
-        let bin_dir = self.path(WorkspacePath::Bin);
-        if !bin_dir.exists() {
-            return Ok(());
+        prune_bin_dir(&self.path(WorkspacePath::Bin), lockfile)
+    }
+}
+
+/// Remove symlinks and generated wrappers in `bin_dir` that `lockfile` no longer lists.
+fn prune_bin_dir(bin_dir: &Path, lockfile: &Lockfile) -> Result<()> {
+    if !bin_dir.exists() {
+        return Ok(());
+    }
+
+    let mut valid: HashSet<PathBuf> = HashSet::new();
+    for receipt in lockfile.tool_receipts() {
+        for bin in &receipt.binaries {
+            valid.insert(bin.target.clone());
         }
+    }
 
-        let mut valid: HashSet<PathBuf> = HashSet::new();
-        for receipt in lockfile.tool_receipts() {
-            for bin in &receipt.binaries {
-                valid.insert(bin.target.clone());
-            }
+    for entry in fs::read_dir(bin_dir)
+        .with_context(|| format!("Failed to read bin directory {:?}", bin_dir))?
+    {
+        let entry = entry?;
+        let target = entry.path();
+
+        if valid.contains(&target) {
+            continue;
         }
 
-        for entry in fs::read_dir(&bin_dir)
-            .with_context(|| format!("Failed to read bin directory {:?}", bin_dir))?
-        {
-            let entry = entry?;
-            let target = entry.path();
+        let is_symlink = target
+            .symlink_metadata()
+            .map(|metadata| metadata.file_type().is_symlink())
+            .unwrap_or(false);
+        let is_stale_wrapper = !is_symlink
+            && fs::read_to_string(&target)
+                .map(|script| installers::wrapper::is_wrapper(&script))
+                .unwrap_or(false);
+
+        if is_symlink || is_stale_wrapper {
+            fs::remove_file(&target)
+                .with_context(|| format!("Failed to remove stale binary symlink {:?}", target))?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Make `path` absolute, resolving symlinks in its parent but not in the final component.
+fn resolve_parent(path: &Path) -> Result<PathBuf> {
+    let name = path
+        .file_name()
+        .with_context(|| format!("{:?} does not name a file or directory", path))?;
+    let parent = match path.parent() {
+        Some(parent) if !parent.as_os_str().is_empty() => parent,
+        _ => Path::new("."),
+    };
+    let parent = parent
+        .canonicalize()
+        .with_context(|| format!("Failed to resolve {:?}", parent))?;
+    Ok(parent.join(name))
+}
+
+/// Reject profile paths that are absolute or climb out of the profile.
+fn check_profile_relative(relative: &Path) -> Result<()> {
+    let escapes = relative.is_absolute()
+        || relative
+            .components()
+            .any(|component| matches!(component, std::path::Component::ParentDir));
+    if escapes || relative.as_os_str().is_empty() {
+        anyhow::bail!("--as must be a path inside the profile, got {:?}", relative);
+    }
+    Ok(())
+}
+
+/// Remove an installed config entry.
+///
+/// Rendered templates are only removed while they still match the recorded digest, so manual
+/// edits survive; the next install treats such a file as a conflict.
+fn remove_config_entry(entry: &SymlinkEntry) -> io::Result<()> {
+    if entry.target.symlink_metadata().is_err() {
+        return Ok(());
+    }
+    if edited_since_install(entry) {
+        ui::warn(format!(
+            "Keeping {}: it was edited after dws rendered it",
+            entry.target.display()
+        ));
+        return Ok(());
+    }
+    fs::remove_file(&entry.target)
+}
 
-            if valid.contains(&target) {
+/// Whether a rendered entry's file no longer matches the digest recorded when dws wrote it.
+fn edited_since_install(entry: &SymlinkEntry) -> bool {
+    entry
+        .rendered
+        .as_ref()
+        .is_some_and(|digest| file_digest(&entry.target).as_ref() != Some(digest))
+}
+
+/// Move recorded backups back over their dws links, dropping the restored records.
+///
+/// Newer backups are restored first, so when the same path was backed up twice the older copy
+/// stays recorded (its original location is occupied again) instead of being clobbered.
+fn restore_recorded_backups(lockfile: &mut Lockfile) -> Vec<PathBuf> {
+    let installed: HashMap<PathBuf, Option<String>> = lockfile
+        .config_symlinks()
+        .map(|entry| (entry.target.clone(), entry.rendered.clone()))
+        .collect();
+    let mut restored = Vec::new();
+    let mut remaining: Vec<BackupEntry> = Vec::new();
+
+    for entry in std::mem::take(&mut lockfile.backups).into_iter().rev() {
+        if entry.backup.symlink_metadata().is_err() {
+            ui::warn(format!(
+                "Backup {} is missing; forgetting it",
+                entry.backup.display()
+            ));
+            continue;
+        }
+        if let Ok(metadata) = entry.original.symlink_metadata() {
+            let ours = match installed.get(&entry.original) {
+                Some(Some(digest)) => file_digest(&entry.original).as_ref() == Some(digest),
+                Some(None) => metadata.is_symlink(),
+                None => false,
+            };
+            if !ours || fs::remove_file(&entry.original).is_err() {
+                ui::warn(format!(
+                    "Not restoring {}: it is in use (backup kept at {})",
+                    entry.original.display(),
+                    entry.backup.display()
+                ));
+                remaining.push(entry);
                 continue;
             }
+        }
+        match move_path(&entry.backup, &entry.original) {
+            Ok(()) => restored.push(entry.original),
+            Err(err) => {
+                ui::warn(format!("{err:#}"));
+                remaining.push(entry);
+            }
+        }
+    }
 
-            if target
-                .symlink_metadata()
-                .map(|metadata| metadata.file_type().is_symlink())
-                .unwrap_or(false)
-            {
-                fs::remove_file(&target).with_context(|| {
-                    format!("Failed to remove stale binary symlink {:?}", target)
-                })?;
+    remaining.reverse();
+    lockfile.backups = remaining;
+    restored
+}
+
+/// Remove `dir` and any subdirectories left empty after restoring backups.
+fn remove_empty_dirs(dir: &Path) {
+    if let Ok(entries) = fs::read_dir(dir) {
+        for entry in entries.flatten() {
+            if entry.file_type().map(|kind| kind.is_dir()).unwrap_or(false) {
+                remove_empty_dirs(&entry.path());
             }
         }
+    }
+    let _ = fs::remove_dir(dir);
+}
+
+/// Set aside the binary and extra links of `receipt`.
+fn set_aside_tool_links(transaction: &mut Transaction, receipt: &ToolReceipt) -> Result<()> {
+    for bin in &receipt.binaries {
+        transaction.set_aside(&bin.target)?;
+    }
+    for extra in &receipt.extras {
+        transaction.set_aside(&extra.target)?;
+    }
+    Ok(())
+}
+
+/// Best-effort removal of the binary and extra links of `receipt`.
+fn remove_tool_links(receipt: &ToolReceipt) {
+    for target in receipt
+        .binaries
+        .iter()
+        .map(|bin| &bin.target)
+        .chain(receipt.extras.iter().map(|extra| &extra.target))
+    {
+        if target.symlink_metadata().is_ok() {
+            let _ = fs::remove_file(target);
+        }
+    }
+}
 
-        Ok(())
+/// Interpolate the profile-level `[env]` table so `dws env` can read it from the lockfile.
+fn resolve_profile_env(tools: &ToolSet) -> Result<BTreeMap<String, EnvValue>> {
+    tools
+        .env()
+        .iter()
+        .map(|(name, value)| {
+            let value = value
+                .interpolate(None)
+                .with_context(|| format!("Failed to resolve [env] variable `{name}`"))?;
+            Ok((name.clone(), value))
+        })
+        .collect()
+}
+
+/// Remove a cached archive, and the blob it shares storage with, after it failed verification.
+fn discard_tampered_archive(
+    store: &BlobStore,
+    receipt: &ToolReceipt,
+    archive: &Path,
+) -> Result<()> {
+    fs::remove_file(archive)
+        .with_context(|| format!("Failed to remove tampered archive {:?}", archive))?;
+    if let Some(asset) = &receipt.asset {
+        let blob = store.path_for(asset.checksum.trim_start_matches("sha256:"));
+        if blob.is_file() {
+            fs::remove_file(&blob)
+                .with_context(|| format!("Failed to remove tampered blob {:?}", blob))?;
+        }
     }
+    Ok(())
 }
 
 fn receipt_missing_artifacts(receipt: &ToolReceipt) -> bool {
+    if receipt.status.is_damaged() {
+        return true;
+    }
+
     let binaries_missing = receipt.binaries.iter().any(|bin| {
-        !bin.source.exists()
-            || !bin.target.exists()
-            || bin
-                .target
-                .symlink_metadata()
-                .map(|meta| !meta.file_type().is_symlink())
-                .unwrap_or(true)
+        let state = if bin.wrapper.is_some() {
+            check_wrapper(&bin.source, &bin.target)
+        } else {
+            check_symlink(&bin.source, &bin.target)
+        };
+        !matches!(state, LinkState::Ok)
     });
 
-    let extras_missing = receipt.extras.iter().any(|extra| {
-        !extra.source.exists()
-            || !extra.target.exists()
-            || extra
-                .target
-                .symlink_metadata()
-                .map(|meta| !meta.file_type().is_symlink())
-                .unwrap_or(true)
-    });
+    let extras_missing = receipt
+        .extras
+        .iter()
+        .any(|extra| !matches!(check_symlink(&extra.source, &extra.target), LinkState::Ok));
 
     let asset_missing = receipt
         .asset
@@ -1398,196 +2834,954 @@ fn ensure_clean_worktree(repo: &Repository) -> Result<()> {
         dirty_paths.push("…".to_string());
     }
 
-    anyhow::bail!(
-        "Profile repository has uncommitted changes: {}.\nRe-run with --force to discard local modifications.",
-        dirty_paths.join(", ")
-    );
-}
-
-fn confirm_reset() -> Result<bool> {
-    print!("This will reinstall all tools and dotfiles. Continue? [y/N]: ");
-    io::stdout().flush().context("Failed to flush stdout")?;
+    anyhow::bail!(
+        "Profile repository has uncommitted changes: {}.\nRe-run with --force to discard local modifications.",
+        dirty_paths.join(", ")
+    );
+}
+
+fn confirm_reset() -> Result<bool> {
+    print!("This will reinstall all tools and dotfiles. Continue? [y/N]: ");
+    io::stdout().flush().context("Failed to flush stdout")?;
+
+    let mut input = String::new();
+    io::stdin()
+        .read_line(&mut input)
+        .context("Failed to read confirmation")?;
+
+    let trimmed = input.trim().to_ascii_lowercase();
+    Ok(trimmed == "y" || trimmed == "yes")
+}
+
+fn reset_repository(repo: Repository) -> Result<()> {
+    if let Err(err) = fetch_remote(&repo) {
+        ui::warn(format!("Failed to fetch 'origin': {err}"));
+    }
+
+    let target = determine_reset_target(&repo)?;
+    repo.reset(&target, ResetType::Hard, None)
+        .context("Failed to perform hard reset")?;
+
+    let mut checkout = CheckoutBuilder::new();
+    checkout.force().remove_untracked(true).remove_ignored(true);
+
+    repo.checkout_head(Some(&mut checkout))
+        .context("Failed to clean working tree")?;
+
+    let _ = repo.cleanup_state();
+    Ok(())
+}
+
+fn fetch_remote(repo: &Repository) -> Result<()> {
+    let mut remote = repo
+        .find_remote("origin")
+        .context("Repository has no 'origin' remote")?;
+
+    remote.fetch::<&str>(&[], None, None)?;
+    Ok(())
+}
+
+fn determine_reset_target(repo: &Repository) -> Result<Object<'_>> {
+    let head = repo.head().context("Repository has no HEAD reference")?;
+
+    if head.is_branch() {
+        if let Some(branch) = head.shorthand() {
+            let remote_ref = format!("refs/remotes/origin/{}", branch);
+            if let Ok(obj) = repo.revparse_single(&remote_ref) {
+                return Ok(obj);
+            }
+        }
+    }
+
+    head.peel(ObjectType::Commit)
+        .context("Failed to resolve HEAD commit")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::default_profile_name;
+
+    use crate::installers::{GithubAsset, GithubRelease};
+    use crate::lockfile::{AssetRecord, BinaryLink, ExtraLink, ReceiptStatus, ToolReceipt};
+    use crate::toolset::InstallerKind;
+    use flate2::write::GzEncoder;
+    use flate2::Compression;
+    use rstest::rstest;
+    use serial_test::serial;
+    use sha2::{Digest, Sha256};
+    use tempfile::TempDir;
+
+    fn setup_test_env() -> TempDir {
+        let temp = TempDir::new().unwrap();
+        env::set_var("XDG_CONFIG_HOME", temp.path());
+        env::set_var("XDG_STATE_HOME", temp.path().join("state"));
+        env::set_var("XDG_CACHE_HOME", temp.path().join("cache"));
+        env::set_var("XDG_DATA_HOME", temp.path().join("data"));
+        env::set_var("HOME", temp.path());
+        temp
+    }
+
+    #[test]
+    #[serial]
+    fn test_workspace_new() {
+        let _temp = setup_test_env();
+        let workspace = Workspace::new().unwrap();
+
+        assert!(workspace
+            .path(WorkspacePath::Root)
+            .to_string_lossy()
+            .contains("dws"));
+        assert!(workspace
+            .path(WorkspacePath::Profile)
+            .to_string_lossy()
+            .contains("profiles"));
+        assert_eq!(workspace.active_profile_name(), default_profile_name());
+    }
+
+    #[test]
+    #[serial]
+    fn test_workspace_paths() {
+        let _temp = setup_test_env();
+        let workspace = Workspace::new().unwrap();
+
+        assert!(workspace
+            .path(WorkspacePath::Profiles)
+            .to_string_lossy()
+            .contains("profiles"));
+        assert!(workspace
+            .path(WorkspacePath::Config)
+            .to_string_lossy()
+            .contains("config"));
+        assert!(workspace
+            .path(WorkspacePath::ProfileConfig)
+            .to_string_lossy()
+            .ends_with("dws.toml"));
+        assert!(workspace
+            .path(WorkspacePath::Share)
+            .to_string_lossy()
+            .contains("share"));
+        assert!(workspace
+            .path(WorkspacePath::Cache)
+            .to_string_lossy()
+            .contains("cache"));
+        assert!(workspace
+            .path(WorkspacePath::Lockfile)
+            .to_string_lossy()
+            .contains("dws.lock"));
+        assert!(workspace
+            .path(WorkspacePath::Bin)
+            .to_string_lossy()
+            .contains("bin"));
+    }
+
+    #[test]
+    #[serial]
+    fn test_workspace_tools() {
+        let _temp = setup_test_env();
+        let workspace = Workspace::new().unwrap();
+
+        let profile_config = workspace.path(WorkspacePath::ProfileConfig);
+        if let Some(parent) = profile_config.parent() {
+            fs::create_dir_all(parent).unwrap();
+        }
+        fs::write(
+            &profile_config,
+            r#"
+[tools.ripgrep]
+installer = "curl"
+project = "BurntSushi/ripgrep"
+"#,
+        )
+        .unwrap();
+
+        let tools = workspace.tools().unwrap();
+        assert_eq!(tools.len(), 1);
+        let (name, entry) = tools.iter().next().unwrap();
+        assert_eq!(name, "ripgrep");
+        assert_eq!(entry.definition.installer, InstallerKind::Curl);
+    }
+
+    #[test]
+    #[serial]
+    fn test_workspace_install() {
+        let _temp = setup_test_env();
+        let workspace = Workspace::new().unwrap();
+
+        // Create workspace structure
+        let config_dir = workspace.path(WorkspacePath::Config);
+        fs::create_dir_all(&config_dir).unwrap();
+        fs::create_dir_all(config_dir.join("zsh")).unwrap();
+        fs::write(config_dir.join("zsh/.zshrc"), "test").unwrap();
+
+        // Install
+        workspace.install().unwrap();
+
+        // Verify lockfile was created
+        assert!(workspace.path(WorkspacePath::Lockfile).exists());
+        assert!(workspace.path(WorkspacePath::Bin).exists());
+        assert!(workspace.path(WorkspacePath::Share).join("man").exists());
+        assert!(workspace
+            .path(WorkspacePath::Share)
+            .join("zsh/site-functions")
+            .exists());
+
+        // Verify lockfile contents
+        let lockfile = Lockfile::load(&workspace.path(WorkspacePath::Lockfile)).unwrap();
+        assert_eq!(lockfile.config_symlinks.len(), 1);
+    }
+
+    #[test]
+    #[serial]
+    fn test_workspace_install_links_home_data_and_state_dirs() {
+        let temp = setup_test_env();
+        let home = temp.path().join("home");
+        env::set_var("HOME", &home);
+        let workspace = Workspace::new().unwrap();
+
+        let home_dir = workspace.path(WorkspacePath::Home);
+        fs::create_dir_all(home_dir.join(".ssh")).unwrap();
+        fs::write(home_dir.join(".gitconfig"), "[user]").unwrap();
+        fs::write(home_dir.join(".ssh/config"), "Host *").unwrap();
+        fs::create_dir_all(workspace.path(WorkspacePath::Data).join("fonts")).unwrap();
+        fs::create_dir_all(workspace.path(WorkspacePath::State)).unwrap();
+        fs::write(workspace.path(WorkspacePath::State).join("history"), "").unwrap();
+        // Would shadow the dws state directory.
+        fs::create_dir_all(workspace.path(WorkspacePath::State).join("dws")).unwrap();
+
+        workspace.install().unwrap();
+
+        assert_eq!(
+            fs::read_link(home.join(".gitconfig")).unwrap(),
+            home_dir.join(".gitconfig")
+        );
+        assert_eq!(
+            fs::read_link(home.join(".ssh")).unwrap(),
+            home_dir.join(".ssh")
+        );
+        assert!(fs::read_link(temp.path().join("data/fonts")).is_ok());
+        assert!(fs::read_link(temp.path().join("state/history")).is_ok());
+        assert!(!fs::symlink_metadata(temp.path().join("state/dws"))
+            .unwrap()
+            .is_symlink());
+
+        let lockfile = Lockfile::load(&workspace.path(WorkspacePath::Lockfile)).unwrap();
+        assert_eq!(lockfile.config_symlinks().count(), 4);
+    }
+
+    #[test]
+    #[serial]
+    fn test_workspace_install_renders_templates() {
+        let _temp = setup_test_env();
+        fs::create_dir_all(env::var("XDG_CONFIG_HOME").unwrap() + "/dws").unwrap();
+        fs::write(
+            PathBuf::from(env::var("XDG_CONFIG_HOME").unwrap()).join("dws/config.toml"),
+            "[vars]\nemail = \"work@example.com\"\n",
+        )
+        .unwrap();
+        let workspace = Workspace::new().unwrap();
+        let profile_config = workspace.path(WorkspacePath::ProfileConfig);
+        fs::create_dir_all(profile_config.parent().unwrap()).unwrap();
+        fs::write(
+            &profile_config,
+            "[vars]\nemail = \"home@example.com\"\neditor = \"nvim\"\n",
+        )
+        .unwrap();
+
+        let config_dir = workspace.path(WorkspacePath::Config);
+        fs::create_dir_all(config_dir.join("git")).unwrap();
+        fs::write(
+            config_dir.join("git/config.tmpl"),
+            "email = {{ vars.email }}\neditor = {{ vars.editor }}\nprofile = {{ profile }}\n",
+        )
+        .unwrap();
+
+        workspace.install().unwrap();
+        let target = workspace.config_home.join("git/config");
+        assert_eq!(
+            fs::read_to_string(&target).unwrap(),
+            "email = work@example.com\neditor = nvim\nprofile = default\n"
+        );
+        let lockfile = Lockfile::load(&workspace.path(WorkspacePath::Lockfile)).unwrap();
+        let entry = lockfile.config_symlinks().next().unwrap();
+        assert_eq!(entry.rendered, file_digest(&target));
+
+        // A manual edit is kept as a backup rather than silently re-rendered away.
+        fs::write(&target, "edited").unwrap();
+        workspace.install().unwrap();
+        let lockfile = Lockfile::load(&workspace.path(WorkspacePath::Lockfile)).unwrap();
+        assert_eq!(lockfile.backups.len(), 1);
+        assert_eq!(
+            fs::read_to_string(&lockfile.backups[0].backup).unwrap(),
+            "edited"
+        );
+        assert!(fs::read_to_string(&target).unwrap().starts_with("email"));
+    }
+
+    #[test]
+    #[serial]
+    fn test_workspace_install_decrypts_secrets() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let temp = setup_test_env();
+        let workspace = Workspace::new().unwrap();
+        let config_dir = workspace.path(WorkspacePath::Config);
+        fs::create_dir_all(config_dir.join("gh")).unwrap();
+        fs::write(config_dir.join("gh/config.yml"), "editor: nvim").unwrap();
+
+        let plaintext = temp.path().join(".netrc");
+        fs::write(&plaintext, "machine example.com password hunter2").unwrap();
+        let (secret, generated) = workspace
+            .add_secret(&plaintext, Some(Path::new("home/.netrc")))
+            .unwrap();
+        assert!(generated);
+        assert_eq!(
+            secret,
+            workspace.path(WorkspacePath::Home).join(".netrc.age")
+        );
+        assert!(workspace
+            .add_secret(&plaintext, Some(Path::new("home/.netrc")))
+            .is_err());
+        assert!(workspace
+            .add_secret(&plaintext, Some(Path::new("../outside")))
+            .is_err());
+        fs::remove_file(&plaintext).unwrap();
+
+        let source = temp.path().join("hosts.yml");
+        fs::write(&source, "token: abc").unwrap();
+        let (hosts, generated) = workspace
+            .add_secret(&source, Some(Path::new("config/gh/hosts.yml")))
+            .unwrap();
+        assert!(!generated);
+        assert_eq!(hosts, config_dir.join("gh/hosts.yml.age"));
+
+        workspace.install().unwrap();
+        let netrc = workspace.home_dir.join(".netrc");
+        assert!(!netrc.is_symlink());
+        assert_eq!(
+            fs::read_to_string(&netrc).unwrap(),
+            "machine example.com password hunter2"
+        );
+        let mode = fs::metadata(&netrc).unwrap().permissions().mode();
+        assert_eq!(mode & 0o777, 0o600);
+
+        // A directory holding a secret is linked file by file.
+        let gh = workspace.config_home.join("gh");
+        assert!(!gh.is_symlink());
+        assert!(gh.join("config.yml").is_symlink());
+        assert_eq!(
+            fs::read_to_string(gh.join("hosts.yml")).unwrap(),
+            "token: abc"
+        );
+        let lockfile = Lockfile::load(&workspace.path(WorkspacePath::Lockfile)).unwrap();
+        assert_eq!(
+            lockfile
+                .config_symlinks()
+                .filter(|entry| entry.rendered.is_some())
+                .count(),
+            2
+        );
+
+        // Without the identity secrets are skipped instead of failing the install.
+        fs::remove_file(workspace.path(WorkspacePath::Identity)).unwrap();
+        fs::remove_file(&netrc).unwrap();
+        workspace.install().unwrap();
+        assert!(!netrc.exists());
+    }
+
+    #[test]
+    #[serial]
+    fn test_workspace_adopt_moves_into_profile_and_links_back() {
+        let temp = setup_test_env();
+        let workspace = Workspace::new().unwrap();
+        let profile = workspace.path(WorkspacePath::Profile);
+        fs::create_dir_all(&profile).unwrap();
+        let repo = git2::Repository::init(&profile).unwrap();
+
+        let foo = temp.path().join("foo");
+        fs::create_dir_all(&foo).unwrap();
+        fs::write(foo.join("settings.toml"), "theme = 'dark'").unwrap();
+        let source = workspace.adopt(&foo, None, true).unwrap();
+        assert_eq!(source, workspace.path(WorkspacePath::Config).join("foo"));
+        assert_eq!(fs::read_link(&foo).unwrap(), source);
+        assert!(repo
+            .index()
+            .unwrap()
+            .get_path(Path::new("config/foo/settings.toml"), 0)
+            .is_some());
+        assert!(workspace.adopt(&foo, None, false).is_err());
+
+        // A single file keeps its real parent directory.
+        let bar = temp.path().join("bar");
+        fs::create_dir_all(&bar).unwrap();
+        fs::write(bar.join("local.conf"), "local").unwrap();
+        fs::write(bar.join("baz.conf"), "baz").unwrap();
+        workspace.adopt(&bar.join("baz.conf"), None, false).unwrap();
+        let lockfile = Lockfile::load(&workspace.path(WorkspacePath::Lockfile)).unwrap();
+        assert_eq!(lockfile.config_symlinks().count(), 2);
+
+        workspace.install().unwrap();
+        assert!(!bar.is_symlink());
+        assert!(bar.join("baz.conf").is_symlink());
+        assert_eq!(fs::read_to_string(bar.join("local.conf")).unwrap(), "local");
+        assert_eq!(fs::read_link(&foo).unwrap(), source);
+
+        // `--as` must still link to the adopted path.
+        let other = temp.path().join("other");
+        fs::write(&other, "other").unwrap();
+        assert!(workspace
+            .adopt(&other, Some(Path::new("data/other")), false)
+            .is_err());
+        assert!(workspace
+            .adopt(&other, Some(Path::new("../other")), false)
+            .is_err());
+        assert!(other.is_file());
+    }
+
+    #[test]
+    #[serial]
+    fn test_workspace_plan_leaves_old_lockfile_untouched() {
+        let temp = setup_test_env();
+        let workspace = Workspace::new().unwrap();
+        let config_dir = workspace.path(WorkspacePath::Config);
+        fs::create_dir_all(config_dir.join("git")).unwrap();
+        let lockfile_path = workspace.path(WorkspacePath::Lockfile);
+        fs::create_dir_all(lockfile_path.parent().unwrap()).unwrap();
+        let v1 = format!(
+            "version = 1\n\n[metadata]\ninstalled_at = \"2024-05-01T10:00:00Z\"\n\n\
+             [[config_symlinks]]\nsource = \"{}\"\ntarget = \"{}\"\n",
+            config_dir.join("git").display(),
+            temp.path().join("git").display()
+        );
+        fs::write(&lockfile_path, &v1).unwrap();
+
+        workspace.plan_install().unwrap();
+        assert_eq!(fs::read_to_string(&lockfile_path).unwrap(), v1);
+        assert!(!lockfile_path.with_file_name("dws.lock.v1.bak").exists());
+
+        // The first exclusive write upgrades the file and keeps the original.
+        workspace.install().unwrap();
+        assert_eq!(
+            fs::read_to_string(lockfile_path.with_file_name("dws.lock.v1.bak")).unwrap(),
+            v1
+        );
+    }
+
+    #[test]
+    #[serial]
+    fn test_workspace_plan_matches_install_without_touching_disk() {
+        let temp = setup_test_env();
+        let workspace = Workspace::new().unwrap();
+        let config_dir = workspace.path(WorkspacePath::Config);
+        fs::create_dir_all(config_dir.join("git")).unwrap();
+        fs::write(config_dir.join("git/config"), "profile").unwrap();
+        fs::create_dir_all(config_dir.join("nvim")).unwrap();
+        fs::write(config_dir.join("nvim/init.tmpl"), "-- {{ profile }}\n").unwrap();
+        fs::create_dir_all(temp.path().join("git")).unwrap();
+
+        let plan = workspace.plan_install().unwrap();
+        assert_eq!(
+            targets_of(&plan, ActionKind::Backup),
+            vec![temp.path().join("git").display().to_string()]
+        );
+        assert_eq!(
+            targets_of(&plan, ActionKind::Render),
+            vec![temp.path().join("nvim/init").display().to_string()]
+        );
+        assert!(temp.path().join("git").is_dir());
+        assert!(!temp.path().join("nvim").exists());
+        assert!(!workspace.path(WorkspacePath::Lockfile).exists());
+
+        workspace.install().unwrap();
+        assert!(workspace.plan_install().unwrap().is_empty());
+
+        // Dropping an entry from the profile unlinks it; a new one is linked.
+        fs::remove_dir_all(config_dir.join("git")).unwrap();
+        fs::create_dir_all(config_dir.join("helix")).unwrap();
+        let plan = workspace.plan_install().unwrap();
+        assert_eq!(
+            targets_of(&plan, ActionKind::Unlink),
+            vec![temp.path().join("git").display().to_string()]
+        );
+        assert_eq!(
+            targets_of(&plan, ActionKind::Link),
+            vec![temp.path().join("helix").display().to_string()]
+        );
+
+        // Switching profiles relinks shared targets and removes the rest.
+        let work = workspace.path(WorkspacePath::Profiles).join("work");
+        fs::create_dir_all(work.join("config/git")).unwrap();
+        let plan = workspace.plan_use("work").unwrap();
+        assert_eq!(targets_of(&plan, ActionKind::Activate), vec!["work"]);
+        assert_eq!(
+            targets_of(&plan, ActionKind::Link),
+            vec![temp.path().join("git").display().to_string()]
+        );
+        assert_eq!(
+            targets_of(&plan, ActionKind::Unlink),
+            vec![temp.path().join("nvim/init").display().to_string()]
+        );
+        assert_eq!(workspace.active_profile_name(), "default");
+        assert!(workspace.plan_use("missing").is_err());
+    }
+
+    fn targets_of(plan: &Plan, kind: ActionKind) -> Vec<String> {
+        plan.actions_of(kind)
+            .map(|action| action.target.clone())
+            .collect()
+    }
+
+    #[test]
+    #[serial]
+    fn test_workspace_install_backs_up_and_restores_conflicts() {
+        let _temp = setup_test_env();
+        let workspace = Workspace::new().unwrap();
+
+        let config_dir = workspace.path(WorkspacePath::Config);
+        fs::create_dir_all(config_dir.join("git")).unwrap();
+        fs::write(config_dir.join("git/config"), "profile").unwrap();
+        let target = workspace.config_home.join("git");
+        fs::create_dir_all(&target).unwrap();
+        fs::write(target.join("config"), "distro").unwrap();
+
+        workspace.install().unwrap();
+        assert_eq!(fs::read_link(&target).unwrap(), config_dir.join("git"));
+        let lockfile = Lockfile::load(&workspace.path(WorkspacePath::Lockfile)).unwrap();
+        assert_eq!(lockfile.backups.len(), 1);
+        assert_eq!(lockfile.backups[0].original, target);
+
+        // Reinstalling keeps the record and does not back up our own link.
+        workspace.install().unwrap();
+        let lockfile = Lockfile::load(&workspace.path(WorkspacePath::Lockfile)).unwrap();
+        assert_eq!(lockfile.backups.len(), 1);
+
+        assert_eq!(workspace.restore_backups().unwrap(), vec![target.clone()]);
+        assert_eq!(fs::read_to_string(target.join("config")).unwrap(), "distro");
+        let lockfile = Lockfile::load(&workspace.path(WorkspacePath::Lockfile)).unwrap();
+        assert!(lockfile.backups.is_empty());
+        assert_eq!(lockfile.config_symlinks().count(), 0);
+        assert_eq!(
+            fs::read_dir(workspace.path(WorkspacePath::Backups))
+                .unwrap()
+                .count(),
+            0
+        );
+    }
+
+    /// Serves a `tool.tar.gz` release per project; downloads of unknown projects fail.
+    struct LocalReleases {
+        archives: HashMap<String, Vec<u8>>,
+    }
+
+    impl GithubApi for LocalReleases {
+        fn fetch_release(&self, project: &str, tag: Option<&str>) -> Result<GithubRelease> {
+            Ok(GithubRelease {
+                id: 1,
+                tag_name: tag.unwrap_or("v1.0.0").to_string(),
+                name: None,
+                draft: false,
+                prerelease: false,
+                assets: vec![GithubAsset {
+                    id: 1,
+                    name: "tool.tar.gz".to_string(),
+                    content_type: None,
+                    browser_download_url: format!("https://example.invalid/{project}"),
+                    size: 0,
+                    state: None,
+                }],
+            })
+        }
+
+        fn download_asset(&self, url: &str, dest: &Path) -> Result<[u8; 32]> {
+            let project = url.trim_start_matches("https://example.invalid/");
+            let Some(bytes) = self.archives.get(project) else {
+                anyhow::bail!("connection refused while downloading {url}");
+            };
+            fs::write(dest, bytes)?;
+            Ok(Sha256::digest(bytes).into())
+        }
+    }
+
+    fn tool_archive(files: &[&str]) -> Vec<u8> {
+        let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+        {
+            let mut builder = tar::Builder::new(&mut encoder);
+            for file in files {
+                let mut header = tar::Header::new_gnu();
+                header.set_size(3);
+                header.set_mode(0o755);
+                header.set_cksum();
+                builder
+                    .append_data(&mut header, file, &b"sh\n"[..])
+                    .unwrap();
+            }
+            builder.finish().unwrap();
+        }
+        encoder.finish().unwrap()
+    }
+
+    /// `[tools.<name>]` entry linking `bin` from `owner/<name>`.
+    fn github_tool(name: &str, bin: &str, archive: Option<&[u8]>) -> String {
+        let digest: [u8; 32] = Sha256::digest(archive.unwrap_or_default()).into();
+        format!(
+            r#"
+[tools.{name}]
+installer = "github"
+project = "owner/{name}"
+version = "v1.0.0"
+asset_filter = ["tar.gz"]
+checksum = "sha256:{}"
+
+[[tools.{name}.bin]]
+source = "{bin}"
+"#,
+            hex::encode(digest)
+        )
+    }
+
+    /// A workspace with `git` config and a working `good` tool installed, plus the mock API.
+    fn installed_workspace() -> (Workspace, HashMap<String, Vec<u8>>) {
+        let mut workspace = Workspace::new().unwrap();
+        let good = tool_archive(&["good"]);
+        let archives = HashMap::from([
+            ("owner/good".to_string(), good.clone()),
+            ("owner/nobin".to_string(), tool_archive(&["other"])),
+            (
+                "owner/partial".to_string(),
+                tool_archive(&["partial", "partial.1"]),
+            ),
+        ]);
+        workspace.set_github_api(Arc::new(LocalReleases {
+            archives: archives.clone(),
+        }));
+
+        let config_dir = workspace.path(WorkspacePath::Config);
+        fs::create_dir_all(config_dir.join("git")).unwrap();
+        fs::write(config_dir.join("git/config"), "profile").unwrap();
+        fs::write(
+            workspace.path(WorkspacePath::ProfileConfig),
+            github_tool("good", "good", Some(&good)),
+        )
+        .unwrap();
+        workspace.install().unwrap();
+        (workspace, archives)
+    }
+
+    #[test]
+    #[serial]
+    fn test_workspace_up_to_date_wrapper_tool_is_left_alone() {
+        let _temp = setup_test_env();
+        let mut workspace = Workspace::new().unwrap();
+        let good = tool_archive(&["good"]);
+        workspace.set_github_api(Arc::new(LocalReleases {
+            archives: HashMap::from([("owner/good".to_string(), good.clone())]),
+        }));
+        let manifest = github_tool("good", "good", Some(&good))
+            .replace("version = \"v1.0.0\"\n", "")
+            + "wrapper = { args = [\"--stdio\"] }\n";
+        let profile_config = workspace.path(WorkspacePath::ProfileConfig);
+        fs::create_dir_all(profile_config.parent().unwrap()).unwrap();
+        fs::write(&profile_config, manifest).unwrap();
+        workspace.install().unwrap();
 
-    let mut input = String::new();
-    io::stdin()
-        .read_line(&mut input)
-        .context("Failed to read confirmation")?;
+        let installed_at = |workspace: &Workspace| {
+            let receipt = workspace.current_lockfile().unwrap().unwrap().tool_receipts[0].clone();
+            assert!(receipt.binaries[0].wrapper.is_some());
+            receipt.installed_at
+        };
+        let before = installed_at(&workspace);
 
-    let trimmed = input.trim().to_ascii_lowercase();
-    Ok(trimmed == "y" || trimmed == "yes")
-}
+        assert!(workspace.plan_install().unwrap().is_empty());
+        workspace.update_tools(None).unwrap();
+        assert_eq!(installed_at(&workspace), before);
+    }
 
-fn reset_repository(repo: Repository) -> Result<()> {
-    if let Err(err) = fetch_remote(&repo) {
-        ui::warn(format!("Failed to fetch 'origin': {err}"));
+    #[test]
+    #[serial]
+    fn test_workspace_plan_reset_reinstalls_into_wiped_workspace() {
+        let _temp = setup_test_env();
+        let (workspace, _) = installed_workspace();
+        assert!(workspace.plan_install().unwrap().is_empty());
+
+        let plan = workspace.plan_reset().unwrap();
+        assert_eq!(targets_of(&plan, ActionKind::InstallTool), vec!["good"]);
+        assert_eq!(
+            targets_of(&plan, ActionKind::PruneCache),
+            vec![
+                workspace.cache_dir.display().to_string(),
+                workspace.state_dir.display().to_string(),
+            ]
+        );
+        // The git link comes back as it is, so only the uninstall side shows up.
+        assert!(targets_of(&plan, ActionKind::Link).is_empty());
+        assert!(targets_of(&plan, ActionKind::Unlink).is_empty());
+
+        fs::remove_dir_all(workspace.path(WorkspacePath::Config).join("git")).unwrap();
+        let plan = workspace.plan_reset().unwrap();
+        assert_eq!(
+            targets_of(&plan, ActionKind::Unlink),
+            vec![workspace.config_home.join("git").display().to_string()]
+        );
+        assert!(workspace.path(WorkspacePath::Lockfile).exists());
+        assert!(workspace.path(WorkspacePath::Bin).join("good").exists());
     }
 
-    let target = determine_reset_target(&repo)?;
-    repo.reset(&target, ResetType::Hard, None)
-        .context("Failed to perform hard reset")?;
+    #[rstest]
+    #[case::download_fails("broken", "broken", &[])]
+    #[case::binary_missing_from_archive("nobin", "nobin", &[])]
+    #[case::later_extra_missing("partial", "partial", &["partial.1", "missing.1"])]
+    #[serial]
+    fn test_workspace_install_failure_restores_previous_links(
+        #[case] tool: &str,
+        #[case] bin: &str,
+        #[case] extras: &[&str],
+    ) {
+        let _temp = setup_test_env();
+        let (workspace, archives) = installed_workspace();
+        let lockfile_path = workspace.path(WorkspacePath::Lockfile);
+        let before = fs::read_to_string(&lockfile_path).unwrap();
+        let git_link = workspace.config_home.join("git");
+        let good_link = workspace.path(WorkspacePath::Bin).join("good");
+        let good_source = fs::read_link(&good_link).unwrap();
 
-    let mut checkout = CheckoutBuilder::new();
-    checkout.force().remove_untracked(true).remove_ignored(true);
+        let config_dir = workspace.path(WorkspacePath::Config);
+        fs::create_dir_all(config_dir.join("fish")).unwrap();
+        fs::write(config_dir.join("fish/config.fish"), "new").unwrap();
+        let profile_config = workspace.path(WorkspacePath::ProfileConfig);
+        let mut manifest = fs::read_to_string(&profile_config).unwrap();
+        manifest.push_str(&github_tool(
+            tool,
+            bin,
+            archives.get(&format!("owner/{tool}")).map(Vec::as_slice),
+        ));
+        for extra in extras {
+            manifest.push_str(&format!(
+                "\n[[tools.{tool}.extras]]\nsource = \"{extra}\"\nkind = \"man\"\n"
+            ));
+        }
+        fs::write(&profile_config, manifest).unwrap();
 
-    repo.checkout_head(Some(&mut checkout))
-        .context("Failed to clean working tree")?;
+        let err = workspace.install().unwrap_err();
+        assert!(format!("{err:#}").contains(tool), "{err:#}");
 
-    let _ = repo.cleanup_state();
-    Ok(())
-}
+        assert_eq!(fs::read_to_string(&lockfile_path).unwrap(), before);
+        assert!(git_link.symlink_metadata().unwrap().is_symlink());
+        assert_eq!(fs::read_link(&good_link).unwrap(), good_source);
+        assert!(workspace
+            .config_home
+            .join("fish")
+            .symlink_metadata()
+            .is_err());
+        assert!(workspace
+            .path(WorkspacePath::Bin)
+            .join(bin)
+            .symlink_metadata()
+            .is_err());
+        // Links made before the tool failed are on no receipt, so the tool itself removes them.
+        let share_links = walkdir::WalkDir::new(workspace.path(WorkspacePath::Share))
+            .into_iter()
+            .filter_map(|entry| entry.ok())
+            .filter(|entry| !entry.file_type().is_dir())
+            .count();
+        assert_eq!(share_links, 0);
+        let transaction_dir = workspace.path(WorkspacePath::Transaction);
+        assert!(fs::read_dir(&transaction_dir).map_or(true, |mut dir| dir.next().is_none()));
+    }
 
-fn fetch_remote(repo: &Repository) -> Result<()> {
-    let mut remote = repo
-        .find_remote("origin")
-        .context("Repository has no 'origin' remote")?;
+    #[test]
+    #[serial]
+    fn test_workspace_use_profile_failure_keeps_active_profile() {
+        let _temp = setup_test_env();
+        let (mut workspace, _) = installed_workspace();
+        let git_link = workspace.config_home.join("git");
 
-    remote.fetch::<&str>(&[], None, None)?;
-    Ok(())
-}
+        let work = workspace.profile_path("work");
+        fs::create_dir_all(work.join("config/fish")).unwrap();
+        fs::write(work.join("config/fish/config.fish"), "work").unwrap();
+        fs::write(work.join("dws.toml"), github_tool("broken", "broken", None)).unwrap();
 
-fn determine_reset_target(repo: &Repository) -> Result<Object<'_>> {
-    let head = repo.head().context("Repository has no HEAD reference")?;
+        assert!(workspace.use_profile("work").is_err());
 
-    if head.is_branch() {
-        if let Some(branch) = head.shorthand() {
-            let remote_ref = format!("refs/remotes/origin/{}", branch);
-            if let Ok(obj) = repo.revparse_single(&remote_ref) {
-                return Ok(obj);
-            }
-        }
+        assert_eq!(workspace.active_profile_name(), default_profile_name());
+        assert_eq!(
+            Workspace::new().unwrap().active_profile_name(),
+            default_profile_name()
+        );
+        assert!(git_link.symlink_metadata().unwrap().is_symlink());
+        assert!(workspace.path(WorkspacePath::Bin).join("good").exists());
+        assert!(workspace
+            .config_home
+            .join("fish")
+            .symlink_metadata()
+            .is_err());
     }
 
-    head.peel(ObjectType::Commit)
-        .context("Failed to resolve HEAD commit")
-}
+    #[test]
+    #[serial]
+    fn test_workspace_verify_tools_records_tampering() {
+        let _temp = setup_test_env();
+        let (workspace, _) = installed_workspace();
+        let lockfile_path = workspace.path(WorkspacePath::Lockfile);
+
+        let verified = workspace.verify_tools(None).unwrap();
+        assert_eq!(verified.len(), 1);
+        assert_eq!(verified[0].status, ReceiptStatus::Ok);
+
+        let receipt = Lockfile::load(&lockfile_path).unwrap().tool_receipts[0].clone();
+        let asset = receipt.asset.clone().unwrap();
+        assert!(receipt.binaries[0].sha256.is_some());
+        fs::write(&receipt.binaries[0].source, "evil").unwrap();
+        fs::write(&asset.archive_path, "evil").unwrap();
+
+        let verified = workspace.verify_tools(Some("good")).unwrap();
+        assert_eq!(verified[0].status, ReceiptStatus::ChecksumMismatch);
+        let saved = Lockfile::load(&lockfile_path).unwrap();
+        assert_eq!(
+            saved.tool_receipts[0].status,
+            ReceiptStatus::ChecksumMismatch
+        );
+        // The tampered download is not reused by the reinstall.
+        assert!(!asset.archive_path.exists());
+        assert!(!BlobStore::new(workspace.path(WorkspacePath::Blobs)).contains(&asset.checksum));
 
-#[cfg(test)]
-mod tests {
-    use super::*;
-    use crate::config::default_profile_name;
+        workspace.install().unwrap();
+        let verified = workspace.verify_tools(None).unwrap();
+        assert_eq!(verified[0].status, ReceiptStatus::Ok);
+        assert!(workspace.verify_tools(Some("missing")).is_err());
+    }
 
-    use crate::lockfile::{AssetRecord, BinaryLink, ExtraLink, ToolReceipt};
-    use crate::toolset::InstallerKind;
-    use rstest::rstest;
-    use serial_test::serial;
-    use tempfile::TempDir;
+    #[rstest]
+    #[case::from_cached_archive(false, ActionKind::Extract)]
+    #[case::after_download(true, ActionKind::Download)]
+    #[serial]
+    fn test_workspace_repair_restores_contents_and_links(
+        #[case] drop_archive: bool,
+        #[case] restore: ActionKind,
+    ) {
+        let _temp = setup_test_env();
+        let (workspace, _) = installed_workspace();
+        let receipt = workspace.current_lockfile().unwrap().unwrap().tool_receipts[0].clone();
+        let asset = receipt.asset.clone().unwrap();
+        let link = &receipt.binaries[0].target;
+        fs::remove_file(link).unwrap();
+        fs::remove_dir_all(&asset.extract_dir).unwrap();
+        if drop_archive {
+            fs::remove_file(&asset.archive_path).unwrap();
+            fs::remove_file(
+                BlobStore::new(workspace.path(WorkspacePath::Blobs)).path_for(&asset.checksum),
+            )
+            .unwrap();
+        }
 
-    fn setup_test_env() -> TempDir {
-        let temp = TempDir::new().unwrap();
-        env::set_var("XDG_CONFIG_HOME", temp.path());
-        env::set_var("XDG_STATE_HOME", temp.path().join("state"));
-        env::set_var("XDG_CACHE_HOME", temp.path().join("cache"));
-        env::set_var("HOME", temp.path());
-        temp
+        let planned = workspace.plan_repair().unwrap();
+        assert!(link.symlink_metadata().is_err());
+        let kinds: Vec<_> = planned.actions.iter().map(|action| action.action).collect();
+        assert_eq!(kinds, [restore, ActionKind::Relink]);
+
+        let fixed = workspace.repair().unwrap();
+        assert_eq!(fixed.actions, planned.actions);
+        assert_eq!(fs::read_link(link).unwrap(), receipt.binaries[0].source);
+        assert!(receipt.binaries[0].source.is_file());
+        assert!(workspace.plan_repair().unwrap().is_empty());
     }
 
     #[test]
     #[serial]
-    fn test_workspace_new() {
+    fn test_workspace_uninstall_keeps_held_process_lock() {
         let _temp = setup_test_env();
-        let workspace = Workspace::new().unwrap();
+        let (workspace, _) = installed_workspace();
+        let lock_path = workspace.path(WorkspacePath::ProcessLock);
+        let _lock = workspace.lock(LockMode::Exclusive).unwrap();
 
-        assert!(workspace
-            .path(WorkspacePath::Root)
-            .to_string_lossy()
-            .contains("dws"));
-        assert!(workspace
-            .path(WorkspacePath::Profile)
-            .to_string_lossy()
-            .contains("profiles"));
-        assert_eq!(workspace.active_profile_name(), default_profile_name());
+        workspace.uninstall().unwrap();
+
+        assert!(lock_path.exists());
+        assert!(!workspace.path(WorkspacePath::Lockfile).exists());
+        assert!(!workspace.path(WorkspacePath::Bin).exists());
+        assert!(ProcessLock::acquire(&lock_path, LockMode::Shared, Duration::ZERO).is_err());
     }
 
     #[test]
     #[serial]
-    fn test_workspace_paths() {
+    fn test_workspace_install_conflict_policy_skip() {
         let _temp = setup_test_env();
-        let workspace = Workspace::new().unwrap();
+        let mut workspace = Workspace::new().unwrap();
+        workspace.set_conflict_policy(Some(ConflictPolicy::Skip));
 
-        assert!(workspace
-            .path(WorkspacePath::Profiles)
-            .to_string_lossy()
-            .contains("profiles"));
-        assert!(workspace
-            .path(WorkspacePath::Config)
-            .to_string_lossy()
-            .contains("config"));
-        assert!(workspace
-            .path(WorkspacePath::ProfileConfig)
-            .to_string_lossy()
-            .ends_with("dws.toml"));
-        assert!(workspace
-            .path(WorkspacePath::Share)
-            .to_string_lossy()
-            .contains("share"));
-        assert!(workspace
-            .path(WorkspacePath::Cache)
-            .to_string_lossy()
-            .contains("cache"));
-        assert!(workspace
-            .path(WorkspacePath::Lockfile)
-            .to_string_lossy()
-            .contains("dws.lock"));
-        assert!(workspace
-            .path(WorkspacePath::Bin)
-            .to_string_lossy()
-            .contains("bin"));
+        let config_dir = workspace.path(WorkspacePath::Config);
+        fs::create_dir_all(&config_dir).unwrap();
+        fs::write(config_dir.join("starship.toml"), "profile").unwrap();
+        let target = workspace.config_home.join("starship.toml");
+        fs::write(&target, "local").unwrap();
+
+        workspace.install().unwrap();
+        assert_eq!(fs::read_to_string(&target).unwrap(), "local");
+        let lockfile = Lockfile::load(&workspace.path(WorkspacePath::Lockfile)).unwrap();
+        assert_eq!(lockfile.config_symlinks().count(), 0);
+        assert!(lockfile.backups.is_empty());
     }
 
     #[test]
     #[serial]
-    fn test_workspace_tools() {
+    fn test_workspace_uninstall_restores_backups() {
         let _temp = setup_test_env();
         let workspace = Workspace::new().unwrap();
 
-        let profile_config = workspace.path(WorkspacePath::ProfileConfig);
-        if let Some(parent) = profile_config.parent() {
-            fs::create_dir_all(parent).unwrap();
-        }
-        fs::write(
-            &profile_config,
-            r#"
-[tools.ripgrep]
-installer = "curl"
-project = "BurntSushi/ripgrep"
-"#,
-        )
-        .unwrap();
+        let config_dir = workspace.path(WorkspacePath::Config);
+        fs::create_dir_all(&config_dir).unwrap();
+        fs::write(config_dir.join("starship.toml"), "profile").unwrap();
+        let target = workspace.config_home.join("starship.toml");
+        fs::write(&target, "distro").unwrap();
 
-        let tools = workspace.tools().unwrap();
-        assert_eq!(tools.len(), 1);
-        let (name, entry) = tools.iter().next().unwrap();
-        assert_eq!(name, "ripgrep");
-        assert_eq!(entry.definition.installer, InstallerKind::Curl);
+        workspace.install().unwrap();
+        workspace.uninstall().unwrap();
+        assert_eq!(fs::read_to_string(&target).unwrap(), "distro");
+        assert!(!workspace.path(WorkspacePath::Lockfile).exists());
     }
 
     #[test]
     #[serial]
-    fn test_workspace_install() {
+    fn test_workspace_install_files_link_mode() {
         let _temp = setup_test_env();
+        fs::create_dir_all(env::var("XDG_CONFIG_HOME").unwrap() + "/dws").unwrap();
+        fs::write(
+            PathBuf::from(env::var("XDG_CONFIG_HOME").unwrap()).join("dws/config.toml"),
+            "[dotfiles]\nlink_mode = \"files\"\n",
+        )
+        .unwrap();
         let workspace = Workspace::new().unwrap();
 
-        // Create workspace structure
         let config_dir = workspace.path(WorkspacePath::Config);
-        fs::create_dir_all(&config_dir).unwrap();
-        fs::create_dir_all(config_dir.join("zsh")).unwrap();
-        fs::write(config_dir.join("zsh/.zshrc"), "test").unwrap();
+        fs::create_dir_all(config_dir.join("zsh/functions")).unwrap();
+        fs::write(config_dir.join("zsh/.zshrc"), "rc").unwrap();
+        fs::write(config_dir.join("zsh/functions/mkcd"), "fn").unwrap();
 
-        // Install
         workspace.install().unwrap();
 
-        // Verify lockfile was created
-        assert!(workspace.path(WorkspacePath::Lockfile).exists());
-        assert!(workspace.path(WorkspacePath::Bin).exists());
-        assert!(workspace.path(WorkspacePath::Share).join("man").exists());
-        assert!(workspace
-            .path(WorkspacePath::Share)
-            .join("zsh/site-functions")
-            .exists());
-
-        // Verify lockfile contents
         let lockfile = Lockfile::load(&workspace.path(WorkspacePath::Lockfile)).unwrap();
-        assert_eq!(lockfile.config_symlinks.len(), 1);
+        let targets: Vec<_> = lockfile
+            .config_symlinks()
+            .map(|entry| {
+                entry
+                    .target
+                    .strip_prefix(&workspace.config_home)
+                    .unwrap()
+                    .to_path_buf()
+            })
+            .collect();
+        assert_eq!(
+            targets,
+            vec![
+                PathBuf::from("zsh/.zshrc"),
+                PathBuf::from("zsh/functions/mkcd")
+            ]
+        );
+        assert!(!workspace
+            .config_home
+            .join("zsh")
+            .symlink_metadata()
+            .unwrap()
+            .is_symlink());
     }
 
     #[test]
@@ -1613,6 +3807,8 @@ project = "BurntSushi/ripgrep"
                 link: "rg".to_string(),
                 source: PathBuf::from("/cache/tools/rg/14.0.0/rg"),
                 target: target.clone(),
+                wrapper: None,
+                sha256: None,
             }],
             Vec::new(),
             None,
@@ -2036,7 +4232,6 @@ version = "14.0.0"
             .ensure_profile_template(workspace.active_profile())
             .unwrap();
         let export = workspace.environment_export("bash").unwrap();
-        assert!(!export.defaulted);
         assert_eq!(export.shell, Shell::Bash);
         assert!(export.script.contains("export PATH"));
         drop(temp);
@@ -2044,19 +4239,74 @@ version = "14.0.0"
 
     #[test]
     #[serial]
-    fn test_environment_export_defaults_unknown_shell() {
+    fn test_environment_export_rejects_unknown_shell() {
         let temp = setup_test_env();
         let workspace = Workspace::new().unwrap();
         workspace
             .ensure_profile_template(workspace.active_profile())
             .unwrap();
+        let err = workspace.environment_export("tcsh").unwrap_err();
+        assert!(err.to_string().contains("Unsupported shell: tcsh"));
+        assert!(err.to_string().contains("nu, pwsh, elvish, xonsh"));
+        drop(temp);
+    }
+
+    #[test]
+    #[serial]
+    fn test_environment_export_accepts_shell_aliases() {
+        let temp = setup_test_env();
+        let workspace = Workspace::new().unwrap();
+        workspace
+            .ensure_profile_template(workspace.active_profile())
+            .unwrap();
+        let export = workspace.environment_export("nushell").unwrap();
+        assert_eq!(export.shell, Shell::Nushell);
+        assert!(export.script.contains("$env.PATH"));
         let export = workspace.environment_export("powershell").unwrap();
-        assert!(export.defaulted);
-        assert_eq!(export.shell, Shell::Zsh);
-        assert!(export.script.contains("export PATH"));
+        assert_eq!(export.shell, Shell::PowerShell);
+        assert!(export.script.contains("$env:PATH"));
         drop(temp);
     }
 
+    #[rstest]
+    #[case("nu", ".config/nushell/env.nu", "dws env --shell nu | save --force")]
+    #[case(
+        "pwsh",
+        ".config/powershell/Microsoft.PowerShell_profile.ps1",
+        "Invoke-Expression (& dws env --shell pwsh | Out-String)"
+    )]
+    #[case(
+        "elvish",
+        ".config/elvish/rc.elv",
+        "eval (dws env --shell elvish | slurp)"
+    )]
+    #[case("xonsh", ".xonshrc", "execx($(dws env --shell xonsh))")]
+    #[serial]
+    fn test_setup_writes_rc_integration(
+        #[case] shell: &str,
+        #[case] rc_file: &str,
+        #[case] expected: &str,
+    ) {
+        let temp = setup_test_env();
+        let workspace = Workspace::new().unwrap();
+
+        workspace.setup(shell).unwrap();
+        workspace.setup(shell).unwrap();
+
+        let contents = fs::read_to_string(temp.path().join(rc_file)).unwrap();
+        assert!(contents.contains(expected), "{contents}");
+        assert_eq!(contents.matches("# dws shell integration").count(), 1);
+    }
+
+    #[test]
+    #[serial]
+    fn test_setup_rejects_unknown_shell() {
+        let _temp = setup_test_env();
+        let workspace = Workspace::new().unwrap();
+        let err = workspace.setup("tcsh").unwrap_err();
+        assert!(err.to_string().contains("Unsupported shell: tcsh"));
+    }
+
     #[cfg(unix)]
     #[test]
     fn test_prune_unused_bin_and_cache() {
@@ -2100,6 +4350,8 @@ version = "14.0.0"
                 link: "exa".to_string(),
                 source: source_bin.clone(),
                 target: valid_target.clone(),
+                wrapper: None,
+                sha256: None,
             }],
             Vec::new(),
             None,
@@ -2134,6 +4386,62 @@ version = "14.0.0"
         drop(temp);
     }
 
+    #[test]
+    #[serial]
+    fn test_prune_unused_cache_keeps_project_and_later_receipts() {
+        let temp = setup_test_env();
+        let workspace = Workspace::new().unwrap();
+        let tools_root = workspace.path(WorkspacePath::Cache).join("tools");
+
+        let receipt_for = |lockfile: &mut Lockfile, name: &str, version: &str| {
+            let dir = tools_root.join(name).join(version);
+            fs::create_dir_all(&dir).unwrap();
+            fs::write(dir.join(name), "bin").unwrap();
+            lockfile.record_tool_install(
+                name,
+                "latest",
+                version,
+                "github",
+                vec![BinaryLink {
+                    link: name.to_string(),
+                    source: dir.join(name),
+                    target: temp.path().join("bin").join(name),
+                    wrapper: None,
+                    sha256: None,
+                }],
+                Vec::new(),
+                None,
+            );
+            dir
+        };
+
+        let mut lockfile = Lockfile::new();
+        let jq = receipt_for(&mut lockfile, "jq", "1.7");
+        let rg = receipt_for(&mut lockfile, "rg", "14.0");
+
+        // An older terraform only referenced by a project environment.
+        let mut project_lockfile = Lockfile::new();
+        let terraform = receipt_for(&mut project_lockfile, "terraform", "0.12.31");
+        let project_dir = workspace.path(WorkspacePath::Projects).join("abc");
+        fs::create_dir_all(&project_dir).unwrap();
+        project_lockfile
+            .save(&project_dir.join("dws.lock"))
+            .unwrap();
+
+        let unused = tools_root.join("terraform").join("1.9.0");
+        fs::create_dir_all(&unused).unwrap();
+
+        workspace.prune_unused_cache(&lockfile).unwrap();
+
+        assert!(jq.exists());
+        assert!(
+            rg.exists(),
+            "receipts after the first must keep their cache"
+        );
+        assert!(terraform.exists(), "project receipts must keep their cache");
+        assert!(!unused.exists());
+    }
+
     #[test]
     fn receipt_missing_artifacts_detects_missing_asset() {
         let temp = TempDir::new().unwrap();
@@ -2154,6 +4462,8 @@ version = "14.0.0"
                 pattern_index: Some(0),
                 pattern: Some("mock".to_string()),
             }),
+            env: BTreeMap::new(),
+            status: ReceiptStatus::Ok,
         };
 
         assert!(receipt_missing_artifacts(&receipt));
@@ -2173,8 +4483,11 @@ version = "14.0.0"
                 kind: "completion".to_string(),
                 source: temp.path().join("cache/tools/mock/v1.0.0/completion/_mock"),
                 target: temp.path().join("state/share/zsh/site-functions/_mock"),
+                sha256: None,
             }],
             asset: None,
+            env: BTreeMap::new(),
+            status: ReceiptStatus::Ok,
         };
 
         assert!(receipt_missing_artifacts(&receipt));
@@ -1,6 +1,7 @@
 use assert_cmd::Command;
 use git2::{Repository, Signature};
 use predicates::prelude::*;
+use rstest::rstest;
 use serial_test::serial;
 use std::fs;
 use std::path::Path;
@@ -441,6 +442,129 @@ pattern = "mock"
         .stderr(predicate::str::contains("Asset archive missing"));
 }
 
+#[test]
+fn test_verify_detects_modified_binary() {
+    let temp = TempDir::new().unwrap();
+    let config_home = temp.path();
+    let state_home = temp.path().join("state");
+    let cache_home = temp.path().join("cache");
+    let profile_dir = config_home.join("dws/profiles/default");
+    fs::create_dir_all(&profile_dir).unwrap();
+    fs::write(
+        profile_dir.join("dws.toml"),
+        r#"
+[tools.mock]
+installer = "github"
+project = "owner/mock"
+version = "v1.0.0"
+asset_filter = ["mock"]
+checksum = "sha256:0000000000000000000000000000000000000000000000000000000000000000"
+        "#,
+    )
+    .unwrap();
+
+    let bin_source = cache_home.join("dws/tools/mock/v1.0.0/contents/mock");
+    fs::create_dir_all(bin_source.parent().unwrap()).unwrap();
+    fs::write(&bin_source, "tampered").unwrap();
+    fs::create_dir_all(state_home.join("dws")).unwrap();
+    let lockfile_path = state_home.join("dws/dws.lock");
+    fs::write(
+        &lockfile_path,
+        format!(
+            r#"
+version = 3
+
+[metadata]
+installed_at = "2025-01-01T00:00:00Z"
+
+[[tool_receipts]]
+name = "mock"
+manifest_version = "latest"
+resolved_version = "v1.0.0"
+installer_kind = "github"
+installed_at = "2025-01-01T00:00:00Z"
+status = "ok"
+
+[[tool_receipts.binaries]]
+link = "mock"
+source = "{}"
+target = "{}"
+sha256 = "sha256:0000000000000000000000000000000000000000000000000000000000000000"
+            "#,
+            bin_source.display(),
+            state_home.join("bin/mock").display()
+        ),
+    )
+    .unwrap();
+
+    let dws = |arg: &str| {
+        let mut command = Command::cargo_bin("dws").unwrap();
+        command
+            .env("XDG_CONFIG_HOME", config_home)
+            .env("XDG_STATE_HOME", &state_home)
+            .env("XDG_CACHE_HOME", &cache_home)
+            .env("HOME", config_home)
+            .arg(arg);
+        command
+    };
+
+    dws("verify")
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("mock v1.0.0: checksum_mismatch"))
+        .stderr(predicate::str::contains("1 tool(s) failed verification"));
+    assert!(fs::read_to_string(&lockfile_path)
+        .unwrap()
+        .contains(r#"status = "checksum_mismatch""#));
+
+    dws("status")
+        .assert()
+        .success()
+        .stderr(predicate::str::contains("integrity: checksum_mismatch"));
+}
+
+#[test]
+#[serial]
+fn test_repair_recreates_missing_config_link() {
+    let temp = TempDir::new().unwrap();
+    let dws = |args: &[&str]| {
+        let mut command = Command::cargo_bin("dws").unwrap();
+        command
+            .env("XDG_CONFIG_HOME", temp.path())
+            .env("XDG_STATE_HOME", temp.path().join("state"))
+            .env("XDG_CACHE_HOME", temp.path().join("cache"))
+            .env("HOME", temp.path())
+            .env("SHELL", "/bin/zsh")
+            .args(args);
+        command
+    };
+    dws(&["init"]).assert().success();
+    let link = temp.path().join("zsh");
+    fs::remove_file(&link).unwrap();
+
+    dws(&["--dry-run", "repair"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("Relink"))
+        .stdout(predicate::str::contains("nothing was modified"));
+    assert!(link.symlink_metadata().is_err());
+
+    dws(&["repair"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("Relinked"))
+        .stdout(predicate::str::contains("Repaired 1 issue(s)"));
+    assert_eq!(
+        fs::read_link(&link).unwrap(),
+        temp.path().join("dws/profiles/default/config/zsh")
+    );
+
+    dws(&["repair"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("nothing to fix"));
+}
+
 fn copy_dir_all(src: &Path, dst: &Path) {
     fs::create_dir_all(dst).unwrap();
     for entry in fs::read_dir(src).unwrap() {
@@ -587,3 +711,578 @@ fn test_reset_confirmation_can_abort() {
         .success()
         .stdout(predicate::str::contains("Reset cancelled."));
 }
+
+/// Return from the test when `shell` is not installed. CI installs every shell these tests
+/// cover, so there a missing shell fails the test instead of skipping it.
+macro_rules! require_shell {
+    ($shell:expr) => {
+        if !shell_available($shell) {
+            assert!(
+                std::env::var_os("CI").is_none(),
+                "{} is not installed, but CI is expected to provide it",
+                $shell
+            );
+            eprintln!("skipping: {} is not installed", $shell);
+            return;
+        }
+    };
+}
+
+fn shell_available(shell: &str) -> bool {
+    std::process::Command::new(shell)
+        .arg("-c")
+        .arg("exit 0")
+        .output()
+        .map(|output| output.status.success())
+        .unwrap_or(false)
+}
+
+/// Initialise a workspace in `temp` and return the `dws env` output for `shell`.
+fn env_script(temp: &TempDir, shell: &str) -> String {
+    Command::cargo_bin("dws")
+        .unwrap()
+        .env("XDG_CONFIG_HOME", temp.path())
+        .env("XDG_STATE_HOME", temp.path().join("state"))
+        .env("XDG_CACHE_HOME", temp.path().join("cache"))
+        .env("HOME", temp.path())
+        .args(["init", "--shell", shell])
+        .assert()
+        .success();
+
+    let output = Command::cargo_bin("dws")
+        .unwrap()
+        .env("XDG_CONFIG_HOME", temp.path())
+        .env("XDG_STATE_HOME", temp.path().join("state"))
+        .env("XDG_CACHE_HOME", temp.path().join("cache"))
+        .env("HOME", temp.path())
+        .args(["env", "--shell", shell])
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    String::from_utf8(output.stdout).unwrap()
+}
+
+#[test]
+#[serial]
+fn test_env_bash_loads_completions() {
+    require_shell!("bash");
+
+    let temp = TempDir::new().unwrap();
+    let script = env_script(&temp, "bash");
+    let completions = temp
+        .path()
+        .join("state/dws/share/bash-completion/completions");
+    fs::create_dir_all(&completions).unwrap();
+    fs::write(
+        completions.join("mytool"),
+        "complete -W 'alpha beta' mytool\n",
+    )
+    .unwrap();
+
+    let output = std::process::Command::new("bash")
+        .args(["--norc", "--noprofile", "-c"])
+        .arg(format!(
+            "{script}\ncomplete -p mytool\necho \"$XDG_DATA_DIRS\""
+        ))
+        .env("HOME", temp.path())
+        .output()
+        .unwrap();
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(output.status.success(), "{stdout}");
+    assert!(
+        stdout.contains("complete -W 'alpha beta' mytool"),
+        "{stdout}"
+    );
+    assert!(stdout.contains("state/dws/share:"), "{stdout}");
+}
+
+#[test]
+#[serial]
+fn test_env_fish_registers_completion_path() {
+    require_shell!("fish");
+
+    let temp = TempDir::new().unwrap();
+    let script = env_script(&temp, "fish");
+    let completions = temp
+        .path()
+        .join("state/dws/share/fish/vendor_completions.d");
+    fs::create_dir_all(&completions).unwrap();
+    fs::write(
+        completions.join("mytool.fish"),
+        "complete -c mytool -f -a 'alpha beta'\n",
+    )
+    .unwrap();
+
+    let output = std::process::Command::new("fish")
+        .args(["--no-config", "-c"])
+        .arg(format!("{script}\ncomplete -C 'mytool '"))
+        .env("HOME", temp.path())
+        .output()
+        .unwrap();
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(output.status.success(), "{stdout}");
+    assert!(stdout.contains("alpha"), "{stdout}");
+}
+
+#[test]
+#[serial]
+fn test_env_zsh_extends_fpath() {
+    require_shell!("zsh");
+
+    let temp = TempDir::new().unwrap();
+    let script = env_script(&temp, "zsh");
+
+    let output = std::process::Command::new("zsh")
+        .args(["-f", "-c"])
+        .arg(format!("{script}\nprint -l $fpath"))
+        .env("HOME", temp.path())
+        .output()
+        .unwrap();
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(output.status.success(), "{stdout}");
+    assert!(
+        stdout.contains("state/dws/share/zsh/site-functions"),
+        "{stdout}"
+    );
+}
+
+#[rstest]
+#[case("nu", &["-n", "-c"], "$env.PATH | str join ':'")]
+#[case("pwsh", &["-NoProfile", "-Command"], "$env:PATH")]
+#[case("elvish", &["-norc", "-c"], "echo $E:PATH")]
+#[case("xonsh", &["--no-rc", "-c"], "print($PATH)")]
+#[serial]
+fn test_env_prepends_bin_path(#[case] shell: &str, #[case] args: &[&str], #[case] probe: &str) {
+    require_shell!(shell);
+
+    let temp = TempDir::new().unwrap();
+    let script = env_script(&temp, shell);
+
+    let output = std::process::Command::new(shell)
+        .args(args)
+        .arg(format!("{script}\n{probe}"))
+        .env("HOME", temp.path())
+        .output()
+        .unwrap();
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(output.status.success(), "{stdout}");
+    assert!(stdout.contains("state/dws/bin"), "{stdout}");
+}
+
+#[test]
+#[serial]
+fn test_exec_runs_command_in_environment() {
+    let temp = TempDir::new().unwrap();
+    env_script(&temp, "bash");
+    let bashrc = fs::read_to_string(temp.path().join(".bashrc")).unwrap();
+
+    Command::cargo_bin("dws")
+        .unwrap()
+        .env("XDG_CONFIG_HOME", temp.path())
+        .env("XDG_STATE_HOME", temp.path().join("state"))
+        .env("XDG_CACHE_HOME", temp.path().join("cache"))
+        .env("HOME", temp.path())
+        .args(["exec", "--", "sh", "-c", "echo \"$PATH\"; exit 3"])
+        .assert()
+        .code(3)
+        .stdout(predicate::str::contains("state/dws/bin:"));
+
+    assert_eq!(
+        fs::read_to_string(temp.path().join(".bashrc")).unwrap(),
+        bashrc
+    );
+}
+
+#[test]
+#[serial]
+fn test_exec_requires_command() {
+    let temp = TempDir::new().unwrap();
+    Command::cargo_bin("dws")
+        .unwrap()
+        .env("XDG_CONFIG_HOME", temp.path())
+        .env("HOME", temp.path())
+        .arg("exec")
+        .assert()
+        .failure();
+}
+
+#[test]
+#[serial]
+fn test_shell_starts_shell_with_environment() {
+    let temp = TempDir::new().unwrap();
+    env_script(&temp, "bash");
+
+    Command::cargo_bin("dws")
+        .unwrap()
+        .env("XDG_CONFIG_HOME", temp.path())
+        .env("XDG_STATE_HOME", temp.path().join("state"))
+        .env("XDG_CACHE_HOME", temp.path().join("cache"))
+        .env("HOME", temp.path())
+        .env("SHELL", "/bin/sh")
+        .arg("shell")
+        .write_stdin("echo \"$MANPATH\"\n")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("state/dws/share/man:"));
+}
+
+fn dws_in(temp: &TempDir) -> Command {
+    let mut cmd = Command::cargo_bin("dws").unwrap();
+    cmd.env("XDG_CONFIG_HOME", temp.path())
+        .env("XDG_STATE_HOME", temp.path().join("state"))
+        .env("XDG_CACHE_HOME", temp.path().join("cache"))
+        .env("HOME", temp.path())
+        .env_remove("DWS_PROJECT")
+        .env_remove("DWS_PROJECT_BIN");
+    cmd
+}
+
+#[test]
+#[serial]
+fn test_project_environment_requires_allow() {
+    let temp = TempDir::new().unwrap();
+    env_script(&temp, "bash");
+    let project = temp.path().join("legacy");
+    fs::create_dir_all(project.join("src")).unwrap();
+    fs::write(project.join(".dws.toml"), "[tools]\n").unwrap();
+
+    dws_in(&temp)
+        .current_dir(project.join("src"))
+        .args(["hook-env", "--shell", "bash"])
+        .assert()
+        .success()
+        .stdout(predicate::str::is_empty())
+        .stderr(predicate::str::contains("is not allowed; run `dws allow`"));
+
+    dws_in(&temp).arg("allow").arg(&project).assert().success();
+
+    let output = dws_in(&temp)
+        .current_dir(project.join("src"))
+        .env("PATH", "/usr/bin:/bin")
+        .args(["hook-env", "--shell", "bash"])
+        .output()
+        .unwrap();
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert!(stdout.contains("export PATH=\""), "{stdout}");
+    assert!(stdout.contains("state/dws/projects/"), "{stdout}");
+    assert!(stdout.contains("/bin:/usr/bin:/bin\""), "{stdout}");
+    assert!(stdout.contains("export DWS_PROJECT="), "{stdout}");
+
+    fs::write(project.join(".dws.toml"), "[tools]\n# edited\n").unwrap();
+    dws_in(&temp)
+        .current_dir(&project)
+        .args(["hook-env", "--shell", "bash"])
+        .assert()
+        .success()
+        .stderr(predicate::str::contains("changed since it was allowed"));
+
+    dws_in(&temp)
+        .args(["allow", "--revoke"])
+        .arg(&project)
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("Revoked"));
+}
+
+#[test]
+#[serial]
+fn test_project_hook_activates_and_restores_path_in_bash() {
+    require_shell!("bash");
+
+    let temp = TempDir::new().unwrap();
+    let script = env_script(&temp, "bash");
+    let project = temp.path().join("app");
+    fs::create_dir_all(&project).unwrap();
+    fs::write(project.join(".dws.toml"), "").unwrap();
+    dws_in(&temp).arg("allow").arg(&project).assert().success();
+
+    let dws = assert_cmd::cargo::cargo_bin("dws");
+    let path = format!("{}:/usr/bin:/bin", dws.parent().unwrap().display());
+    let output = std::process::Command::new("bash")
+        .args(["--norc", "--noprofile", "-c"])
+        .arg(format!(
+            "{script}\ncd '{}' && _dws_hook && echo \"in:$PATH\"\ncd / && _dws_hook && echo \"out:$PATH\"",
+            project.display()
+        ))
+        .env("PATH", &path)
+        .env("HOME", temp.path())
+        .env("XDG_CONFIG_HOME", temp.path())
+        .env("XDG_STATE_HOME", temp.path().join("state"))
+        .env("XDG_CACHE_HOME", temp.path().join("cache"))
+        .env_remove("DWS_PROJECT_BIN")
+        .output()
+        .unwrap();
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(output.status.success(), "{stdout}{stderr}");
+
+    let inside = stdout.lines().find(|line| line.starts_with("in:")).unwrap();
+    let outside = stdout
+        .lines()
+        .find(|line| line.starts_with("out:"))
+        .unwrap();
+    assert!(inside.contains("state/dws/projects/"), "{stdout}");
+    assert!(!outside.contains("state/dws/projects/"), "{stdout}");
+}
+
+#[test]
+#[serial]
+fn test_init_backs_up_existing_dotfiles_and_restores_them() {
+    let temp = TempDir::new().unwrap();
+    let fish = temp.path().join("fish");
+    fs::create_dir_all(&fish).unwrap();
+    fs::write(fish.join("config.fish"), "# distro default").unwrap();
+
+    dws_in(&temp)
+        .args(["init", "-s", "bash"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("Backed up"));
+    assert!(fs::symlink_metadata(&fish).unwrap().is_symlink());
+
+    dws_in(&temp)
+        .arg("restore-backups")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("Restored 1 file(s)"));
+    assert_eq!(
+        fs::read_to_string(fish.join("config.fish")).unwrap(),
+        "# distro default"
+    );
+}
+
+#[test]
+#[serial]
+fn test_init_on_conflict_skip_keeps_existing_dotfiles() {
+    let temp = TempDir::new().unwrap();
+    let fish = temp.path().join("fish");
+    fs::create_dir_all(&fish).unwrap();
+
+    dws_in(&temp)
+        .args(["init", "-s", "bash", "--on-conflict", "skip"])
+        .assert()
+        .success()
+        .stderr(predicate::str::contains("not managed by dws"));
+    assert!(fs::symlink_metadata(&fish).unwrap().is_dir());
+}
+
+#[test]
+#[serial]
+fn test_status_reports_edited_rendered_template() {
+    let temp = TempDir::new().unwrap();
+    env_script(&temp, "bash");
+    let config = temp.path().join("dws/profiles/default/config");
+    fs::create_dir_all(config.join("foot")).unwrap();
+    fs::write(
+        config.join("foot/foot.ini.tmpl"),
+        "font=monospace:size={% if \"linux\" in platform_tags %}11{% else %}13{% endif %}\n",
+    )
+    .unwrap();
+    dws_in(&temp).arg("reset").arg("--force").assert().success();
+
+    let rendered = temp.path().join("foot/foot.ini");
+    assert!(fs::read_to_string(&rendered)
+        .unwrap()
+        .starts_with("font=monospace:size="));
+    dws_in(&temp)
+        .arg("status")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("healthy"));
+
+    fs::write(&rendered, "font=monospace:size=20\n").unwrap();
+    dws_in(&temp)
+        .arg("status")
+        .assert()
+        .success()
+        .stderr(predicate::str::contains("edited since last sync"));
+}
+
+#[cfg(target_os = "linux")]
+#[test]
+#[serial]
+fn test_status_shows_overlay_source() {
+    let temp = TempDir::new().unwrap();
+    env_script(&temp, "bash");
+    let overlay = temp.path().join("dws/profiles/default/config@linux/bash");
+    fs::create_dir_all(&overlay).unwrap();
+    fs::write(overlay.join(".bashrc"), "# linux bashrc\n").unwrap();
+    dws_in(&temp).arg("reset").arg("--force").assert().success();
+
+    assert_eq!(
+        fs::read_link(temp.path().join("bash/.bashrc")).unwrap(),
+        overlay.join(".bashrc")
+    );
+    dws_in(&temp)
+        .arg("status")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("from config@linux"));
+}
+
+#[test]
+#[serial]
+fn test_secret_add_edit_and_install() {
+    let temp = TempDir::new().unwrap();
+    env_script(&temp, "bash");
+    let plaintext = temp.path().join("token.txt");
+    fs::write(&plaintext, "token=abc\n").unwrap();
+
+    dws_in(&temp)
+        .args(["secret", "add"])
+        .arg(&plaintext)
+        .args(["--as", "config/gh/hosts.yml"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("Generated"));
+    let secret = temp
+        .path()
+        .join("dws/profiles/default/config/gh/hosts.yml.age");
+    assert!(!fs::read(&secret).unwrap().starts_with(b"token"));
+
+    dws_in(&temp)
+        .args(["secret", "edit", "config/gh/hosts.yml"])
+        .env("VISUAL", "echo token=def >")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("Encrypted"));
+
+    dws_in(&temp).arg("reset").arg("--force").assert().success();
+    let installed = temp.path().join("gh/hosts.yml");
+    assert!(!fs::symlink_metadata(&installed).unwrap().is_symlink());
+    assert_eq!(fs::read_to_string(&installed).unwrap(), "token=def\n");
+
+    fs::write(&installed, "token=local\n").unwrap();
+    dws_in(&temp)
+        .arg("status")
+        .assert()
+        .success()
+        .stderr(predicate::str::contains("dws secret edit"));
+}
+
+#[test]
+#[serial]
+fn test_adopt_links_existing_config_from_profile() {
+    let temp = TempDir::new().unwrap();
+    env_script(&temp, "bash");
+    let starship = temp.path().join("starship.toml");
+    fs::write(&starship, "add_newline = false\n").unwrap();
+
+    dws_in(&temp)
+        .arg("adopt")
+        .arg(&starship)
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("Adopted"));
+    let source = temp
+        .path()
+        .join("dws/profiles/default/config/starship.toml");
+    assert_eq!(fs::read_link(&starship).unwrap(), source);
+    assert_eq!(
+        fs::read_to_string(&source).unwrap(),
+        "add_newline = false\n"
+    );
+
+    dws_in(&temp)
+        .arg("adopt")
+        .arg(&starship)
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("already linked"));
+    dws_in(&temp)
+        .arg("status")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("healthy"));
+}
+
+#[test]
+#[serial]
+fn test_dry_run_use_reports_plan_without_switching() {
+    let temp = TempDir::new().unwrap();
+    env_script(&temp, "bash");
+    let work = temp.path().join("dws/profiles/work/config/helix");
+    fs::create_dir_all(&work).unwrap();
+
+    dws_in(&temp)
+        .args(["--dry-run", "use", "work"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("Activate work"))
+        .stdout(predicate::str::contains("Unlink"))
+        .stdout(predicate::str::contains("nothing was modified"));
+    assert!(fs::symlink_metadata(temp.path().join("bash"))
+        .unwrap()
+        .is_symlink());
+    assert!(!temp.path().join("helix").exists());
+    assert!(fs::read_to_string(temp.path().join("dws/config.toml"))
+        .unwrap()
+        .contains("default"));
+
+    dws_in(&temp)
+        .args(["--dry-run", "adopt", "bash"])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("--dry-run is only supported"));
+}
+
+#[test]
+#[serial]
+fn test_plan_outputs_json() {
+    let temp = TempDir::new().unwrap();
+    env_script(&temp, "bash");
+    dws_in(&temp)
+        .args(["plan", "--json"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("\"actions\": []"));
+
+    let config = temp.path().join("dws/profiles/default/config");
+    fs::create_dir_all(config.join("helix")).unwrap();
+    let output = dws_in(&temp).args(["plan", "--json"]).output().unwrap();
+    assert!(output.status.success());
+    let plan: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(plan["command"], "install");
+    assert_eq!(plan["actions"][0]["action"], "link");
+    assert_eq!(
+        plan["actions"][0]["target"],
+        temp.path().join("helix").display().to_string()
+    );
+}
+
+#[test]
+#[serial]
+fn test_process_lock_blocks_concurrent_commands() {
+    let temp = TempDir::new().unwrap();
+    env_script(&temp, "bash");
+    let lock_path = temp.path().join("state/dws/process.lock");
+
+    let held = dws::ProcessLock::acquire(
+        &lock_path,
+        dws::LockMode::Exclusive,
+        std::time::Duration::ZERO,
+    )
+    .unwrap();
+    dws_in(&temp)
+        .args(["--lock-timeout", "0", "update"])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains(format!(
+            "another dws process (pid {}) is running",
+            std::process::id()
+        )));
+    drop(held);
+
+    let _shared =
+        dws::ProcessLock::acquire(&lock_path, dws::LockMode::Shared, std::time::Duration::ZERO)
+            .unwrap();
+    dws_in(&temp)
+        .args(["--lock-timeout", "0", "env", "--shell", "bash"])
+        .assert()
+        .success();
+    dws_in(&temp)
+        .args(["--lock-timeout", "0", "restore-backups"])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("another dws process is running"));
+}